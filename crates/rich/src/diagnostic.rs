@@ -0,0 +1,560 @@
+//! Compiler-style diagnostics pointing into source files.
+//!
+//! A [`Diagnostic`] holds a message and labels attached to [`Location`]s. It is
+//! rendered using a [`SourceMap`] as plain text, text with ANSI colors, or
+//! JSON:
+//!
+//! ```text
+//! error: port out of range
+//!  --> local.toml:2:8
+//!   |
+//! 2 | port = 99999
+//!   |        ^^^^^ overridden here
+//!   |
+//!  ::: base.toml:1:8
+//!   |
+//! 1 | port = 8080
+//!   |        ---- defined here
+//!   |
+//!   = note: ports must be below 65536
+//! ```
+//!
+//! Labels can be created from anything implementing [`ToLocation`], such as
+//! `Rich<T, Location>` values or [`MetaRef`] handles to a [`MetaStore`]
+//! holding locations.
+//!
+//! [`MetaStore`]: crate::MetaStore
+
+use crate::{ColumnMode, LineCol, Location, MetaRef, Rich, SourceFile, SourceMap};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::{self, Write};
+
+/// Retrieve the source location of a value or of its metadata.
+pub trait ToLocation {
+  fn to_location(&self) -> Option<Location>;
+}
+
+impl ToLocation for Location {
+  fn to_location(&self) -> Option<Location> {
+    Some(*self)
+  }
+}
+
+impl<T: ToLocation> ToLocation for Option<T> {
+  fn to_location(&self) -> Option<Location> {
+    self.as_ref()?.to_location()
+  }
+}
+
+impl<T: ToLocation + ?Sized> ToLocation for &T {
+  fn to_location(&self) -> Option<Location> {
+    (**self).to_location()
+  }
+}
+
+impl<T, M: ToLocation> ToLocation for Rich<T, M> {
+  fn to_location(&self) -> Option<Location> {
+    self.meta.to_location()
+  }
+}
+
+/// The location is either the data of the store, or its `Location` extension.
+impl<D: Any> ToLocation for MetaRef<'_, D> {
+  fn to_location(&self) -> Option<Location> {
+    let data = self
+      .get()
+      .and_then(|data| (data as &dyn Any).downcast_ref::<Location>());
+    data.or_else(|| self.extension::<Location>()).copied()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+  Error,
+  Warning,
+  Note,
+  Help,
+}
+
+impl Severity {
+  pub const fn as_str(self) -> &'static str {
+    match self {
+      Self::Error => "error",
+      Self::Warning => "warning",
+      Self::Note => "note",
+      Self::Help => "help",
+    }
+  }
+
+  /// ANSI SGR parameters for this severity.
+  const fn ansi_color(self) -> &'static str {
+    match self {
+      Self::Error => "1;31",
+      Self::Warning => "1;33",
+      Self::Note => "1;32",
+      Self::Help => "1;36",
+    }
+  }
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+/// Message attached to a location of a [`Diagnostic`].
+///
+/// Primary labels point to the cause of the diagnostic and are underlined with
+/// `^`, secondary labels provide context and are underlined with `-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+  pub location: Location,
+  pub message: String,
+  pub primary: bool,
+}
+
+impl Label {
+  pub fn primary(location: Location, message: impl Into<String>) -> Self {
+    Self {
+      location,
+      message: message.into(),
+      primary: true,
+    }
+  }
+
+  pub fn secondary(location: Location, message: impl Into<String>) -> Self {
+    Self {
+      location,
+      message: message.into(),
+      primary: false,
+    }
+  }
+}
+
+/// Diagnostic message with labels pointing into source files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  pub message: String,
+  pub labels: Vec<Label>,
+  pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+  pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+    Self {
+      severity,
+      message: message.into(),
+      labels: Vec::new(),
+      notes: Vec::new(),
+    }
+  }
+
+  pub fn error(message: impl Into<String>) -> Self {
+    Self::new(Severity::Error, message)
+  }
+
+  pub fn warning(message: impl Into<String>) -> Self {
+    Self::new(Severity::Warning, message)
+  }
+
+  pub fn with_label(mut self, label: Label) -> Self {
+    self.labels.push(label);
+    self
+  }
+
+  /// Add a primary label at the location of `at`.
+  ///
+  /// If `at` has no location, the message is added as a note instead.
+  pub fn with_primary(self, at: impl ToLocation, message: impl Into<String>) -> Self {
+    match at.to_location() {
+      Some(location) => self.with_label(Label::primary(location, message)),
+      None => self.with_note(message),
+    }
+  }
+
+  /// Add a secondary label at the location of `at`.
+  ///
+  /// If `at` has no location, the message is added as a note instead.
+  pub fn with_secondary(self, at: impl ToLocation, message: impl Into<String>) -> Self {
+    match at.to_location() {
+      Some(location) => self.with_label(Label::secondary(location, message)),
+      None => self.with_note(message),
+    }
+  }
+
+  /// Add a secondary label pointing to the definition of a value.
+  pub fn defined_here(self, at: impl ToLocation) -> Self {
+    self.with_secondary(at, "defined here")
+  }
+
+  /// Add a secondary label pointing to a value overriding a previous
+  /// definition.
+  pub fn overridden_here(self, at: impl ToLocation) -> Self {
+    self.with_secondary(at, "overridden here")
+  }
+
+  pub fn with_note(mut self, note: impl Into<String>) -> Self {
+    self.notes.push(note.into());
+    self
+  }
+
+  /// Render this diagnostic using the default [`Renderer`] for `format`.
+  pub fn render(&self, sources: &SourceMap, format: Format) -> String {
+    Renderer::new(format).render(self, sources)
+  }
+}
+
+/// Output format of a [`Renderer`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+  /// Plain text, with source snippets.
+  #[default]
+  Text,
+  /// Text with ANSI color codes, for terminals.
+  Ansi,
+  /// JSON object, for tooling.
+  ///
+  /// Lines and columns are zero-based, as in [`LineCol`].
+  Json,
+}
+
+/// Configuration for the rendering of diagnostics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Renderer {
+  pub format: Format,
+  /// Unit used for the columns printed in locations.
+  pub column_mode: ColumnMode,
+}
+
+impl Renderer {
+  pub const fn new(format: Format) -> Self {
+    Self {
+      format,
+      column_mode: ColumnMode::Utf8,
+    }
+  }
+
+  pub fn render(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+    let mut out = String::new();
+    self
+      .write(&mut out, diagnostic, sources)
+      .expect("writing to a `String` never fails");
+    out
+  }
+
+  pub fn write(&self, out: &mut dyn Write, diagnostic: &Diagnostic, sources: &SourceMap) -> fmt::Result {
+    match self.format {
+      Format::Text => self.write_text(out, diagnostic, sources, false),
+      Format::Ansi => self.write_text(out, diagnostic, sources, true),
+      Format::Json => self.write_json(out, diagnostic, sources),
+    }
+  }
+
+  fn write_text(&self, out: &mut dyn Write, diagnostic: &Diagnostic, sources: &SourceMap, color: bool) -> fmt::Result {
+    let paint = Paint(color);
+    let severity = diagnostic.severity;
+    paint.write(out, severity.ansi_color(), severity.as_str())?;
+    paint.write(out, "1", &[": ", diagnostic.message.as_str()].concat())?;
+    out.write_char('\n')?;
+
+    let mut notes: Vec<&str> = Vec::new();
+    let mut resolved: Vec<Resolved<'_>> = Vec::new();
+    for label in &diagnostic.labels {
+      match Resolved::new(label, sources, self.column_mode) {
+        Some(label) => resolved.push(label),
+        None => notes.push(&label.message),
+      }
+    }
+    notes.extend(diagnostic.notes.iter().map(String::as_str));
+
+    let width = resolved
+      .iter()
+      .map(|label| digits(label.start.line + 1))
+      .max()
+      .unwrap_or(1);
+    let pad = Repeat(' ', width);
+    let gutter = |out: &mut dyn Write| -> fmt::Result {
+      write!(out, "{pad} ")?;
+      paint.write(out, GUTTER, "|")
+    };
+
+    // Group the labels by source, starting with the source of the first
+    // primary label.
+    let mut groups: Vec<Vec<&Resolved<'_>>> = Vec::new();
+    let first = resolved.iter().position(|label| label.label.primary).unwrap_or(0);
+    for label in resolved[first..].iter().chain(&resolved[..first]) {
+      match groups
+        .iter_mut()
+        .find(|group| group[0].label.location.source == label.label.location.source)
+      {
+        Some(group) => group.push(label),
+        None => groups.push(alloc::vec![label]),
+      }
+    }
+
+    for (index, group) in groups.iter().enumerate() {
+      if index > 0 {
+        gutter(out)?;
+        out.write_char('\n')?;
+      }
+      let head = group[0];
+      write!(out, "{pad}")?;
+      paint.write(out, GUTTER, if index == 0 { "-->" } else { ":::" })?;
+      writeln!(out, " {}:{}", head.file.name(), head.start)?;
+      gutter(out)?;
+      out.write_char('\n')?;
+
+      let mut lines: BTreeMap<usize, Vec<&Resolved<'_>>> = BTreeMap::new();
+      for label in group {
+        lines.entry(label.start.line).or_default().push(label);
+      }
+      for (line, mut labels) in lines {
+        labels.sort_by_key(|label| label.label.location.span.start);
+        let text = labels[0].line_text;
+        paint.write(out, GUTTER, &alloc::format!("{:>width$} |", line + 1))?;
+        if !text.is_empty() {
+          write!(out, " {text}")?;
+        }
+        out.write_char('\n')?;
+        for label in labels {
+          gutter(out)?;
+          out.write_char(' ')?;
+          for c in text[..label.columns.0].chars() {
+            out.write_char(if c == '\t' { '\t' } else { ' ' })?;
+          }
+          let (mark, color) = if label.label.primary {
+            ('^', severity.ansi_color())
+          } else {
+            ('-', SECONDARY)
+          };
+          let marks = text[label.columns.0..label.columns.1].chars().count().max(1);
+          let mut underline = alloc::format!("{}", Repeat(mark, marks));
+          if !label.label.message.is_empty() {
+            underline.push(' ');
+            underline.push_str(&label.label.message);
+          }
+          paint.write(out, color, &underline)?;
+          out.write_char('\n')?;
+        }
+      }
+    }
+
+    if !groups.is_empty() && !notes.is_empty() {
+      gutter(out)?;
+      out.write_char('\n')?;
+    }
+    for note in notes {
+      write!(out, "{pad} ")?;
+      paint.write(out, GUTTER, "=")?;
+      paint.write(out, "1", " note")?;
+      writeln!(out, ": {note}")?;
+    }
+    Ok(())
+  }
+
+  fn write_json(&self, out: &mut dyn Write, diagnostic: &Diagnostic, sources: &SourceMap) -> fmt::Result {
+    out.write_str("{\"severity\":")?;
+    write_json_str(out, diagnostic.severity.as_str())?;
+    out.write_str(",\"message\":")?;
+    write_json_str(out, &diagnostic.message)?;
+    out.write_str(",\"labels\":[")?;
+    for (index, label) in diagnostic.labels.iter().enumerate() {
+      if index > 0 {
+        out.write_char(',')?;
+      }
+      let location = label.location;
+      let file = sources.get(location.source);
+      write!(out, "{{\"primary\":{},\"message\":", label.primary)?;
+      write_json_str(out, &label.message)?;
+      out.write_str(",\"file\":")?;
+      match file {
+        Some(file) => write_json_str(out, file.name())?,
+        None => out.write_str("null")?,
+      }
+      for (key, offset) in [("start", location.span.start), ("end", location.span.end)] {
+        write!(out, ",\"{key}\":{{\"offset\":{offset}")?;
+        if let Some(position) = file.and_then(|file| file.line_col(offset, self.column_mode)) {
+          write!(out, ",\"line\":{},\"column\":{}", position.line, position.column)?;
+        }
+        out.write_char('}')?;
+      }
+      out.write_char('}')?;
+    }
+    out.write_str("],\"notes\":[")?;
+    for (index, note) in diagnostic.notes.iter().enumerate() {
+      if index > 0 {
+        out.write_char(',')?;
+      }
+      write_json_str(out, note)?;
+    }
+    out.write_str("]}")
+  }
+}
+
+/// ANSI SGR parameters for the gutter.
+const GUTTER: &str = "1;34";
+/// ANSI SGR parameters for secondary labels.
+const SECONDARY: &str = "1;34";
+
+/// Write text, with ANSI colors if enabled.
+#[derive(Clone, Copy)]
+struct Paint(bool);
+
+impl Paint {
+  fn write(self, out: &mut dyn Write, sgr: &str, text: &str) -> fmt::Result {
+    if self.0 {
+      write!(out, "\x1b[{sgr}m{text}\x1b[0m")
+    } else {
+      out.write_str(text)
+    }
+  }
+}
+
+/// Display a character `n` times.
+struct Repeat(char, usize);
+
+impl fmt::Display for Repeat {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    (0..self.1).try_for_each(|_| f.write_char(self.0))
+  }
+}
+
+/// Label with its location resolved in a source map.
+struct Resolved<'a> {
+  label: &'a Label,
+  file: &'a SourceFile,
+  start: LineCol,
+  /// Text of the first line of the label, without its line terminator.
+  line_text: &'a str,
+  /// Byte range of the label within `line_text`.
+  columns: (usize, usize),
+}
+
+impl<'a> Resolved<'a> {
+  fn new(label: &'a Label, sources: &'a SourceMap, column_mode: ColumnMode) -> Option<Self> {
+    let file = sources.get(label.location.source)?;
+    let span = label.location.span;
+    let start = file.line_col(span.start, column_mode)?;
+    let line = file.line_span(start.line)?;
+    let line_text = file.slice(line)?;
+    let line_text = line_text.strip_suffix('\r').unwrap_or(line_text);
+    let start_column = (span.start - line.start).min(line_text.len());
+    let end_column = span.end.saturating_sub(line.start).clamp(start_column, line_text.len());
+    // Ensure the label covers whole characters, even with invalid spans.
+    if !line_text.is_char_boundary(end_column) {
+      return None;
+    }
+    Some(Self {
+      label,
+      file,
+      start,
+      line_text,
+      columns: (start_column, end_column),
+    })
+  }
+}
+
+fn digits(mut value: usize) -> usize {
+  let mut digits = 1;
+  while value >= 10 {
+    value /= 10;
+    digits += 1;
+  }
+  digits
+}
+
+fn write_json_str(out: &mut dyn Write, value: &str) -> fmt::Result {
+  out.write_char('"')?;
+  for c in value.chars() {
+    match c {
+      '"' => out.write_str("\\\"")?,
+      '\\' => out.write_str("\\\\")?,
+      '\n' => out.write_str("\\n")?,
+      '\r' => out.write_str("\\r")?,
+      '\t' => out.write_str("\\t")?,
+      c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+      c => out.write_char(c)?,
+    }
+  }
+  out.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{ByteSpan, MetaStore};
+
+  fn override_diagnostic() -> (SourceMap, Diagnostic) {
+    let mut sources = SourceMap::new();
+    let base = sources.add("base.toml", "port = 8080\n");
+    let local = sources.add("local.toml", "# local\nport = 99999\n");
+    let mut store = MetaStore::new();
+    let base_port = store.insert(Location::new(base, ByteSpan::new(7, 11)));
+    let local_port = Rich::new(99999u32, Location::new(local, ByteSpan::new(15, 20)));
+
+    let diagnostic = Diagnostic::error("port out of range")
+      .with_primary(local_port, "overridden here")
+      .defined_here(store.meta_ref(base_port))
+      .with_note("ports must be below 65536");
+    (sources, diagnostic)
+  }
+
+  #[test]
+  fn render_text() {
+    let (sources, diagnostic) = override_diagnostic();
+    let expected = "\
+error: port out of range
+ --> local.toml:2:8
+  |
+2 | port = 99999
+  |        ^^^^^ overridden here
+  |
+ ::: base.toml:1:8
+  |
+1 | port = 8080
+  |        ---- defined here
+  |
+  = note: ports must be below 65536
+";
+    assert_eq!(diagnostic.render(&sources, Format::Text), expected);
+  }
+
+  #[test]
+  fn render_ansi() {
+    let (sources, diagnostic) = override_diagnostic();
+    let actual = diagnostic.render(&sources, Format::Ansi);
+    assert!(actual.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: port out of range\x1b[0m\n"));
+    assert!(actual.contains("\x1b[1;31m^^^^^ overridden here\x1b[0m"));
+    assert!(actual.contains("\x1b[1;34m---- defined here\x1b[0m"));
+  }
+
+  #[test]
+  fn render_json() {
+    let (sources, diagnostic) = override_diagnostic();
+    let expected = concat!(
+      r#"{"severity":"error","message":"port out of range","labels":["#,
+      r#"{"primary":true,"message":"overridden here","file":"local.toml","#,
+      r#""start":{"offset":15,"line":1,"column":7},"end":{"offset":20,"line":1,"column":12}},"#,
+      r#"{"primary":false,"message":"defined here","file":"base.toml","#,
+      r#""start":{"offset":7,"line":0,"column":7},"end":{"offset":11,"line":0,"column":11}}"#,
+      r#"],"notes":["ports must be below 65536"]}"#,
+    );
+    assert_eq!(diagnostic.render(&sources, Format::Json), expected);
+  }
+
+  #[test]
+  fn render_without_location() {
+    let sources = SourceMap::new();
+    let diagnostic = Diagnostic::warning("unused key").with_primary(None::<Location>, "\"debug\" is ignored");
+
+    assert_eq!(
+      diagnostic.render(&sources, Format::Text),
+      "warning: unused key\n  = note: \"debug\" is ignored\n"
+    );
+  }
+}
@@ -1,148 +1,169 @@
-use crate::{Meta, MetaId, StructuralProjection, WrappedMeta};
-
 pub mod value {
-  use super::*;
-  use crate::{BoolView, Rich};
-  use std::borrow::Borrow;
+  use crate::view::{View, ViewMeta};
+  use crate::{EntryMeta, MergeMeta, MergeMetaError, Rich, RichEntry, SplitMeta, TreeMeta, TreeMetaProjection};
+  use serde_json1::value::{Number, Value};
   use std::collections::BTreeMap;
-  use std::hash::Hash;
-
-  /// Metadata for [serde_json::Value](serde_json1::value::Value).
-  #[derive(Debug)]
-  pub enum ValueMeta {
+  use std::string::String;
+  use std::vec::Vec;
+
+  /// External metadata for the components of a
+  /// [serde_json::Value](serde_json1::value::Value).
+  ///
+  /// Arrays and objects hold the metadata of their elements, and objects the
+  /// metadata of their keys. The other variants have no sub-component.
+  #[derive(Debug, Clone, PartialEq, Eq)]
+  pub enum ValueMeta<M> {
     /// Metadata for [serde_json::Value::Null](serde_json1::value::Value::Null).
-    Null(WrappedMeta<Meta<()>>),
+    Null,
     /// Metadata for [serde_json::Value::Bool](serde_json1::value::Value::Bool).
-    Bool(WrappedMeta<Meta<bool>>),
+    Bool,
     /// Metadata for [serde_json::Value::Number](serde_json1::value::Value::Number).
-    Number(MetaId),
+    Number,
     /// Metadata for [serde_json::Value::String](serde_json1::value::Value::String).
-    String(WrappedMeta<Meta<String>>),
+    String,
     /// Metadata for [serde_json::Value::Array](serde_json1::value::Value::Array).
-    Array(Vec<WrappedMeta<Meta<::serde_json1::value::Value>>>),
+    Array(Vec<TreeMeta<M, ValueMeta<M>>>),
     /// Metadata for [serde_json::Value::Object](serde_json1::value::Value::Object).
-    Object(BTreeMap<String, WrappedMeta<Meta<::serde_json1::value::Value>>>),
+    Object(BTreeMap<String, ObjectEntryMeta<M>>),
   }
 
-  impl StructuralProjection for ::serde_json1::value::Value {
-    type Meta = Option<ValueMeta>;
+  /// External metadata for an entry of an object: the metadata trees of its
+  /// key and of its value.
+  pub type ObjectEntryMeta<M> = EntryMeta<TreeMeta<M, ()>, TreeMeta<M, ValueMeta<M>>>;
+
+  impl<M> ValueMeta<M> {
+    /// Name of the variant, as used by [`MergeMetaError::VariantMismatch`].
+    const fn variant_name(&self) -> &'static str {
+      match self {
+        Self::Null => "Null",
+        Self::Bool => "Bool",
+        Self::Number => "Number",
+        Self::String => "String",
+        Self::Array(_) => "Array",
+        Self::Object(_) => "Object",
+      }
+    }
   }
 
-  pub struct ValueView<'rich>(Rich<&'rich ::serde_json1::value::Value, &'rich WrappedMeta<Option<ValueMeta>>>);
-
-  impl<'rich> ValueView<'rich> {
-    pub fn new(rich: Rich<&'rich ::serde_json1::value::Value, &'rich WrappedMeta<Option<ValueMeta>>>) -> Self {
-      Self(rich)
-    }
+  /// [serde_json::Value](serde_json1::value::Value) using internal metadata
+  /// representation: array elements and object entries hold their metadata.
+  #[derive(Debug, Clone, PartialEq)]
+  pub enum RichValue<M> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Rich<RichValue<M>, M>>),
+    Object(BTreeMap<String, RichEntry<Rich<RichValue<M>, M>, M>>),
+  }
 
-    pub fn value(&self) -> &'rich ::serde_json1::Value {
-      &self.0.value
+  /// Name of the variant of `value`, as used by
+  /// [`MergeMetaError::VariantMismatch`].
+  const fn variant_name(value: &Value) -> &'static str {
+    match value {
+      Value::Null => "Null",
+      Value::Bool(_) => "Bool",
+      Value::Number(_) => "Number",
+      Value::String(_) => "String",
+      Value::Array(_) => "Array",
+      Value::Object(_) => "Object",
     }
+  }
 
-    pub fn meta(&self) -> MetaId {
-      self.0.meta.id
-    }
+  impl<M> TreeMetaProjection<M> for Value {
+    type TreeMeta = ValueMeta<M>;
+  }
 
-    pub fn visit(&self) -> ValueVisit<'_> {
-      let rich = &self.0;
-      match &rich.value {
-        ::serde_json1::value::Value::Bool(value) => {
-          const DEFAULT: &WrappedMeta<Meta<bool>> = &WrappedMeta::new((), MetaId::from_usize(0));
-          let meta = match rich.meta.nested.as_ref() {
-            Some(ValueMeta::Bool(meta)) => meta,
-            _ => DEFAULT,
-          };
-          ValueVisit::Bool(BoolView::new(Rich::new(value, meta)))
-        }
-        ::serde_json1::value::Value::Array(value) => {
-          const DEFAULT: &Vec<WrappedMeta<Meta<serde_json1::Value>>> = &Vec::new();
-          let meta = match rich.meta.nested.as_ref() {
-            Some(ValueMeta::Array(meta)) => meta,
-            _ => DEFAULT,
-          };
-          ValueVisit::Array(ArrayView::new(Rich::new(value, meta)))
+  impl<M> SplitMeta<M> for RichValue<M> {
+    type Value = Value;
+
+    fn split_meta(self) -> Rich<Value, ValueMeta<M>> {
+      match self {
+        Self::Null => Rich::new(Value::Null, ValueMeta::Null),
+        Self::Bool(value) => Rich::new(Value::Bool(value), ValueMeta::Bool),
+        Self::Number(value) => Rich::new(Value::Number(value), ValueMeta::Number),
+        Self::String(value) => Rich::new(Value::String(value), ValueMeta::String),
+        Self::Array(values) => {
+          let values = values.split_meta();
+          Rich::new(Value::Array(values.value), ValueMeta::Array(values.meta))
         }
-        ::serde_json1::value::Value::Object(value) => {
-          const DEFAULT: &BTreeMap<String, WrappedMeta<Meta<::serde_json1::value::Value>>> = &BTreeMap::new();
-          let meta = match rich.meta.nested.as_ref() {
-            Some(ValueMeta::Object(meta)) => meta,
-            _ => DEFAULT,
-          };
-          ValueVisit::Object(ObjectView::new(Rich::new(value, meta)))
+        Self::Object(entries) => {
+          let entries = entries.split_meta();
+          Rich::new(
+            Value::Object(entries.value.into_iter().collect()),
+            ValueMeta::Object(entries.meta),
+          )
         }
-        _ => todo!(),
       }
     }
   }
 
-  pub enum ValueVisit<'rich> {
-    Bool(BoolView<'rich>),
-    Array(ArrayView<'rich>),
-    Object(ObjectView<'rich>),
-  }
-
-  #[derive(Debug, Clone, Copy)]
-  pub struct ArrayView<'rich>(
-    Rich<&'rich Vec<::serde_json1::value::Value>, &'rich Vec<WrappedMeta<Meta<::serde_json1::value::Value>>>>,
-  );
-
-  impl<'rich> ArrayView<'rich> {
-    pub fn new(
-      rich: Rich<&'rich Vec<::serde_json1::value::Value>, &'rich Vec<WrappedMeta<Meta<::serde_json1::value::Value>>>>,
-    ) -> Self {
-      Self(rich)
-    }
-
-    pub fn get(&self, index: usize) -> Option<ValueView<'rich>> {
-      const DEFAULT: &WrappedMeta<Option<ValueMeta>> = &WrappedMeta::new(None, MetaId::from_usize(0));
-      let rich = &self.0;
-      let value = rich.value.get(index)?;
-      let meta = match rich.meta.get(index) {
-        Some(meta) => meta,
-        None => DEFAULT,
-      };
-      Some(ValueView::new(Rich::new(value, meta)))
+  impl<M> MergeMeta<M> for Value {
+    type Rich = RichValue<M>;
+
+    fn merge_meta(self, meta: ValueMeta<M>) -> Result<RichValue<M>, MergeMetaError> {
+      match (self, meta) {
+        (Self::Null, ValueMeta::Null) => Ok(RichValue::Null),
+        (Self::Bool(value), ValueMeta::Bool) => Ok(RichValue::Bool(value)),
+        (Self::Number(value), ValueMeta::Number) => Ok(RichValue::Number(value)),
+        (Self::String(value), ValueMeta::String) => Ok(RichValue::String(value)),
+        (Self::Array(values), ValueMeta::Array(metas)) => values.merge_meta(metas).map(RichValue::Array),
+        (Self::Object(entries), ValueMeta::Object(metas)) => entries
+          .into_iter()
+          .collect::<BTreeMap<String, Value>>()
+          .merge_meta(metas)
+          .map(RichValue::Object),
+        (value, meta) => Err(MergeMetaError::VariantMismatch {
+          value: variant_name(&value),
+          meta: meta.variant_name(),
+        }),
+      }
     }
   }
 
-  #[derive(Debug, Clone, Copy)]
-  pub struct ObjectView<'rich>(
-    Rich<
-      &'rich ::serde_json1::value::Map<String, ::serde_json1::value::Value>,
-      &'rich BTreeMap<String, WrappedMeta<Meta<::serde_json1::value::Value>>>,
-    >,
-  );
+  impl<M> ViewMeta<M> for Value {
+    type View<'a>
+      = View<'a, Self, M>
+    where
+      Self: 'a,
+      M: 'a;
+  }
 
-  impl<'rich> ObjectView<'rich> {
-    pub fn new(
-      rich: Rich<
-        &'rich ::serde_json1::value::Map<String, ::serde_json1::value::Value>,
-        &'rich BTreeMap<String, WrappedMeta<Meta<::serde_json1::value::Value>>>,
-      >,
-    ) -> Self {
-      Self(rich)
+  impl<'a, M> View<'a, Value, M> {
+    /// View the element at `index`, or `None` if the value is not an array or
+    /// is too short.
+    pub fn index(&self, index: usize) -> Option<Self> {
+      let Value::Array(values) = self.value() else {
+        return None;
+      };
+      let meta = match self.nested() {
+        Some(ValueMeta::Array(metas)) => metas.get(index),
+        _ => None,
+      };
+      Some(View::new(values.get(index)?, meta))
     }
 
-    pub fn value(&self) -> &::serde_json1::value::Map<String, ::serde_json1::value::Value> {
-      self.0.value
+    /// View the value for `key`, or `None` if the value is not an object or
+    /// the key is missing.
+    pub fn get(&self, key: &str) -> Option<Self> {
+      let Value::Object(entries) = self.value() else {
+        return None;
+      };
+      let meta = match self.nested() {
+        Some(ValueMeta::Object(metas)) => metas.get(key).map(|meta| &meta.value),
+        _ => None,
+      };
+      Some(View::new(entries.get(key)?, meta))
     }
 
-    #[inline]
-    pub fn get<Q>(&self, key: &Q) -> Option<ValueView<'rich>>
-    where
-      String: Borrow<Q>,
-      Q: ?Sized + Ord + Eq + Hash,
-    {
-      const DEFAULT: &WrappedMeta<Option<ValueMeta>> = &WrappedMeta::new(None, MetaId::from_usize(0));
-      let rich = &self.0;
-      let value = rich.value.get(key)?;
-      let meta = match rich.meta.get(key) {
-        Some(meta) => meta,
-        None => DEFAULT,
-      };
-      Some(ValueView::new(Rich::new(value, meta)))
+    /// Metadata for the key `key` of an object.
+    pub fn key_meta(&self, key: &str) -> Option<&'a M> {
+      match self.nested()? {
+        ValueMeta::Object(metas) => Some(metas.get(key)?.key.meta()),
+        _ => None,
+      }
     }
   }
 }
 
-pub use value::ValueMeta;
+pub use value::{ObjectEntryMeta, RichValue, ValueMeta};
@@ -1,10 +1,44 @@
 //! This module defines the [`Data`] struct. It is an internal implementation
 //! that should not be relied on by external code.
+//!
+//! This crate is `no_std`. The `alloc` feature enables the implementations for
+//! `String` and `Vec`, and the `std` feature (enabled by default) the ones
+//! requiring the standard library.
 
-use std::marker::PhantomData;
-use styp::{ConstProjector, Projector, StructuralProjection};
+#![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::marker::PhantomData;
+use styp::{ConstProjector, Deep, DeepProjector, StructuralProjection};
+
+#[cfg(feature = "alloc")]
+pub mod diagnostic;
 pub mod ecosystem;
+pub mod partial;
+#[cfg(target_has_atomic = "ptr")]
+pub mod scope;
+pub mod source;
+#[cfg(feature = "alloc")]
+pub mod store;
+pub mod view;
+pub mod view_mut;
+
+pub use partial::AlignMeta;
+#[cfg(target_has_atomic = "ptr")]
+pub use scope::{MetaScope, MetaScopeError, ScopeId, ScopeRemap, ScopedMetaId};
+pub use source::{ByteSpan, ColumnMode, LineCol, Location, SourceId};
+#[cfg(feature = "alloc")]
+pub use source::{SourceFile, SourceMap};
+#[cfg(feature = "alloc")]
+pub use store::{MetaRef, MetaStore};
+pub use view::{View, ViewMeta};
+pub use view_mut::{Provenance, RichMut, RuntimeMeta, SyntheticMeta};
 
 /// Placeholder type for [`Data`] values where there is no metadata associated
 /// with the value.
@@ -22,16 +56,20 @@ pub struct EmptyMeta;
 ///
 /// Internally, this is represented using a `usize`. The value is unique within
 /// a scope that must be documented by functions issuing these metadata ids.
+/// Use [`MetaScope`] to issue ids tagged with their scope, which can be checked
+/// when ids from multiple sources are mixed.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct MetaId(usize);
 
 impl MetaId {
-  /// Wrap the provided `usize` into a `MetaId`.
+  /// Retrieve the inner `usize` from this `MetaId`.
   pub const fn into_usize(self) -> usize {
     self.0
   }
 
-  /// Retrieve the inner `usize` from this `MetaId`.
+  /// Wrap the provided `usize` into a `MetaId`.
+  ///
+  /// The caller is responsible for the uniqueness of the id within its scope.
   pub const fn from_usize(value: usize) -> Self {
     Self(value)
   }
@@ -177,11 +215,18 @@ impl<M> MetaProjection<M> for u32 {
   type Meta = <Self as StructuralProjection<ConstProjector<M>>>::Projection;
 }
 
+#[cfg(feature = "alloc")]
 impl<M> MetaProjection<M> for String {
   // type Meta = ()
   type Meta = <Self as StructuralProjection<ConstProjector<M>>>::Projection;
 }
 
+impl<M, T, const N: usize> MetaProjection<M> for [T; N] {
+  // type Meta = [M; N]
+  type Meta = <Self as StructuralProjection<ConstProjector<M>>>::Projection;
+}
+
+#[cfg(feature = "alloc")]
 impl<M, T> MetaProjection<M> for Vec<T> {
   // type Meta = Vec<M>
   type Meta = <Self as StructuralProjection<ConstProjector<M>>>::Projection;
@@ -194,7 +239,7 @@ impl<M, T> MetaProjection<M> for Vec<T> {
 /// - The second field has different semantics:
 ///   - In [`Rich`], it's the value being described
 ///   - In [`TreeMeta`], it's more metadata: for sub-components of the main value.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TreeMeta<MainMeta, NestedMeta> {
   /// Metadata id for this level of the hierarchy
   meta: MainMeta,
@@ -207,25 +252,113 @@ impl<MainMeta, NestedMeta> TreeMeta<MainMeta, NestedMeta> {
   pub const fn new(meta: MainMeta, nested: NestedMeta) -> Self {
     Self { meta, nested }
   }
+
+  /// Metadata for the main value.
+  pub const fn meta(&self) -> &MainMeta {
+    &self.meta
+  }
+
+  /// Metadata for the sub-components of the main value.
+  pub const fn nested(&self) -> &NestedMeta {
+    &self.nested
+  }
 }
 
-/// Projector recursively converting each component type into a `TreeMeta` for holding metadata of type `M`.
-pub struct TreeMetaProjector<M>(PhantomData<M>, core::convert::Infallible);
+/// External metadata for a map entry, with separate metadata trees for the
+/// key and the value.
+///
+/// This is the [`styp::Entry`] used by the structural projection of maps, so
+/// the `TreeMeta` of a map matches its projection with [`TreeMetaProjector`].
+pub type EntryMeta<KeyMeta, ValueMeta> = styp::Entry<KeyMeta, ValueMeta>;
 
-impl<TyInput, M> Projector<TyInput> for TreeMetaProjector<M>
-where
-  TyInput: StructuralProjection<TreeMetaProjector<M>>,
-{
-  type Output = TreeMeta<M, <TyInput as StructuralProjection<TreeMetaProjector<M>>>::Projection>;
+/// Map entry using internal metadata representation: the value of a map
+/// holding metadata for its keys.
+///
+/// Map keys must be plain data so they can be used for lookups, so their
+/// metadata is stored alongside the value of the entry. Only the metadata for
+/// the key itself is kept: splitting uses the default nested metadata for the
+/// key, and merging drops it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RichEntry<V, M> {
+  /// Metadata for the key of the entry
+  pub key_meta: M,
+  /// Value of the entry
+  pub value: V,
+}
+
+impl<V, M> RichEntry<V, M> {
+  /// Create a [`RichEntry`] value, by attaching key metadata to a map value.
+  pub const fn new(key_meta: M, value: V) -> Self {
+    Self { key_meta, value }
+  }
+}
+
+/// Marker selecting the metadata attached to the discriminant of an enum, in
+/// addition to the metadata of the fields of its active variant.
+///
+/// See [`WithDiscriminant`] and [`WithoutDiscriminant`].
+pub trait DiscriminantMeta<M> {
+  /// Type of the metadata for the discriminant.
+  type Meta;
+}
+
+/// Attach metadata of type `M` to the discriminant of an enum.
+///
+/// This is useful when the variant is selected explicitly in the input, such
+/// as with a `kind = "oauth"` tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WithDiscriminant {}
+
+impl<M> DiscriminantMeta<M> for WithDiscriminant {
+  type Meta = M;
+}
+
+/// Do not attach metadata to the discriminant of an enum: only the fields of
+/// its active variant carry metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WithoutDiscriminant {}
+
+impl<M> DiscriminantMeta<M> for WithoutDiscriminant {
+  type Meta = ();
+}
+
+/// External metadata for an enum value.
+///
+/// `variant` is an enum with the same variants as the value, where each field
+/// holds the metadata of the corresponding field of the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EnumMeta<DiscriminantMeta, VariantMeta> {
+  /// Metadata for the discriminant
+  pub discriminant: DiscriminantMeta,
+  /// Metadata for the fields of the active variant
+  pub variant: VariantMeta,
+}
+
+impl<DiscriminantMeta, VariantMeta> EnumMeta<DiscriminantMeta, VariantMeta> {
+  /// Create an [`EnumMeta`] value, by pairing metadata for the discriminant and for the variant fields.
+  pub const fn new(discriminant: DiscriminantMeta, variant: VariantMeta) -> Self {
+    Self { discriminant, variant }
+  }
+}
+
+/// Deep projector wrapping each level of the recursion into a `TreeMeta` for
+/// holding metadata of type `M`.
+pub struct TreeMetaNode<M>(PhantomData<M>, core::convert::Infallible);
+
+impl<TyInput, TyNested, M> DeepProjector<TyInput, TyNested> for TreeMetaNode<M> {
+  type Output = TreeMeta<M, TyNested>;
 }
 
+/// Projector recursively converting each component type into a `TreeMeta` for holding metadata of type `M`.
+pub type TreeMetaProjector<M> = Deep<TreeMetaNode<M>>;
+
 /// Trait marking types supporting structural projection into a recursive
 /// `TreeMeta` container for metadata of type `M` for each subcomponent.
 ///
-/// This is equivalent to `StructuralProjection<TreeMetaProjector<M>>`. For types
-/// implementing `StructuralProjection` too, it is recommended to have
-/// `Meta` equal to `<Self as StructuralProjection<TreeMetaProjector<M>>>::Projection`.
-///
+/// For the types provided by this crate, `TreeMeta` is the same type as
+/// `<Self as StructuralProjection<TreeMetaProjector<M>>>::Projection` when the
+/// component types are provided by this crate too. Types deriving `MetaType`
+/// use their generated metadata struct instead of their structure type.
 // TODO: rename to `MetaProjection`
 pub trait TreeMetaProjection<M> {
   /// Projection result: type holding recursive nested metadata.
@@ -236,26 +369,58 @@ pub trait TreeMetaProjection<M> {
   type TreeMeta;
 }
 
-impl<M> TreeMetaProjection<M> for () {
-  // type TreeMeta = ()
-  type TreeMeta = <Self as StructuralProjection<TreeMetaProjector<M>>>::Projection;
+/// Unlike `Vec`, arrays guarantee that the metadata has the same length as the
+/// value.
+impl<M, T, const N: usize> TreeMetaProjection<M> for [T; N]
+where
+  T: TreeMetaProjection<M>,
+{
+  type TreeMeta = [TreeMeta<M, T::TreeMeta>; N];
 }
 
-impl<M> TreeMetaProjection<M> for bool {
-  // type TreeMeta = ()
-  type TreeMeta = <Self as StructuralProjection<TreeMetaProjector<M>>>::Projection;
+#[cfg(feature = "alloc")]
+impl<M, T> TreeMetaProjection<M> for Vec<T>
+where
+  T: TreeMetaProjection<M>,
+{
+  type TreeMeta = Vec<TreeMeta<M, T::TreeMeta>>;
 }
 
-impl<M> TreeMetaProjection<M> for u32 {
-  // type TreeMeta = ()
-  type TreeMeta = <Self as StructuralProjection<TreeMetaProjector<M>>>::Projection;
+impl<M, T> TreeMetaProjection<M> for Option<T>
+where
+  T: TreeMetaProjection<M>,
+{
+  type TreeMeta = Option<TreeMeta<M, T::TreeMeta>>;
 }
 
-impl<M, T> TreeMetaProjection<M> for Vec<T>
+#[cfg(feature = "alloc")]
+impl<M, T> TreeMetaProjection<M> for Box<T>
 where
   T: TreeMetaProjection<M>,
 {
-  type TreeMeta = Vec<TreeMeta<M, T::TreeMeta>>;
+  type TreeMeta = Box<TreeMeta<M, T::TreeMeta>>;
+}
+
+/// Maps keep their keys to index the metadata: each entry holds separate
+/// metadata trees for its key and its value.
+#[cfg(feature = "std")]
+impl<M, K, V, S> TreeMetaProjection<M> for std::collections::HashMap<K, V, S>
+where
+  K: TreeMetaProjection<M>,
+  V: TreeMetaProjection<M>,
+{
+  type TreeMeta = std::collections::HashMap<K, EntryMeta<TreeMeta<M, K::TreeMeta>, TreeMeta<M, V::TreeMeta>>, S>;
+}
+
+/// Maps keep their keys to index the metadata: each entry holds separate
+/// metadata trees for its key and its value.
+#[cfg(feature = "alloc")]
+impl<M, K, V> TreeMetaProjection<M> for alloc::collections::BTreeMap<K, V>
+where
+  K: TreeMetaProjection<M>,
+  V: TreeMetaProjection<M>,
+{
+  type TreeMeta = alloc::collections::BTreeMap<K, EntryMeta<TreeMeta<M, K::TreeMeta>, TreeMeta<M, V::TreeMeta>>>;
 }
 
 /// Convert a rich value using (potentially nested) internal metadata
@@ -264,8 +429,8 @@ where
 /// The shape of the external metadata must be the same as the primary
 /// value, except the each child component should store metadata for the
 /// corresponding sub-component in the primary value. In other words, the
-/// metadata type must be a structural projection of the primary alue through
-/// the `TreeMetaProjector` operation.
+/// metadata type must be the `TreeMeta` projection of the primary value, see
+/// [`TreeMetaProjection`].
 ///
 /// For convenience, this trait can also be implemented on regular values that
 /// don't contain any metadata. In this case, the semantics should be to produce
@@ -305,174 +470,1558 @@ where
   }
 }
 
-impl<M> SplitMeta<M> for bool {
-  type Value = Self;
+/// Arrays of rich values are split element-wise, each element keeping its own
+/// metadata.
+impl<T, M, const N: usize> SplitMeta<M> for [Rich<T, M>; N]
+where
+  T: SplitMeta<M>,
+{
+  type Value = [T::Value; N];
 
-  fn split_meta(self) -> Rich<Self::Value, ()> {
-    Rich::new(self, ())
+  fn split_meta(self) -> Rich<Self::Value, <Self::Value as TreeMetaProjection<M>>::TreeMeta> {
+    let mut meta = [const { None }; N];
+    let mut index = 0;
+    let value = self.map(|item| {
+      let item = item.deep_split_meta();
+      meta[index] = Some(item.meta);
+      index += 1;
+      item.value
+    });
+    let meta = meta.map(|item| item.expect("all elements are split"));
+    Rich::new(value, meta)
   }
 }
 
-impl<M> SplitMeta<M> for u32 {
-  type Value = Self;
+#[cfg(feature = "alloc")]
+impl<T, M> SplitMeta<M> for Vec<Rich<T, M>>
+where
+  T: SplitMeta<M>,
+{
+  type Value = Vec<T::Value>;
 
-  fn split_meta(self) -> Rich<Self::Value, ()> {
-    Rich::new(self, ())
+  fn split_meta(self) -> Rich<Self::Value, <Self::Value as TreeMetaProjection<M>>::TreeMeta> {
+    let (value, meta) = self
+      .into_iter()
+      .map(|item| {
+        let item = item.deep_split_meta();
+        (item.value, item.meta)
+      })
+      .unzip();
+    Rich::new(value, meta)
   }
 }
 
-// impl<M> SplitMeta<M> for String {
-//   type Value = Self;
-//
-//   fn split_meta(self) -> Rich<Self::Value, ()> {
-//     Rich::new(self, ())
-//   }
-// }
-//
-// pub trait IsA<T> {}
-//
-// impl<T> IsA<T> for T {}
-//
-// pub trait MergeMeta<TyMeta>
-// where
-//   Self: StructuralProjection<ConstProjector<MetaId>>,
-//   Self::Rich: SplitMeta<Value=Self>,
-// {
-//   type Rich;
-//
-//   fn merge_meta(self, meta: TyMeta) -> Self::Rich;
-// }
-//
-impl<T, M> Rich<T, M>
+impl<T, M> SplitMeta<M> for Option<Rich<T, M>>
 where
   T: SplitMeta<M>,
 {
-  /// Convert a rich holding a `T` with internal metadata into an external
-  /// metadata representation with pure data and pure metadata.
-  // #[expect(
-  //   clippy::type_complexity,
-  //   reason = "keeping the signature self-contained is valuable"
-  // )]
-  pub fn deep_split_meta(self) -> Rich<T::Value, TreeMeta<M, <T::Value as TreeMetaProjection<M>>::TreeMeta>> {
-    let value_and_nested_meta: Rich<T::Value, _> = self.value.split_meta();
-    Rich::new(
-      value_and_nested_meta.value,
-      TreeMeta::new(self.meta, value_and_nested_meta.meta),
-    )
+  type Value = Option<T::Value>;
+
+  fn split_meta(self) -> Rich<Self::Value, <Self::Value as TreeMetaProjection<M>>::TreeMeta> {
+    match self {
+      Some(item) => {
+        let item = item.deep_split_meta();
+        Rich::new(Some(item.value), Some(item.meta))
+      }
+      None => Rich::new(None, None),
+    }
   }
 }
 
-// impl<T, M> SplitMeta<M> for Rich<T, M>
-// where
-//   T: StructuralProjection<TreeMetaProjector<M>, Projection = ()>, // <Self::Value as StructuralProjection<TreeMetaProjector<M>>>::Projection>
-// {
-//   type Value = T;
-//
-//   fn split_meta(self) -> Rich<T, ()> {
-//     Rich::new(self.value, ())
-//   }
-// }
-
-// // /// Represents a metadata node in a structured hierarchy
-// // #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-// // pub struct WrappedMeta<N> {
-// //   id: MetaId,
-// //   nested: N,
-// // }
-// //
-// // impl<N> WrappedMeta<N> {
-// //   /// Create a new [WrappedMeta].
-// //   pub const fn new(nested: N, id: MetaId) -> Self {
-// //     Self { nested, id }
-// //   }
-// // }
-// //
-// // #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-// // pub struct BoolView<'rich>(Rich<&'rich bool, &'rich WrappedMeta<Meta<bool, MetaId>>>);
-// //
-// // impl<'rich> BoolView<'rich> {
-// //   pub fn new(rich: Rich<&'rich bool, &'rich WrappedMeta<Meta<bool, MetaId>>>) -> Self {
-// //     Self(rich)
-// //   }
-// // }
-//
-#[cfg(test)]
-mod tests {
-  use super::*;
+#[cfg(feature = "alloc")]
+impl<T, M> SplitMeta<M> for Box<Rich<T, M>>
+where
+  T: SplitMeta<M>,
+{
+  type Value = Box<T::Value>;
 
-  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-  struct Mascot {
-    is_crab: bool,
-    price: u32,
+  fn split_meta(self) -> Rich<Self::Value, <Self::Value as TreeMetaProjection<M>>::TreeMeta> {
+    let item = (*self).deep_split_meta();
+    Rich::new(Box::new(item.value), Box::new(item.meta))
   }
+}
 
-  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-  struct MascotMeta<M> {
-    is_crab: TreeMeta<M, ()>,
-    price: TreeMeta<M, ()>,
+#[cfg(feature = "std")]
+impl<K, V, M, S> SplitMeta<M> for std::collections::HashMap<K, RichEntry<Rich<V, M>, M>, S>
+where
+  K: Clone + Eq + core::hash::Hash + TreeMetaProjection<M>,
+  K::TreeMeta: Default,
+  V: SplitMeta<M>,
+  S: core::hash::BuildHasher + Default,
+{
+  type Value = std::collections::HashMap<K, V::Value, S>;
+
+  fn split_meta(self) -> Rich<Self::Value, <Self::Value as TreeMetaProjection<M>>::TreeMeta> {
+    let mut value = std::collections::HashMap::with_capacity_and_hasher(self.len(), S::default());
+    let mut meta = std::collections::HashMap::with_capacity_and_hasher(self.len(), S::default());
+    for (key, entry) in self {
+      let item = entry.value.deep_split_meta();
+      value.insert(key.clone(), item.value);
+      meta.insert(
+        key,
+        EntryMeta::new(TreeMeta::new(entry.key_meta, Default::default()), item.meta),
+      );
+    }
+    Rich::new(value, meta)
   }
+}
 
-  impl<M> TreeMetaProjection<M> for Mascot {
-    type TreeMeta = MascotMeta<M>;
+#[cfg(feature = "alloc")]
+impl<K, V, M> SplitMeta<M> for alloc::collections::BTreeMap<K, RichEntry<Rich<V, M>, M>>
+where
+  K: Clone + Ord + TreeMetaProjection<M>,
+  K::TreeMeta: Default,
+  V: SplitMeta<M>,
+{
+  type Value = alloc::collections::BTreeMap<K, V::Value>;
+
+  fn split_meta(self) -> Rich<Self::Value, <Self::Value as TreeMetaProjection<M>>::TreeMeta> {
+    let mut value = alloc::collections::BTreeMap::new();
+    let mut meta = alloc::collections::BTreeMap::new();
+    for (key, entry) in self {
+      let item = entry.value.deep_split_meta();
+      value.insert(key.clone(), item.value);
+      meta.insert(
+        key,
+        EntryMeta::new(TreeMeta::new(entry.key_meta, Default::default()), item.meta),
+      );
+    }
+    Rich::new(value, meta)
   }
+}
 
-  // #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-  // struct MascotMeta {
-  //   is_crab: TreeMeta<MetaId, Meta<bool, MetaId>>,
-  //   price: TreeMeta<MetaId, Meta<u32, MetaId>>,
-  // }
+/// Error when external metadata does not have the same shape as the value it
+/// is merged into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MergeMetaError {
+  /// The value and its metadata have a different number of elements.
+  LengthMismatch { value: usize, meta: usize },
+  /// The map and its metadata do not have the same set of keys.
+  KeyMismatch,
+  /// The value and its metadata do not use the same variant.
+  VariantMismatch {
+    /// Name of the variant of the value
+    value: &'static str,
+    /// Name of the variant of the metadata
+    meta: &'static str,
+  },
+}
 
-  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-  struct RichMascot {
-    is_crab: Rich<bool, MetaId>,
-    price: Rich<u32, MetaId>,
+impl core::fmt::Display for MergeMetaError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      Self::LengthMismatch { value, meta } => {
+        write!(f, "length mismatch between value ({value}) and metadata ({meta})")
+      }
+      Self::KeyMismatch => f.write_str("key mismatch between value and metadata"),
+      Self::VariantMismatch { value, meta } => {
+        write!(f, "variant mismatch between value ({value}) and metadata ({meta})")
+      }
+    }
   }
+}
 
-  impl SplitMeta<MetaId> for RichMascot {
-    type Value = Mascot;
+impl core::error::Error for MergeMetaError {}
 
-    fn split_meta(self) -> Rich<Self::Value, MascotMeta<MetaId>> {
-      let is_crab: Rich<bool, _> = self.is_crab.deep_split_meta();
-      let price: Rich<u32, _> = self.price.deep_split_meta();
-      Rich::new(
-        Mascot {
-          is_crab: is_crab.value,
-          price: price.value,
-        },
-        MascotMeta {
-          is_crab: is_crab.meta,
-          price: price.meta,
-        },
-      )
+/// Convert a pair of pure data and pure external metadata into a value using
+/// internal metadata representation.
+///
+/// This is the inverse of [`SplitMeta`]: merging the result of a split must
+/// produce the original value, and splitting the result of a merge must
+/// produce the original data and metadata.
+///
+/// Merging fails if the shape of the metadata depends on the value and does
+/// not match it, for example when a `Vec` and its metadata have different
+/// lengths.
+pub trait MergeMeta<M>: TreeMetaProjection<M> + Sized {
+  /// Type of the value with internal metadata.
+  type Rich: SplitMeta<M, Value = Self>;
+
+  fn merge_meta(self, meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError>;
+}
+
+/// Metadata support for enums.
+///
+/// This trait is implemented on the enum holding pure data. The metadata of
+/// an enum is split in two parts: the metadata of its discriminant, selected
+/// through the [`DiscriminantMeta`] marker `Self::Discriminant`, and the
+/// metadata for the fields of the active variant.
+///
+/// Implementing this trait enables the use of [`RichEnum`] as the internal
+/// metadata representation, and [`EnumTreeMeta`] as the external one. The
+/// `TreeMetaProjection` and `MergeMeta` impls of the enum should then use
+/// these types, with [`RichEnum::merge`] as the implementation of
+/// `merge_meta`.
+pub trait EnumMetaFor<M>: Sized {
+  /// Marker selecting the metadata for the discriminant.
+  type Discriminant: DiscriminantMeta<M>;
+  /// Enum with the same variants as `Self`, where each field holds the
+  /// `TreeMeta` of the corresponding field.
+  type VariantMeta;
+  /// Enum with the same variants as `Self`, where each field holds internal
+  /// metadata.
+  type RichVariant;
+
+  /// Split the metadata of the fields of the active variant.
+  fn split_variant_meta(rich: Self::RichVariant) -> Rich<Self, Self::VariantMeta>;
+
+  /// Merge the metadata of the fields of the active variant.
+  ///
+  /// This must fail with [`MergeMetaError::VariantMismatch`] if `meta` is for
+  /// a different variant.
+  fn merge_variant_meta(self, meta: Self::VariantMeta) -> Result<Self::RichVariant, MergeMetaError>;
+}
+
+/// External metadata for an enum `E` implementing [`EnumMetaFor`].
+///
+/// This is the recommended `TreeMeta` for `E`.
+#[expect(
+  type_alias_bounds,
+  reason = "even if it's not enforced yet (see <https://github.com/rust-lang/rust/issues/112792>) the type bound serves as documentation"
+)]
+pub type EnumTreeMeta<E, M>
+where
+  E: EnumMetaFor<M>,
+= EnumMeta<<E::Discriminant as DiscriminantMeta<M>>::Meta, E::VariantMeta>;
+
+/// Internal metadata representation for an enum `E` implementing
+/// [`EnumMetaFor`]: the variant with internal metadata for its fields, along
+/// with the metadata of the discriminant.
+pub struct RichEnum<E, M>
+where
+  E: EnumMetaFor<M>,
+{
+  /// Metadata for the discriminant
+  pub discriminant_meta: <E::Discriminant as DiscriminantMeta<M>>::Meta,
+  /// Active variant, with internal metadata for its fields
+  pub variant: E::RichVariant,
+}
+
+impl<E, M> RichEnum<E, M>
+where
+  E: EnumMetaFor<M>,
+{
+  /// Create a [`RichEnum`] value, by attaching discriminant metadata to a variant with internal metadata.
+  pub const fn new(discriminant_meta: <E::Discriminant as DiscriminantMeta<M>>::Meta, variant: E::RichVariant) -> Self {
+    Self {
+      discriminant_meta,
+      variant,
     }
   }
 
-  #[test]
-  fn deep_split_meta_mascot() {
-    let config = Rich::new(
-      RichMascot {
-        is_crab: Rich::new(true, MetaId(1)),
-        price: Rich::new(42, MetaId(2)),
-      },
-      MetaId(3),
-    );
+  /// Merge an enum value with its external metadata.
+  ///
+  /// This is the recommended implementation of `MergeMeta` for `E`.
+  pub fn merge(value: E, meta: EnumTreeMeta<E, M>) -> Result<Self, MergeMetaError> {
+    Ok(Self::new(meta.discriminant, value.merge_variant_meta(meta.variant)?))
+  }
+}
 
-    let actual = config.deep_split_meta();
+impl<E, M> SplitMeta<M> for RichEnum<E, M>
+where
+  E: EnumMetaFor<M> + TreeMetaProjection<M, TreeMeta = EnumTreeMeta<E, M>>,
+{
+  type Value = E;
 
-    let expected = Rich::new(
-      Mascot {
-        is_crab: true,
-        price: 42,
-      },
-      TreeMeta::new(
-        MetaId(3),
-        MascotMeta {
-          is_crab: TreeMeta::new(MetaId(1), ()),
-          price: TreeMeta::new(MetaId(2), ()),
-        },
-      ),
-    );
+  fn split_meta(self) -> Rich<E, EnumTreeMeta<E, M>> {
+    let variant = E::split_variant_meta(self.variant);
+    Rich::new(variant.value, EnumMeta::new(self.discriminant_meta, variant.meta))
+  }
+}
 
-    assert_eq!(actual, expected);
+impl<E, M> core::fmt::Debug for RichEnum<E, M>
+where
+  E: EnumMetaFor<M>,
+  <E::Discriminant as DiscriminantMeta<M>>::Meta: core::fmt::Debug,
+  E::RichVariant: core::fmt::Debug,
+{
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.debug_struct("RichEnum")
+      .field("discriminant_meta", &self.discriminant_meta)
+      .field("variant", &self.variant)
+      .finish()
+  }
+}
+
+impl<E, M> Clone for RichEnum<E, M>
+where
+  E: EnumMetaFor<M>,
+  <E::Discriminant as DiscriminantMeta<M>>::Meta: Clone,
+  E::RichVariant: Clone,
+{
+  fn clone(&self) -> Self {
+    Self::new(self.discriminant_meta.clone(), self.variant.clone())
+  }
+}
+
+impl<E, M> PartialEq for RichEnum<E, M>
+where
+  E: EnumMetaFor<M>,
+  <E::Discriminant as DiscriminantMeta<M>>::Meta: PartialEq,
+  E::RichVariant: PartialEq,
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.discriminant_meta == other.discriminant_meta && self.variant == other.variant
+  }
+}
+
+impl<E, M> Eq for RichEnum<E, M>
+where
+  E: EnumMetaFor<M>,
+  <E::Discriminant as DiscriminantMeta<M>>::Meta: Eq,
+  E::RichVariant: Eq,
+{
+}
+
+/// Implement `TreeMetaProjection`, `SplitMeta`, `MergeMeta`, `ViewMeta`,
+/// `SyntheticMeta` and `AlignMeta` for types without any sub-component, which
+/// are their own internal metadata representation.
+macro_rules! impl_primitive {
+  ($($ty:ty),* $(,)?) => {
+    $(
+      impl<M> TreeMetaProjection<M> for $ty {
+        // type TreeMeta = ()
+        type TreeMeta = <Self as StructuralProjection<TreeMetaProjector<M>>>::Projection;
+      }
+
+      impl<M> SplitMeta<M> for $ty {
+        type Value = Self;
+
+        fn split_meta(self) -> Rich<Self::Value, ()> {
+          Rich::new(self, ())
+        }
+      }
+
+      impl<M> MergeMeta<M> for $ty {
+        type Rich = Self;
+
+        fn merge_meta(self, _meta: ()) -> Result<Self::Rich, MergeMetaError> {
+          Ok(self)
+        }
+      }
+
+      impl<M> ViewMeta<M> for $ty {
+        type View<'a>
+          = View<'a, Self, M>
+        where
+          Self: 'a,
+          M: 'a;
+      }
+
+      impl<M: RuntimeMeta> SyntheticMeta<M> for $ty {
+        fn synthetic_meta(&self) {}
+      }
+
+      impl<M: RuntimeMeta> AlignMeta<M> for $ty {
+        fn align_meta(&self, _meta: &mut ()) {}
+      }
+    )*
+  };
+}
+
+impl_primitive!(
+  (),
+  bool,
+  char,
+  u8,
+  u16,
+  u32,
+  u64,
+  u128,
+  usize,
+  i8,
+  i16,
+  i32,
+  i64,
+  i128,
+  isize,
+  f32,
+  f64,
+);
+
+impl_primitive!(&str);
+
+#[cfg(feature = "alloc")]
+impl_primitive!(String);
+
+impl<T, M, const N: usize> MergeMeta<M> for [T; N]
+where
+  T: MergeMeta<M>,
+{
+  type Rich = [Rich<T::Rich, M>; N];
+
+  fn merge_meta(self, meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError> {
+    let mut meta = meta.into_iter();
+    let mut error = None;
+    let rich = self.map(|value| {
+      let meta = meta.next().expect("arrays have the same length");
+      match Rich::new(value, meta).deep_merge_meta() {
+        Ok(rich) => Some(rich),
+        Err(e) => {
+          error.get_or_insert(e);
+          None
+        }
+      }
+    });
+    match error {
+      Some(e) => Err(e),
+      None => Ok(rich.map(|item| item.expect("all elements are merged"))),
+    }
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, M> MergeMeta<M> for Vec<T>
+where
+  T: MergeMeta<M>,
+{
+  type Rich = Vec<Rich<T::Rich, M>>;
+
+  fn merge_meta(self, meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError> {
+    if self.len() != meta.len() {
+      return Err(MergeMetaError::LengthMismatch {
+        value: self.len(),
+        meta: meta.len(),
+      });
+    }
+    self
+      .into_iter()
+      .zip(meta)
+      .map(|(value, meta)| Rich::new(value, meta).deep_merge_meta())
+      .collect()
+  }
+}
+
+impl<T, M> MergeMeta<M> for Option<T>
+where
+  T: MergeMeta<M>,
+{
+  type Rich = Option<Rich<T::Rich, M>>;
+
+  fn merge_meta(self, meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError> {
+    match (self, meta) {
+      (Some(value), Some(meta)) => Ok(Some(Rich::new(value, meta).deep_merge_meta()?)),
+      (None, None) => Ok(None),
+      (Some(_), None) => Err(MergeMetaError::VariantMismatch {
+        value: "Some",
+        meta: "None",
+      }),
+      (None, Some(_)) => Err(MergeMetaError::VariantMismatch {
+        value: "None",
+        meta: "Some",
+      }),
+    }
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, M> MergeMeta<M> for Box<T>
+where
+  T: MergeMeta<M>,
+{
+  type Rich = Box<Rich<T::Rich, M>>;
+
+  fn merge_meta(self, meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError> {
+    Ok(Box::new(Rich::new(*self, *meta).deep_merge_meta()?))
+  }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, M, S> MergeMeta<M> for std::collections::HashMap<K, V, S>
+where
+  K: Clone + Eq + core::hash::Hash + TreeMetaProjection<M>,
+  K::TreeMeta: Default,
+  V: MergeMeta<M>,
+  S: core::hash::BuildHasher + Default,
+{
+  type Rich = std::collections::HashMap<K, RichEntry<Rich<V::Rich, M>, M>, S>;
+
+  fn merge_meta(self, mut meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError> {
+    if self.len() != meta.len() {
+      return Err(MergeMetaError::KeyMismatch);
+    }
+    let mut rich = std::collections::HashMap::with_capacity_and_hasher(self.len(), S::default());
+    for (key, value) in self {
+      let entry_meta = meta.remove(&key).ok_or(MergeMetaError::KeyMismatch)?;
+      let value = Rich::new(value, entry_meta.value).deep_merge_meta()?;
+      rich.insert(key, RichEntry::new(entry_meta.key.meta, value));
+    }
+    Ok(rich)
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, M> MergeMeta<M> for alloc::collections::BTreeMap<K, V>
+where
+  K: Clone + Ord + TreeMetaProjection<M>,
+  K::TreeMeta: Default,
+  V: MergeMeta<M>,
+{
+  type Rich = alloc::collections::BTreeMap<K, RichEntry<Rich<V::Rich, M>, M>>;
+
+  fn merge_meta(self, mut meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError> {
+    if self.len() != meta.len() {
+      return Err(MergeMetaError::KeyMismatch);
+    }
+    let mut rich = alloc::collections::BTreeMap::new();
+    for (key, value) in self {
+      let entry_meta = meta.remove(&key).ok_or(MergeMetaError::KeyMismatch)?;
+      let value = Rich::new(value, entry_meta.value).deep_merge_meta()?;
+      rich.insert(key, RichEntry::new(entry_meta.key.meta, value));
+    }
+    Ok(rich)
+  }
+}
+
+/// Implement `TreeMetaProjection`, `SplitMeta`, `MergeMeta`, `ViewMeta`,
+/// `SyntheticMeta` and `AlignMeta` for tuples, handling each element as a
+/// sub-component.
+macro_rules! impl_tuple {
+  ($($ty:ident $index:tt $view:ident),+) => {
+    impl<M, $($ty),+> TreeMetaProjection<M> for ($($ty,)+)
+    where
+      $($ty: TreeMetaProjection<M>,)+
+    {
+      type TreeMeta = ($(TreeMeta<M, $ty::TreeMeta>,)+);
+    }
+
+    impl<M, $($ty),+> SplitMeta<M> for ($(Rich<$ty, M>,)+)
+    where
+      $($ty: SplitMeta<M>,)+
+    {
+      type Value = ($($ty::Value,)+);
+
+      fn split_meta(self) -> Rich<Self::Value, <Self::Value as TreeMetaProjection<M>>::TreeMeta> {
+        let split = ($(self.$index.deep_split_meta(),)+);
+        Rich::new(($(split.$index.value,)+), ($(split.$index.meta,)+))
+      }
+    }
+
+    impl<M, $($ty),+> MergeMeta<M> for ($($ty,)+)
+    where
+      $($ty: MergeMeta<M>,)+
+    {
+      type Rich = ($(Rich<$ty::Rich, M>,)+);
+
+      fn merge_meta(self, meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError> {
+        Ok(($(Rich::new(self.$index, meta.$index).deep_merge_meta()?,)+))
+      }
+    }
+
+    impl<M, $($ty),+> ViewMeta<M> for ($($ty,)+)
+    where
+      $($ty: TreeMetaProjection<M>,)+
+    {
+      type View<'a>
+        = View<'a, Self, M>
+      where
+        Self: 'a,
+        M: 'a;
+    }
+
+    impl<'a, M, $($ty),+> View<'a, ($($ty,)+), M>
+    where
+      $($ty: ViewMeta<M>,)+
+    {
+      $(
+        /// View the element at this index of the tuple.
+        pub fn $view(&self) -> $ty::View<'a> {
+          View::new(&self.value().$index, self.nested().map(|meta| &meta.$index)).typed()
+        }
+      )+
+    }
+
+    impl<M, $($ty),+> SyntheticMeta<M> for ($($ty,)+)
+    where
+      M: RuntimeMeta,
+      $($ty: SyntheticMeta<M>,)+
+    {
+      fn synthetic_meta(&self) -> Self::TreeMeta {
+        ($(view_mut::synthetic_tree_meta(&self.$index),)+)
+      }
+    }
+
+    impl<M, $($ty),+> AlignMeta<M> for ($($ty,)+)
+    where
+      M: RuntimeMeta,
+      $($ty: AlignMeta<M>,)+
+    {
+      fn align_meta(&self, meta: &mut Self::TreeMeta) {
+        $(partial::align_tree_meta(&self.$index, &mut meta.$index);)+
+      }
+    }
+
+    impl<M, $($ty),+> RichMut<'_, ($($ty,)+), M>
+    where
+      $($ty: TreeMetaProjection<M>,)+
+    {
+      $(
+        /// Borrow the element at this index of the tuple.
+        pub fn $view(&mut self) -> RichMut<'_, $ty, M> {
+          self.reborrow().project(|value| &mut value.$index, |meta| &mut meta.$index)
+        }
+      )+
+    }
+  };
+}
+
+impl_tuple!(T0 0 _0);
+impl_tuple!(T0 0 _0, T1 1 _1);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2, T3 3 _3);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2, T3 3 _3, T4 4 _4);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2, T3 3 _3, T4 4 _4, T5 5 _5);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2, T3 3 _3, T4 4 _4, T5 5 _5, T6 6 _6);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2, T3 3 _3, T4 4 _4, T5 5 _5, T6 6 _6, T7 7 _7);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2, T3 3 _3, T4 4 _4, T5 5 _5, T6 6 _6, T7 7 _7, T8 8 _8);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2, T3 3 _3, T4 4 _4, T5 5 _5, T6 6 _6, T7 7 _7, T8 8 _8, T9 9 _9);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2, T3 3 _3, T4 4 _4, T5 5 _5, T6 6 _6, T7 7 _7, T8 8 _8, T9 9 _9, T10 10 _10);
+impl_tuple!(T0 0 _0, T1 1 _1, T2 2 _2, T3 3 _3, T4 4 _4, T5 5 _5, T6 6 _6, T7 7 _7, T8 8 _8, T9 9 _9, T10 10 _10, T11 11 _11);
+
+impl<T, M> Rich<T, M>
+where
+  T: SplitMeta<M>,
+{
+  /// Convert a rich holding a `T` with internal metadata into an external
+  /// metadata representation with pure data and pure metadata.
+  #[expect(clippy::type_complexity, reason = "keeping the signature self-contained is valuable")]
+  pub fn deep_split_meta(self) -> Rich<T::Value, TreeMeta<M, <T::Value as TreeMetaProjection<M>>::TreeMeta>> {
+    let value_and_nested_meta: Rich<T::Value, _> = self.value.split_meta();
+    Rich::new(
+      value_and_nested_meta.value,
+      TreeMeta::new(self.meta, value_and_nested_meta.meta),
+    )
+  }
+}
+
+impl<T, M> Rich<T, TreeMeta<M, T::TreeMeta>>
+where
+  T: MergeMeta<M>,
+{
+  /// Convert a rich holding pure data and pure external metadata into a
+  /// `T::Rich` value with internal metadata.
+  ///
+  /// This is the inverse of [`Rich::deep_split_meta`].
+  pub fn deep_merge_meta(self) -> Result<Rich<T::Rich, M>, MergeMetaError> {
+    let TreeMeta { meta, nested } = self.meta;
+    Ok(Rich::new(self.value.merge_meta(nested)?, meta))
+  }
+}
+
+// impl<T, M> SplitMeta<M> for Rich<T, M>
+// where
+//   T: StructuralProjection<TreeMetaProjector<M>, Projection = ()>, // <Self::Value as StructuralProjection<TreeMetaProjector<M>>>::Projection>
+// {
+//   type Value = T;
+//
+//   fn split_meta(self) -> Rich<T, ()> {
+//     Rich::new(self.value, ())
+//   }
+// }
+
+// // /// Represents a metadata node in a structured hierarchy
+// // #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+// // pub struct WrappedMeta<N> {
+// //   id: MetaId,
+// //   nested: N,
+// // }
+// //
+// // impl<N> WrappedMeta<N> {
+// //   /// Create a new [WrappedMeta].
+// //   pub const fn new(nested: N, id: MetaId) -> Self {
+// //     Self { nested, id }
+// //   }
+// // }
+// //
+// // #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// // pub struct BoolView<'rich>(Rich<&'rich bool, &'rich WrappedMeta<Meta<bool, MetaId>>>);
+// //
+// // impl<'rich> BoolView<'rich> {
+// //   pub fn new(rich: Rich<&'rich bool, &'rich WrappedMeta<Meta<bool, MetaId>>>) -> Self {
+// //     Self(rich)
+// //   }
+// // }
+//
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  struct Mascot {
+    is_crab: bool,
+    price: u32,
+  }
+
+  #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  struct MascotMeta<M> {
+    is_crab: TreeMeta<M, ()>,
+    price: TreeMeta<M, ()>,
+  }
+
+  impl<M> TreeMetaProjection<M> for Mascot {
+    type TreeMeta = MascotMeta<M>;
+  }
+
+  // #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  // struct MascotMeta {
+  //   is_crab: TreeMeta<MetaId, Meta<bool, MetaId>>,
+  //   price: TreeMeta<MetaId, Meta<u32, MetaId>>,
+  // }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  enum Operation {
+    Read,
+    Update { is_admin: bool, rate_limit: u32 },
+    Delete(bool),
+  }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  enum RichOperation<M> {
+    Read,
+    Update {
+      is_admin: Rich<bool, M>,
+      rate_limit: Rich<u32, M>,
+    },
+    Delete(Rich<bool, M>),
+  }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  enum OperationMeta<M> {
+    Read,
+    Update {
+      is_admin: TreeMeta<M, ()>,
+      rate_limit: TreeMeta<M, ()>,
+    },
+    Delete(TreeMeta<M, ()>),
+  }
+
+  impl Operation {
+    fn variant_name(&self) -> &'static str {
+      match self {
+        Self::Read => "Read",
+        Self::Update { .. } => "Update",
+        Self::Delete(..) => "Delete",
+      }
+    }
+  }
+
+  impl<M> OperationMeta<M> {
+    fn variant_name(&self) -> &'static str {
+      match self {
+        Self::Read => "Read",
+        Self::Update { .. } => "Update",
+        Self::Delete(..) => "Delete",
+      }
+    }
+  }
+
+  impl<M> EnumMetaFor<M> for Operation {
+    type Discriminant = WithDiscriminant;
+    type VariantMeta = OperationMeta<M>;
+    type RichVariant = RichOperation<M>;
+
+    fn split_variant_meta(rich: RichOperation<M>) -> Rich<Self, OperationMeta<M>> {
+      match rich {
+        RichOperation::Read => Rich::new(Operation::Read, OperationMeta::Read),
+        RichOperation::Update { is_admin, rate_limit } => {
+          let is_admin = is_admin.deep_split_meta();
+          let rate_limit = rate_limit.deep_split_meta();
+          Rich::new(
+            Operation::Update {
+              is_admin: is_admin.value,
+              rate_limit: rate_limit.value,
+            },
+            OperationMeta::Update {
+              is_admin: is_admin.meta,
+              rate_limit: rate_limit.meta,
+            },
+          )
+        }
+        RichOperation::Delete(force) => {
+          let force = force.deep_split_meta();
+          Rich::new(Operation::Delete(force.value), OperationMeta::Delete(force.meta))
+        }
+      }
+    }
+
+    fn merge_variant_meta(self, meta: OperationMeta<M>) -> Result<RichOperation<M>, MergeMetaError> {
+      match (self, meta) {
+        (Operation::Read, OperationMeta::Read) => Ok(RichOperation::Read),
+        (
+          Operation::Update { is_admin, rate_limit },
+          OperationMeta::Update {
+            is_admin: is_admin_meta,
+            rate_limit: rate_limit_meta,
+          },
+        ) => Ok(RichOperation::Update {
+          is_admin: Rich::new(is_admin, is_admin_meta).deep_merge_meta()?,
+          rate_limit: Rich::new(rate_limit, rate_limit_meta).deep_merge_meta()?,
+        }),
+        (Operation::Delete(force), OperationMeta::Delete(force_meta)) => {
+          Ok(RichOperation::Delete(Rich::new(force, force_meta).deep_merge_meta()?))
+        }
+        (value, meta) => Err(MergeMetaError::VariantMismatch {
+          value: value.variant_name(),
+          meta: meta.variant_name(),
+        }),
+      }
+    }
+  }
+
+  impl<M> TreeMetaProjection<M> for Operation {
+    type TreeMeta = EnumTreeMeta<Self, M>;
+  }
+
+  impl<M> MergeMeta<M> for Operation {
+    type Rich = RichEnum<Self, M>;
+
+    fn merge_meta(self, meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError> {
+      RichEnum::merge(self, meta)
+    }
+  }
+
+  impl<M: RuntimeMeta> SyntheticMeta<M> for Operation {
+    fn synthetic_meta(&self) -> Self::TreeMeta {
+      EnumMeta::synthetic(match self {
+        Operation::Read => OperationMeta::Read,
+        Operation::Update { is_admin, rate_limit } => OperationMeta::Update {
+          is_admin: view_mut::synthetic_tree_meta(is_admin),
+          rate_limit: view_mut::synthetic_tree_meta(rate_limit),
+        },
+        Operation::Delete(force) => OperationMeta::Delete(view_mut::synthetic_tree_meta(force)),
+      })
+    }
+  }
+
+  /// Metadata for another variant is replaced, along with the metadata of the
+  /// discriminant.
+  impl<M: RuntimeMeta> AlignMeta<M> for Operation {
+    fn align_meta(&self, meta: &mut Self::TreeMeta) {
+      match (self, &mut meta.variant) {
+        (Operation::Read, OperationMeta::Read) => {}
+        (
+          Operation::Update { is_admin, rate_limit },
+          OperationMeta::Update {
+            is_admin: is_admin_meta,
+            rate_limit: rate_limit_meta,
+          },
+        ) => {
+          partial::align_tree_meta(is_admin, is_admin_meta);
+          partial::align_tree_meta(rate_limit, rate_limit_meta);
+        }
+        (Operation::Delete(force), OperationMeta::Delete(force_meta)) => partial::align_tree_meta(force, force_meta),
+        _ => *meta = self.synthetic_meta(),
+      }
+    }
+  }
+
+  /// Enum whose variant is inferred from its fields, so there is no input
+  /// to attach discriminant metadata to.
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  enum Limit {
+    Unlimited,
+    Fixed(u32),
+  }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  enum RichLimit<M> {
+    Unlimited,
+    Fixed(Rich<u32, M>),
+  }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  enum LimitMeta<M> {
+    Unlimited,
+    Fixed(TreeMeta<M, ()>),
+  }
+
+  impl<M> EnumMetaFor<M> for Limit {
+    type Discriminant = WithoutDiscriminant;
+    type VariantMeta = LimitMeta<M>;
+    type RichVariant = RichLimit<M>;
+
+    fn split_variant_meta(rich: RichLimit<M>) -> Rich<Self, LimitMeta<M>> {
+      match rich {
+        RichLimit::Unlimited => Rich::new(Limit::Unlimited, LimitMeta::Unlimited),
+        RichLimit::Fixed(max) => {
+          let max = max.deep_split_meta();
+          Rich::new(Limit::Fixed(max.value), LimitMeta::Fixed(max.meta))
+        }
+      }
+    }
+
+    fn merge_variant_meta(self, meta: LimitMeta<M>) -> Result<RichLimit<M>, MergeMetaError> {
+      match (self, meta) {
+        (Limit::Unlimited, LimitMeta::Unlimited) => Ok(RichLimit::Unlimited),
+        (Limit::Fixed(max), LimitMeta::Fixed(max_meta)) => {
+          Ok(RichLimit::Fixed(Rich::new(max, max_meta).deep_merge_meta()?))
+        }
+        (Limit::Unlimited, _) => Err(MergeMetaError::VariantMismatch {
+          value: "Unlimited",
+          meta: "Fixed",
+        }),
+        (Limit::Fixed(_), _) => Err(MergeMetaError::VariantMismatch {
+          value: "Fixed",
+          meta: "Unlimited",
+        }),
+      }
+    }
+  }
+
+  impl<M> TreeMetaProjection<M> for Limit {
+    type TreeMeta = EnumTreeMeta<Self, M>;
+  }
+
+  impl<M> MergeMeta<M> for Limit {
+    type Rich = RichEnum<Self, M>;
+
+    fn merge_meta(self, meta: Self::TreeMeta) -> Result<Self::Rich, MergeMetaError> {
+      RichEnum::merge(self, meta)
+    }
+  }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  struct RichMascot<M = MetaId> {
+    is_crab: Rich<bool, M>,
+    price: Rich<u32, M>,
+  }
+
+  impl<M> SplitMeta<M> for RichMascot<M> {
+    type Value = Mascot;
+
+    fn split_meta(self) -> Rich<Self::Value, MascotMeta<M>> {
+      let is_crab: Rich<bool, _> = self.is_crab.deep_split_meta();
+      let price: Rich<u32, _> = self.price.deep_split_meta();
+      Rich::new(
+        Mascot {
+          is_crab: is_crab.value,
+          price: price.value,
+        },
+        MascotMeta {
+          is_crab: is_crab.meta,
+          price: price.meta,
+        },
+      )
+    }
+  }
+
+  impl<M> MergeMeta<M> for Mascot {
+    type Rich = RichMascot<M>;
+
+    fn merge_meta(self, meta: MascotMeta<M>) -> Result<Self::Rich, MergeMetaError> {
+      Ok(RichMascot {
+        is_crab: Rich::new(self.is_crab, meta.is_crab).deep_merge_meta()?,
+        price: Rich::new(self.price, meta.price).deep_merge_meta()?,
+      })
+    }
+  }
+
+  struct MascotView<'a, M>(View<'a, Mascot, M>);
+
+  impl<'a, M> From<View<'a, Mascot, M>> for MascotView<'a, M> {
+    fn from(view: View<'a, Mascot, M>) -> Self {
+      Self(view)
+    }
+  }
+
+  impl<M> ViewMeta<M> for Mascot {
+    type View<'a>
+      = MascotView<'a, M>
+    where
+      M: 'a;
+  }
+
+  impl<'a, M> MascotView<'a, M> {
+    fn is_crab(&self) -> View<'a, bool, M> {
+      View::new(&self.0.value().is_crab, self.0.nested().map(|meta| &meta.is_crab))
+    }
+
+    fn price(&self) -> View<'a, u32, M> {
+      View::new(&self.0.value().price, self.0.nested().map(|meta| &meta.price))
+    }
+  }
+
+  impl<M: RuntimeMeta> SyntheticMeta<M> for Mascot {
+    fn synthetic_meta(&self) -> MascotMeta<M> {
+      MascotMeta {
+        is_crab: view_mut::synthetic_tree_meta(&self.is_crab),
+        price: view_mut::synthetic_tree_meta(&self.price),
+      }
+    }
+  }
+
+  impl<M: RuntimeMeta> AlignMeta<M> for Mascot {
+    fn align_meta(&self, meta: &mut MascotMeta<M>) {
+      partial::align_tree_meta(&self.is_crab, &mut meta.is_crab);
+      partial::align_tree_meta(&self.price, &mut meta.price);
+    }
+  }
+
+  #[test]
+  fn deep_split_meta_mascot() {
+    let config = Rich::new(
+      RichMascot {
+        is_crab: Rich::new(true, MetaId(1)),
+        price: Rich::new(42, MetaId(2)),
+      },
+      MetaId(3),
+    );
+
+    let actual = config.deep_split_meta();
+
+    let expected = Rich::new(
+      Mascot {
+        is_crab: true,
+        price: 42,
+      },
+      TreeMeta::new(
+        MetaId(3),
+        MascotMeta {
+          is_crab: TreeMeta::new(MetaId(1), ()),
+          price: TreeMeta::new(MetaId(2), ()),
+        },
+      ),
+    );
+
+    assert_eq!(actual, expected);
+  }
+
+  #[test]
+  fn deep_split_meta_array() {
+    let channels = Rich::new([Rich::new(true, MetaId(1)), Rich::new(false, MetaId(2))], MetaId(3));
+
+    let actual = channels.deep_split_meta();
+
+    let expected = Rich::new(
+      [true, false],
+      TreeMeta::new(MetaId(3), [TreeMeta::new(MetaId(1), ()), TreeMeta::new(MetaId(2), ())]),
+    );
+
+    assert_eq!(actual, expected);
+  }
+
+  #[test]
+  #[cfg(feature = "alloc")]
+  fn tree_meta_projector_recurses() {
+    let meta: styp::Project<Vec<Option<u32>>, TreeMetaProjector<MetaId>> = alloc::vec![
+      TreeMeta::new(MetaId(1), Some(TreeMeta::new(MetaId(2), ()))),
+      TreeMeta::new(MetaId(3), None),
+    ];
+
+    assert_eq!(meta[0].nested, Some(TreeMeta::new(MetaId(2), ())));
+  }
+
+  #[test]
+  #[cfg(feature = "alloc")]
+  fn tree_meta_projector_matches_tree_meta() {
+    use alloc::collections::BTreeMap;
+
+    type Stock = BTreeMap<String, Vec<Option<u32>>>;
+
+    let meta: TreeMeta<MetaId, <Stock as TreeMetaProjection<MetaId>>::TreeMeta> = TreeMeta::new(
+      MetaId(1),
+      BTreeMap::from([(
+        String::from("krill"),
+        EntryMeta::new(
+          TreeMeta::new(MetaId(2), ()),
+          TreeMeta::new(MetaId(3), alloc::vec![TreeMeta::new(MetaId(4), None)]),
+        ),
+      )]),
+    );
+    let projected: styp::Project<Stock, TreeMetaProjector<MetaId>> = meta.nested;
+
+    assert_eq!(projected["krill"].key, TreeMeta::new(MetaId(2), ()));
+  }
+
+  #[test]
+  fn deep_merge_meta_mascot() {
+    let config = Rich::new(
+      RichMascot {
+        is_crab: Rich::new(true, MetaId(1)),
+        price: Rich::new(42, MetaId(2)),
+      },
+      MetaId(3),
+    );
+
+    let actual = config.deep_split_meta().deep_merge_meta();
+
+    assert_eq!(actual, Ok(config));
+  }
+
+  #[test]
+  fn deep_merge_meta_tuple() {
+    let pair = Rich::new((Rich::new(true, MetaId(1)), Rich::new(7u32, MetaId(2))), MetaId(3));
+
+    let actual = pair.deep_split_meta().deep_merge_meta();
+
+    assert_eq!(actual, Ok(pair));
+  }
+
+  #[test]
+  fn deep_merge_meta_array() {
+    let channels = Rich::new([Rich::new(1u32, MetaId(1)), Rich::new(2u32, MetaId(2))], MetaId(3));
+
+    let actual = channels.deep_split_meta().deep_merge_meta();
+
+    assert_eq!(actual, Ok(channels));
+  }
+
+  #[test]
+  #[cfg(feature = "alloc")]
+  fn deep_merge_meta_vec() {
+    let list = Rich::new(
+      alloc::vec![Rich::new(true, MetaId(1)), Rich::new(false, MetaId(2))],
+      MetaId(3),
+    );
+
+    let actual = list.clone().deep_split_meta().deep_merge_meta();
+
+    assert_eq!(actual, Ok(list));
+  }
+
+  #[test]
+  #[cfg(feature = "alloc")]
+  fn deep_merge_meta_vec_length_mismatch() {
+    let list = Rich::new(
+      alloc::vec![true, false],
+      TreeMeta::new(MetaId(3), alloc::vec![TreeMeta::new(MetaId(1), ())]),
+    );
+
+    let actual = list.deep_merge_meta();
+
+    assert_eq!(actual, Err(MergeMetaError::LengthMismatch { value: 2, meta: 1 }));
+  }
+
+  #[test]
+  fn deep_merge_meta_option_variant_mismatch() {
+    let value = Rich::new(Some(true), TreeMeta::new(MetaId(1), None));
+
+    let actual = value.deep_merge_meta();
+
+    assert_eq!(
+      actual,
+      Err(MergeMetaError::VariantMismatch {
+        value: "Some",
+        meta: "None"
+      })
+    );
+  }
+
+  #[test]
+  #[cfg(feature = "alloc")]
+  fn deep_split_meta_btree_map_keeps_key_meta() {
+    use alloc::collections::BTreeMap;
+
+    let food_stock = Rich::new(
+      BTreeMap::from([
+        (
+          String::from("fish"),
+          RichEntry::new(MetaId(1), Rich::new(12u32, MetaId(2))),
+        ),
+        (
+          String::from("krill"),
+          RichEntry::new(MetaId(3), Rich::new(500u32, MetaId(4))),
+        ),
+      ]),
+      MetaId(5),
+    );
+
+    let split = food_stock.clone().deep_split_meta();
+
+    assert_eq!(
+      split.value,
+      BTreeMap::from([(String::from("fish"), 12), (String::from("krill"), 500)])
+    );
+    assert_eq!(
+      split.meta.nested["krill"],
+      EntryMeta::new(TreeMeta::new(MetaId(3), ()), TreeMeta::new(MetaId(4), ()))
+    );
+    assert_eq!(split.deep_merge_meta(), Ok(food_stock));
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn deep_merge_meta_hash_map_key_mismatch() {
+    use std::collections::HashMap;
+
+    let value = Rich::new(
+      HashMap::from([(String::from("fish"), 12u32)]),
+      TreeMeta::new(
+        MetaId(3),
+        HashMap::from([(
+          String::from("krill"),
+          EntryMeta::new(TreeMeta::new(MetaId(1), ()), TreeMeta::new(MetaId(2), ())),
+        )]),
+      ),
+    );
+
+    let actual = value.deep_merge_meta();
+
+    assert_eq!(actual, Err(MergeMetaError::KeyMismatch));
+  }
+
+  #[test]
+  fn deep_split_meta_enum() {
+    let operation = Rich::new(
+      RichEnum::<Operation, MetaId>::new(
+        MetaId(1),
+        RichOperation::Update {
+          is_admin: Rich::new(false, MetaId(2)),
+          rate_limit: Rich::new(10, MetaId(3)),
+        },
+      ),
+      MetaId(4),
+    );
+
+    let actual = operation.clone().deep_split_meta();
+
+    let expected = Rich::new(
+      Operation::Update {
+        is_admin: false,
+        rate_limit: 10,
+      },
+      TreeMeta::new(
+        MetaId(4),
+        EnumMeta::new(
+          MetaId(1),
+          OperationMeta::Update {
+            is_admin: TreeMeta::new(MetaId(2), ()),
+            rate_limit: TreeMeta::new(MetaId(3), ()),
+          },
+        ),
+      ),
+    );
+
+    assert_eq!(actual, expected);
+    assert_eq!(actual.deep_merge_meta(), Ok(operation));
+  }
+
+  #[test]
+  fn deep_split_meta_enum_without_discriminant() {
+    let limit = Rich::new(
+      RichEnum::<Limit, MetaId>::new((), RichLimit::Fixed(Rich::new(100, MetaId(1)))),
+      MetaId(2),
+    );
+
+    let actual = limit.clone().deep_split_meta();
+
+    let expected = Rich::new(
+      Limit::Fixed(100),
+      TreeMeta::new(
+        MetaId(2),
+        EnumMeta::new((), LimitMeta::Fixed(TreeMeta::new(MetaId(1), ()))),
+      ),
+    );
+
+    assert_eq!(actual, expected);
+    assert_eq!(actual.deep_merge_meta(), Ok(limit));
+  }
+
+  #[test]
+  fn deep_merge_meta_enum_variant_mismatch() {
+    let operation = Rich::new(
+      Operation::Delete(true),
+      TreeMeta::new(MetaId(2), EnumMeta::new(MetaId(1), OperationMeta::Read)),
+    );
+
+    let actual = operation.deep_merge_meta();
+
+    assert_eq!(
+      actual,
+      Err(MergeMetaError::VariantMismatch {
+        value: "Delete",
+        meta: "Read"
+      })
+    );
+  }
+
+  #[test]
+  fn deep_merge_partial_meta_enum() {
+    let id = |id: usize| Some(MetaId(id));
+    let update = Operation::Update {
+      is_admin: true,
+      rate_limit: 10,
+    };
+
+    let meta = TreeMeta::new(
+      id(3),
+      EnumMeta::new(id(2), OperationMeta::Delete(TreeMeta::new(id(1), ()))),
+    );
+
+    // Metadata for another variant is replaced
+    let actual = Rich::new(update, meta).deep_merge_partial_meta();
+
+    let expected = Rich::new(
+      RichEnum::<Operation, Option<MetaId>>::new(
+        None,
+        RichOperation::Update {
+          is_admin: Rich::new(true, None),
+          rate_limit: Rich::new(10, None),
+        },
+      ),
+      id(3),
+    );
+    assert_eq!(actual, Ok(expected));
+
+    // Metadata for the same variant is kept
+    let actual = Rich::new(Operation::Delete(false), meta).deep_merge_partial_meta();
+
+    let expected = Rich::new(
+      RichEnum::<Operation, Option<MetaId>>::new(id(2), RichOperation::Delete(Rich::new(false, id(1)))),
+      id(3),
+    );
+    assert_eq!(actual, Ok(expected));
+  }
+
+  #[test]
+  fn view_struct_fields() {
+    let mascot = Rich::new(
+      RichMascot {
+        is_crab: Rich::new(true, MetaId(1)),
+        price: Rich::new(42, MetaId(2)),
+      },
+      MetaId(3),
+    )
+    .deep_split_meta();
+
+    let view = mascot.view();
+
+    assert_eq!(view.0.meta(), Some(&MetaId(3)));
+    assert_eq!(view.is_crab().rich(), Rich::new(&true, Some(&MetaId(1))));
+    assert_eq!(view.price().rich(), Rich::new(&42, Some(&MetaId(2))));
+  }
+
+  #[test]
+  fn view_missing_meta() {
+    let mascot = Mascot {
+      is_crab: true,
+      price: 42,
+    };
+
+    let view: MascotView<'_, MetaId> = View::new(&mascot, None).typed();
+
+    assert_eq!(view.price().value(), &42);
+    assert_eq!(view.price().meta(), None);
+    assert_eq!(view.price().meta_or(&MetaId(0)), &MetaId(0));
+  }
+
+  #[test]
+  #[cfg(feature = "alloc")]
+  fn view_collections() {
+    use alloc::collections::BTreeMap;
+
+    let prices = Rich::new(
+      (
+        alloc::vec![Some(12u32), None, Some(3)],
+        BTreeMap::from([(String::from("krill"), 500u32)]),
+      ),
+      TreeMeta::new(
+        MetaId(1),
+        (
+          TreeMeta::new(
+            MetaId(2),
+            alloc::vec![
+              TreeMeta::new(MetaId(3), Some(TreeMeta::new(MetaId(4), ()))),
+              TreeMeta::new(MetaId(5), None),
+            ],
+          ),
+          TreeMeta::new(
+            MetaId(6),
+            BTreeMap::from([(
+              String::from("krill"),
+              EntryMeta::new(TreeMeta::new(MetaId(7), ()), TreeMeta::new(MetaId(8), ())),
+            )]),
+          ),
+        ),
+      ),
+    );
+
+    let view = prices.view();
+    let list = view._0();
+    let stock = view._1();
+
+    assert_eq!(list.len(), 3);
+    assert_eq!(
+      list.index(0).and_then(|item| item.inner()).map(|item| item.rich()),
+      Some(Rich::new(&12, Some(&MetaId(4))))
+    );
+    assert_eq!(
+      list.index(1).and_then(|item| item.inner()).map(|item| item.rich()),
+      None
+    );
+    // The metadata is shorter than the value
+    assert_eq!(list.index(2).map(|item| item.meta()), Some(None));
+    assert_eq!(list.index(3).map(|item| item.meta()), None);
+    assert_eq!(
+      stock.get("krill").map(|item| item.rich()),
+      Some(Rich::new(&500, Some(&MetaId(8))))
+    );
+    assert_eq!(stock.key_meta("krill"), Some(&MetaId(7)));
+    assert_eq!(stock.get("fish").map(|item| item.rich()), None);
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn view_hash_map_iter() {
+    use std::collections::HashMap;
+
+    let stock = Rich::new(
+      HashMap::from([(String::from("krill"), 500u32), (String::from("fish"), 12)]),
+      TreeMeta::new(
+        MetaId(1),
+        HashMap::from([(
+          String::from("krill"),
+          EntryMeta::new(TreeMeta::new(MetaId(2), ()), TreeMeta::new(MetaId(3), ())),
+        )]),
+      ),
+    );
+
+    let view = stock.view();
+    let mut entries: Vec<(&str, Rich<&u32, Option<&MetaId>>)> =
+      view.iter().map(|(key, item)| (key.as_str(), item.rich())).collect();
+    entries.sort_by_key(|(key, _)| *key);
+
+    assert_eq!(
+      entries,
+      [
+        ("fish", Rich::new(&12, None)),
+        ("krill", Rich::new(&500, Some(&MetaId(3)))),
+      ]
+    );
+  }
+
+  #[test]
+  #[cfg(feature = "alloc")]
+  fn rich_mut_keeps_meta_in_sync() {
+    let input = |id| Provenance::Input(MetaId(id));
+    let mut mascots = alloc::vec![Mascot {
+      is_crab: true,
+      price: 42,
+    }];
+    let mut meta = TreeMeta::new(
+      input(1),
+      alloc::vec![TreeMeta::new(
+        input(2),
+        MascotMeta {
+          is_crab: TreeMeta::new(input(3), ()),
+          price: TreeMeta::new(input(4), ()),
+        },
+      )],
+    );
+
+    let mut rich = RichMut::new(&mut mascots, Some(&mut meta));
+    if let Some(mascot) = rich.index(0) {
+      let mut price = mascot.project(|mascot| &mut mascot.price, |meta| &mut meta.price);
+      assert_eq!(price.set(50), 42);
+    }
+    rich.push(Mascot {
+      is_crab: false,
+      price: 10,
+    });
+
+    let view = View::new(&mascots, Some(&meta));
+    let first = view.index(0).expect("first mascot exists");
+    let second = view.index(1).expect("second mascot exists");
+    assert_eq!(view.meta(), Some(&Provenance::Modified(MetaId(1))));
+    assert_eq!(first.0.meta(), Some(&input(2)));
+    assert_eq!(first.is_crab().meta(), Some(&input(3)));
+    assert_eq!(
+      first.price().rich(),
+      Rich::new(&50, Some(&Provenance::Modified(MetaId(4))))
+    );
+    assert_eq!(second.price().rich(), Rich::new(&10, Some(&Provenance::Synthetic)));
+  }
+
+  #[test]
+  fn partial_meta_mascot() {
+    let mascot = Mascot {
+      is_crab: true,
+      price: 42,
+    };
+    let built = Rich::<_, TreeMeta<Option<MetaId>, _>>::synthetic(mascot);
+    let mut parsed = Rich::new(mascot, TreeMeta::<Option<MetaId>, MascotMeta<_>>::default());
+    parsed.meta = TreeMeta::new(
+      Some(MetaId(3)),
+      MascotMeta {
+        price: TreeMeta::new(Some(MetaId(2)), ()),
+        ..MascotMeta::default()
+      },
+    );
+
+    assert_eq!(built.meta, TreeMeta::default());
+    assert_eq!(built.view().price().known_meta(), None);
+    assert_eq!(parsed.view().price().known_meta(), Some(&MetaId(2)));
+    assert_eq!(
+      parsed.deep_merge_partial_meta(),
+      Ok(Rich::new(
+        RichMascot {
+          is_crab: Rich::new(true, None),
+          price: Rich::new(42, Some(MetaId(2))),
+        },
+        Some(MetaId(3))
+      ))
+    );
+  }
+
+  #[test]
+  fn view_enum_meta() {
+    let operation = Rich::new(
+      RichEnum::<Operation, MetaId>::new(MetaId(1), RichOperation::Delete(Rich::new(true, MetaId(2)))),
+      MetaId(3),
+    )
+    .deep_split_meta();
+
+    let view = View::<Operation, MetaId>::new(&operation.value, Some(&operation.meta));
+
+    assert_eq!(view.meta(), Some(&MetaId(3)));
+    assert_eq!(view.discriminant_meta(), Some(&MetaId(1)));
+    assert_eq!(
+      view.variant_meta(),
+      Some(&OperationMeta::Delete(TreeMeta::new(MetaId(2), ())))
+    );
+  }
+
+  #[cfg(feature = "serde_json1")]
+  #[test]
+  fn serde_json_value_split_merge() {
+    use crate::ecosystem::serde_json1::{RichValue, ValueMeta};
+    use alloc::collections::BTreeMap;
+    use serde_json1::{Value, json};
+
+    let rich = Rich::new(
+      RichValue::Object(BTreeMap::from([(
+        String::from("tags"),
+        RichEntry::new(
+          MetaId(1),
+          Rich::new(
+            RichValue::Array(alloc::vec![Rich::new(
+              RichValue::String(String::from("crab")),
+              MetaId(2)
+            )]),
+            MetaId(3),
+          ),
+        ),
+      )])),
+      MetaId(4),
+    );
+
+    let split = rich.clone().deep_split_meta();
+
+    assert_eq!(split.value, json!({ "tags": ["crab"] }));
+    assert_eq!(
+      View::<Value, MetaId>::new(&split.value, Some(&split.meta))
+        .get("tags")
+        .and_then(|tags| tags.index(0))
+        .map(|tag| tag.rich()),
+      Some(Rich::new(&json!("crab"), Some(&MetaId(2))))
+    );
+    assert_eq!(split.clone().deep_merge_meta(), Ok(rich));
+    assert_eq!(
+      Rich::new(json!(true), TreeMeta::new(MetaId(1), ValueMeta::Null)).deep_merge_meta(),
+      Err(MergeMetaError::VariantMismatch {
+        value: "Bool",
+        meta: "Null"
+      })
+    );
   }
 }
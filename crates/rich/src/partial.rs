@@ -0,0 +1,268 @@
+//! Partial metadata.
+//!
+//! External metadata does not always cover the whole value: values built in
+//! code have no metadata at all, and metadata may only be known for some
+//! components. Using `Option<M>` as the metadata type lets the metadata tree
+//! start empty (`TreeMeta::default()`), or use `None` for unknown nodes.
+//!
+//! [`AlignMeta`] repairs a metadata tree which does not match the shape of its
+//! value, so it can then be merged like any other metadata. Values built in
+//! code and values parsed from some input then go through the same code paths.
+
+use crate::view_mut::{RuntimeMeta, SyntheticMeta, synthetic_tree_meta};
+use crate::{MergeMeta, MergeMetaError, Rich, TreeMeta, View};
+
+/// `None` is used for synthetic values.
+///
+/// `Option` can't record modifications: the metadata of a modified value is
+/// kept as-is. Use [`Provenance`](crate::Provenance) to track them.
+impl<M> RuntimeMeta for Option<M> {
+  fn synthetic() -> Self {
+    None
+  }
+
+  fn mark_modified(&mut self) {}
+}
+
+impl<M, N> TreeMeta<Option<M>, N> {
+  /// Metadata for the main value, if it is known.
+  pub const fn known_meta(&self) -> Option<&M> {
+    self.meta.as_ref()
+  }
+}
+
+impl<'a, T, M> View<'a, T, Option<M>>
+where
+  T: crate::TreeMetaProjection<Option<M>> + ?Sized,
+{
+  /// Metadata for the value itself, if it is known.
+  ///
+  /// This is `None` both when the view has no metadata and when the metadata
+  /// for this value is `None`.
+  pub fn known_meta(&self) -> Option<&'a M> {
+    self.meta()?.as_ref()
+  }
+}
+
+/// Types able to repair external metadata which does not match their value.
+///
+/// Missing metadata is replaced by synthetic metadata, and metadata without a
+/// matching component in the value is dropped. Once aligned, the metadata can
+/// be merged with the value.
+pub trait AlignMeta<M: RuntimeMeta>: SyntheticMeta<M> {
+  /// Update the nested metadata `meta` to match the shape of `self`.
+  fn align_meta(&self, meta: &mut Self::TreeMeta);
+}
+
+/// Align the metadata tree `meta` with `value`.
+pub fn align_tree_meta<T, M>(value: &T, meta: &mut TreeMeta<M, T::TreeMeta>)
+where
+  T: AlignMeta<M> + ?Sized,
+  M: RuntimeMeta,
+{
+  value.align_meta(&mut meta.nested);
+}
+
+impl<T, M> Rich<T, TreeMeta<M, T::TreeMeta>>
+where
+  T: SyntheticMeta<M>,
+  M: RuntimeMeta,
+{
+  /// Attach synthetic metadata to a value built by the program.
+  ///
+  /// With `Option<M>` metadata, this is a metadata tree where every node is
+  /// `None`.
+  pub fn synthetic(value: T) -> Self {
+    let meta = synthetic_tree_meta(&value);
+    Rich::new(value, meta)
+  }
+}
+
+impl<T, M> Rich<T, TreeMeta<M, T::TreeMeta>>
+where
+  T: MergeMeta<M> + AlignMeta<M>,
+  M: RuntimeMeta,
+{
+  /// Convert a rich holding pure data and partial external metadata into a
+  /// `T::Rich` value with internal metadata.
+  ///
+  /// Unlike [`Rich::deep_merge_meta`], the metadata does not have to match the
+  /// value: it is aligned with the value first. Merging can then only fail if
+  /// the `AlignMeta` and `MergeMeta` implementations are inconsistent.
+  pub fn deep_merge_partial_meta(mut self) -> Result<Rich<T::Rich, M>, MergeMetaError> {
+    align_tree_meta(&self.value, &mut self.meta);
+    self.deep_merge_meta()
+  }
+}
+
+impl<M, T, const N: usize> AlignMeta<M> for [T; N]
+where
+  T: AlignMeta<M>,
+  M: RuntimeMeta,
+{
+  fn align_meta(&self, meta: &mut Self::TreeMeta) {
+    for (value, meta) in self.iter().zip(meta.iter_mut()) {
+      align_tree_meta(value, meta);
+    }
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<M, T> AlignMeta<M> for alloc::vec::Vec<T>
+where
+  T: AlignMeta<M>,
+  M: RuntimeMeta,
+{
+  fn align_meta(&self, meta: &mut Self::TreeMeta) {
+    meta.truncate(self.len());
+    for (value, meta) in self.iter().zip(meta.iter_mut()) {
+      align_tree_meta(value, meta);
+    }
+    let len = meta.len();
+    meta.extend(self[len..].iter().map(synthetic_tree_meta));
+  }
+}
+
+impl<M, T> AlignMeta<M> for Option<T>
+where
+  T: AlignMeta<M>,
+  M: RuntimeMeta,
+{
+  fn align_meta(&self, meta: &mut Self::TreeMeta) {
+    match (self, meta.as_mut()) {
+      (Some(value), Some(meta)) => align_tree_meta(value, meta),
+      (Some(value), None) => *meta = Some(synthetic_tree_meta(value)),
+      (None, _) => *meta = None,
+    }
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<M, T> AlignMeta<M> for alloc::boxed::Box<T>
+where
+  T: AlignMeta<M>,
+  M: RuntimeMeta,
+{
+  fn align_meta(&self, meta: &mut Self::TreeMeta) {
+    align_tree_meta(&**self, meta);
+  }
+}
+
+#[cfg(feature = "std")]
+impl<M, K, V, S> AlignMeta<M> for std::collections::HashMap<K, V, S>
+where
+  K: Clone + Eq + core::hash::Hash + SyntheticMeta<M>,
+  V: AlignMeta<M>,
+  M: RuntimeMeta,
+  S: Default + core::hash::BuildHasher,
+{
+  fn align_meta(&self, meta: &mut Self::TreeMeta) {
+    meta.retain(|key, _| self.contains_key(key));
+    for (key, value) in self {
+      match meta.get_mut(key) {
+        Some(entry) => align_tree_meta(value, &mut entry.value),
+        None => {
+          let entry = crate::EntryMeta::new(synthetic_tree_meta(key), synthetic_tree_meta(value));
+          meta.insert(key.clone(), entry);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<M, K, V> AlignMeta<M> for alloc::collections::BTreeMap<K, V>
+where
+  K: Clone + Ord + SyntheticMeta<M>,
+  V: AlignMeta<M>,
+  M: RuntimeMeta,
+{
+  fn align_meta(&self, meta: &mut Self::TreeMeta) {
+    meta.retain(|key, _| self.contains_key(key));
+    for (key, value) in self {
+      match meta.get_mut(key) {
+        Some(entry) => align_tree_meta(value, &mut entry.value),
+        None => {
+          let entry = crate::EntryMeta::new(synthetic_tree_meta(key), synthetic_tree_meta(value));
+          meta.insert(key.clone(), entry);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+  use super::*;
+  use crate::{EntryMeta, MetaId};
+  use alloc::collections::BTreeMap;
+  use alloc::vec;
+  use alloc::vec::Vec;
+
+  fn leaf(id: usize) -> TreeMeta<Option<MetaId>, ()> {
+    TreeMeta::new(Some(MetaId::from_usize(id)), ())
+  }
+
+  #[test]
+  fn merge_partial_vec() {
+    let ports: Vec<Option<u16>> = vec![Some(80), Some(443), None];
+    let meta = TreeMeta::new(None, vec![TreeMeta::new(Some(MetaId::from_usize(1)), None)]);
+
+    let rich = Rich::new(ports, meta).deep_merge_partial_meta();
+
+    assert_eq!(
+      rich,
+      Ok(Rich::new(
+        vec![
+          Rich::new(Some(Rich::new(80, None)), Some(MetaId::from_usize(1))),
+          Rich::new(Some(Rich::new(443, None)), None),
+          Rich::new(None, None),
+        ],
+        None
+      ))
+    );
+  }
+
+  #[test]
+  fn merge_partial_btree_map() {
+    let stock = BTreeMap::from([("krill", 500u32), ("fish", 20)]);
+    let meta = TreeMeta::new(
+      Some(MetaId::from_usize(0)),
+      BTreeMap::from([
+        ("krill", EntryMeta::new(leaf(1), leaf(2))),
+        ("squid", EntryMeta::new(leaf(3), leaf(4))),
+      ]),
+    );
+
+    let rich = Rich::new(stock, meta)
+      .deep_merge_partial_meta()
+      .expect("merging succeeds");
+
+    assert_eq!(rich.meta, Some(MetaId::from_usize(0)));
+    assert_eq!(
+      rich.value,
+      BTreeMap::from([
+        ("fish", crate::RichEntry::new(None, Rich::new(20, None))),
+        (
+          "krill",
+          crate::RichEntry::new(Some(MetaId::from_usize(1)), Rich::new(500, Some(MetaId::from_usize(2))))
+        ),
+      ])
+    );
+  }
+
+  #[test]
+  fn default_meta_is_unknown() {
+    let ports: Vec<u16> = vec![80];
+    let rich = Rich::new(ports, TreeMeta::<Option<MetaId>, _>::default());
+
+    let view = rich.view();
+
+    assert_eq!(view.known_meta(), None);
+    assert_eq!(view.index(0).map(|port| port.known_meta()), Some(None));
+    assert_eq!(
+      rich.deep_merge_meta(),
+      Err(MergeMetaError::LengthMismatch { value: 1, meta: 0 })
+    );
+  }
+}
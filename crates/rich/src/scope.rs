@@ -0,0 +1,268 @@
+//! Scoped allocation of metadata ids.
+//!
+//! A plain [`MetaId`] is only unique within the scope that issued it: ids
+//! coming from two different parses may collide. A [`MetaScope`] issues
+//! [`ScopedMetaId`] values tagged with the scope they belong to, so they can be
+//! checked before being resolved. Two scopes can be merged, in which case the
+//! ids of the merged scope are remapped to fresh ids of the receiving scope.
+
+use crate::{MetaId, Rich};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counter used to issue process-wide unique scope ids.
+static NEXT_SCOPE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Opaque identifier for a [`MetaScope`], unique within the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScopeId(usize);
+
+/// Metadata id tagged with the scope that issued it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScopedMetaId {
+  scope: ScopeId,
+  id: MetaId,
+}
+
+impl ScopedMetaId {
+  /// Retrieve the id of the scope which issued this metadata id.
+  pub const fn scope(self) -> ScopeId {
+    self.scope
+  }
+
+  /// Retrieve the metadata id, without checking its scope.
+  ///
+  /// Prefer [`MetaScope::check`] when the scope is available.
+  pub const fn unscoped(self) -> MetaId {
+    self.id
+  }
+}
+
+/// Error when issuing, checking or remapping scoped metadata ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetaScopeError {
+  /// All the metadata ids of the scope were already issued.
+  IdOverflow,
+  /// All the scope ids of the process were already issued.
+  ScopeOverflow,
+  /// The metadata id was issued by another scope.
+  ScopeMismatch {
+    /// Scope expected by the operation
+    expected: ScopeId,
+    /// Scope of the metadata id
+    actual: ScopeId,
+  },
+  /// The metadata id has the expected scope, but was not issued by it.
+  UnknownId(MetaId),
+}
+
+impl core::fmt::Display for MetaScopeError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      Self::IdOverflow => f.write_str("metadata id overflow: all the ids of the scope were issued"),
+      Self::ScopeOverflow => f.write_str("scope id overflow: all the scope ids of the process were issued"),
+      Self::ScopeMismatch { expected, actual } => {
+        write!(
+          f,
+          "scope mismatch for metadata id: expected {expected:?}, got {actual:?}"
+        )
+      }
+      Self::UnknownId(id) => write!(f, "metadata id {id:?} was not issued by its scope"),
+    }
+  }
+}
+
+impl core::error::Error for MetaScopeError {}
+
+/// Allocator issuing metadata ids tagged with a unique scope.
+///
+/// The ids are issued sequentially, starting from `0`. Running out of ids is
+/// reported as [`MetaScopeError::IdOverflow`] instead of reusing ids.
+///
+/// This type is intentionally not `Clone`: two copies of the scope would issue
+/// the same ids.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MetaScope {
+  id: ScopeId,
+  next_id: usize,
+}
+
+impl MetaScope {
+  /// Create a new scope, with a scope id distinct from all the scopes created
+  /// before it.
+  pub fn new() -> Result<Self, MetaScopeError> {
+    let id = NEXT_SCOPE_ID
+      .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| id.checked_add(1))
+      .map_err(|_| MetaScopeError::ScopeOverflow)?;
+    Ok(Self {
+      id: ScopeId(id),
+      next_id: 0,
+    })
+  }
+
+  pub const fn id(&self) -> ScopeId {
+    self.id
+  }
+
+  /// Number of metadata ids issued by this scope.
+  pub const fn len(&self) -> usize {
+    self.next_id
+  }
+
+  pub const fn is_empty(&self) -> bool {
+    self.next_id == 0
+  }
+
+  /// Issue a new metadata id.
+  pub fn issue(&mut self) -> Result<ScopedMetaId, MetaScopeError> {
+    let id = self.next_id;
+    self.next_id = id.checked_add(1).ok_or(MetaScopeError::IdOverflow)?;
+    Ok(ScopedMetaId {
+      scope: self.id,
+      id: MetaId::from_usize(id),
+    })
+  }
+
+  /// Attach a new metadata id to `value`.
+  pub fn attach<T>(&mut self, value: T) -> Result<Rich<T, ScopedMetaId>, MetaScopeError> {
+    Ok(Rich::new(value, self.issue()?))
+  }
+
+  /// Test if `id` was issued by this scope.
+  pub fn contains(&self, id: ScopedMetaId) -> bool {
+    self.check(id).is_ok()
+  }
+
+  /// Check that `id` was issued by this scope and return the inner metadata
+  /// id.
+  pub fn check(&self, id: ScopedMetaId) -> Result<MetaId, MetaScopeError> {
+    if id.scope != self.id {
+      return Err(MetaScopeError::ScopeMismatch {
+        expected: self.id,
+        actual: id.scope,
+      });
+    }
+    if id.id.into_usize() >= self.next_id {
+      return Err(MetaScopeError::UnknownId(id.id));
+    }
+    Ok(id.id)
+  }
+
+  /// Merge `other` into this scope.
+  ///
+  /// A fresh range of ids is reserved in this scope for all the ids issued by
+  /// `other`. The returned [`ScopeRemap`] converts the ids of `other` into the
+  /// corresponding ids of this scope.
+  pub fn merge(&mut self, other: MetaScope) -> Result<ScopeRemap, MetaScopeError> {
+    let offset = self.next_id;
+    self.next_id = offset.checked_add(other.next_id).ok_or(MetaScopeError::IdOverflow)?;
+    Ok(ScopeRemap {
+      from: other.id,
+      to: self.id,
+      offset,
+      len: other.next_id,
+    })
+  }
+}
+
+/// Conversion of the ids of a merged scope into the ids of the scope it was
+/// merged into.
+///
+/// Returned by [`MetaScope::merge`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScopeRemap {
+  from: ScopeId,
+  to: ScopeId,
+  offset: usize,
+  len: usize,
+}
+
+impl ScopeRemap {
+  /// Id of the merged scope.
+  pub const fn from(&self) -> ScopeId {
+    self.from
+  }
+
+  /// Id of the scope receiving the merged ids.
+  pub const fn to(&self) -> ScopeId {
+    self.to
+  }
+
+  /// Convert an id of the merged scope into the corresponding id of the
+  /// receiving scope.
+  pub fn remap(&self, id: ScopedMetaId) -> Result<ScopedMetaId, MetaScopeError> {
+    if id.scope != self.from {
+      return Err(MetaScopeError::ScopeMismatch {
+        expected: self.from,
+        actual: id.scope,
+      });
+    }
+    let index = id.id.into_usize();
+    if index >= self.len {
+      return Err(MetaScopeError::UnknownId(id.id));
+    }
+    Ok(ScopedMetaId {
+      scope: self.to,
+      id: MetaId::from_usize(self.offset + index),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn scopes_do_not_collide() {
+    let mut first = MetaScope::new().unwrap();
+    let mut second = MetaScope::new().unwrap();
+    let first_id = first.issue().unwrap();
+    let second_id = second.issue().unwrap();
+
+    assert_eq!(first_id.unscoped(), second_id.unscoped());
+    assert_ne!(first_id, second_id);
+    assert_eq!(first.check(first_id), Ok(MetaId::from_usize(0)));
+    assert_eq!(
+      first.check(second_id),
+      Err(MetaScopeError::ScopeMismatch {
+        expected: first.id(),
+        actual: second.id(),
+      })
+    );
+  }
+
+  #[test]
+  fn merge_remaps_ids() {
+    let mut main = MetaScope::new().unwrap();
+    let mut other = MetaScope::new().unwrap();
+    let main_port = main.attach(8080u16).unwrap();
+    let other_port = other.attach(443u16).unwrap();
+    let other_host = other.attach("localhost").unwrap();
+
+    let remap = main.merge(other).unwrap();
+    let other_port = remap.remap(other_port.meta).unwrap();
+    let other_host = remap.remap(other_host.meta).unwrap();
+
+    assert_eq!(main.len(), 3);
+    assert_eq!(main.check(main_port.meta), Ok(MetaId::from_usize(0)));
+    assert_eq!(main.check(other_port), Ok(MetaId::from_usize(1)));
+    assert_eq!(main.check(other_host), Ok(MetaId::from_usize(2)));
+    assert_eq!(
+      remap.remap(main_port.meta),
+      Err(MetaScopeError::ScopeMismatch {
+        expected: remap.from(),
+        actual: main.id(),
+      })
+    );
+    assert_eq!(main.issue().unwrap().unscoped(), MetaId::from_usize(3));
+  }
+
+  #[test]
+  fn overflow_is_an_error() {
+    let mut scope = MetaScope::new().unwrap();
+    scope.next_id = usize::MAX - 1;
+
+    assert_eq!(scope.issue().unwrap().unscoped(), MetaId::from_usize(usize::MAX - 1));
+    assert_eq!(scope.issue(), Err(MetaScopeError::IdOverflow));
+    assert_eq!(scope.issue(), Err(MetaScopeError::IdOverflow));
+  }
+}
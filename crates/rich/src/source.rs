@@ -0,0 +1,333 @@
+//! Source locations.
+//!
+//! Positions in the source text are stored as byte offsets ([`ByteSpan`]), and
+//! converted on demand into lines and columns ([`LineCol`]) using the
+//! [`SourceFile`] they refer to. Columns can be counted in UTF-8 bytes or in
+//! UTF-16 code units, as expected by most editors.
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+/// Opaque identifier for a source file.
+///
+/// The value is unique within the [`SourceMap`] which issued it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceId(usize);
+
+impl SourceId {
+  /// Retrieve the inner `usize` from this `SourceId`.
+  pub const fn into_usize(self) -> usize {
+    self.0
+  }
+
+  /// Wrap the provided `usize` into a `SourceId`.
+  pub const fn from_usize(value: usize) -> Self {
+    Self(value)
+  }
+}
+
+/// Range of bytes in a source text, with an inclusive start and an exclusive
+/// end.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSpan {
+  pub start: usize,
+  pub end: usize,
+}
+
+impl ByteSpan {
+  /// Create a new `ByteSpan`.
+  ///
+  /// Panics if `start` is greater than `end`.
+  pub const fn new(start: usize, end: usize) -> Self {
+    assert!(start <= end, "span start must not be greater than its end");
+    Self { start, end }
+  }
+
+  /// Create an empty `ByteSpan` at `offset`.
+  pub const fn empty(offset: usize) -> Self {
+    Self {
+      start: offset,
+      end: offset,
+    }
+  }
+
+  /// Length of the span in bytes.
+  ///
+  /// The fields are public, so a span built without [`ByteSpan::new`] may
+  /// have `start` greater than `end`: it is then considered empty.
+  pub const fn len(self) -> usize {
+    self.end.saturating_sub(self.start)
+  }
+
+  pub const fn is_empty(self) -> bool {
+    self.len() == 0
+  }
+
+  /// Test if `offset` is inside this span.
+  pub const fn contains(self, offset: usize) -> bool {
+    self.start <= offset && offset < self.end
+  }
+
+  /// Smallest span covering both `self` and `other`.
+  pub fn join(self, other: Self) -> Self {
+    Self {
+      start: self.start.min(other.start),
+      end: self.end.max(other.end),
+    }
+  }
+}
+
+impl fmt::Display for ByteSpan {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}..{}", self.start, self.end)
+  }
+}
+
+/// Span in a given source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+  pub source: SourceId,
+  pub span: ByteSpan,
+}
+
+impl Location {
+  /// Create a new `Location`.
+  pub const fn new(source: SourceId, span: ByteSpan) -> Self {
+    Self { source, span }
+  }
+}
+
+/// Unit used to count columns.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColumnMode {
+  /// Count columns in UTF-8 bytes.
+  #[default]
+  Utf8,
+  /// Count columns in UTF-16 code units, as used by the Language Server
+  /// Protocol.
+  Utf16,
+}
+
+/// Position in a source text, as a line and column.
+///
+/// Both the line and column are zero-based, the display representation is
+/// one-based.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+  pub line: usize,
+  pub column: usize,
+}
+
+impl LineCol {
+  /// Create a new `LineCol`.
+  pub const fn new(line: usize, column: usize) -> Self {
+    Self { line, column }
+  }
+}
+
+impl fmt::Display for LineCol {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}", self.line + 1, self.column + 1)
+  }
+}
+
+/// Source file, with its name and contents.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+  name: String,
+  text: String,
+  /// Byte offset of the start of each line.
+  line_starts: Vec<usize>,
+}
+
+#[cfg(feature = "alloc")]
+impl SourceFile {
+  /// Create a new `SourceFile`.
+  ///
+  /// Lines are separated by `\n`. A `\r` preceding it is part of the line.
+  pub fn new(name: impl Into<String>, text: impl Into<String>) -> Self {
+    let text = text.into();
+    let line_starts = core::iter::once(0)
+      .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+      .collect();
+    Self {
+      name: name.into(),
+      text,
+      line_starts,
+    }
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+
+  pub fn line_count(&self) -> usize {
+    self.line_starts.len()
+  }
+
+  /// Span of the line at index `line`, excluding its `\n` terminator.
+  pub fn line_span(&self, line: usize) -> Option<ByteSpan> {
+    let start = *self.line_starts.get(line)?;
+    let end = match self.line_starts.get(line + 1) {
+      Some(next) => next - 1,
+      None => self.text.len(),
+    };
+    Some(ByteSpan::new(start, end))
+  }
+
+  /// Retrieve the text for `span`, or `None` if it is out of bounds or does not
+  /// fall on character boundaries.
+  pub fn slice(&self, span: ByteSpan) -> Option<&str> {
+    self.text.get(span.start..span.end)
+  }
+
+  /// Convert a byte offset into a line and column.
+  ///
+  /// Returns `None` if `offset` is out of bounds or is not on a character
+  /// boundary. The end of the text is a valid offset.
+  pub fn line_col(&self, offset: usize, mode: ColumnMode) -> Option<LineCol> {
+    if !self.text.is_char_boundary(offset) {
+      return None;
+    }
+    let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+    let start = self.line_starts[line];
+    let column = match mode {
+      ColumnMode::Utf8 => offset - start,
+      ColumnMode::Utf16 => self.text[start..offset].encode_utf16().count(),
+    };
+    Some(LineCol::new(line, column))
+  }
+
+  /// Convert a line and column into a byte offset.
+  ///
+  /// This is the inverse of [`SourceFile::line_col`]. Returns `None` if the
+  /// position is past the end of its line or is not on a character boundary.
+  pub fn offset(&self, position: LineCol, mode: ColumnMode) -> Option<usize> {
+    let line = self.line_span(position.line)?;
+    let text = &self.text[line.start..line.end];
+    let column = match mode {
+      ColumnMode::Utf8 => text.is_char_boundary(position.column).then_some(position.column)?,
+      ColumnMode::Utf16 => {
+        let mut units = 0;
+        let mut column = None;
+        for (index, c) in text.char_indices().chain(core::iter::once((text.len(), '\0'))) {
+          if units == position.column {
+            column = Some(index);
+            break;
+          }
+          if units > position.column {
+            break;
+          }
+          units += c.len_utf16();
+        }
+        column?
+      }
+    };
+    Some(line.start + column)
+  }
+}
+
+/// Registry of source files.
+///
+/// Source ids are issued sequentially, starting from `0`.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+  files: Vec<SourceFile>,
+}
+
+#[cfg(feature = "alloc")]
+impl SourceMap {
+  /// Create an empty `SourceMap`.
+  pub const fn new() -> Self {
+    Self { files: Vec::new() }
+  }
+
+  /// Register a new source file, and return its id.
+  pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) -> SourceId {
+    let id = SourceId(self.files.len());
+    self.files.push(SourceFile::new(name, text));
+    id
+  }
+
+  /// Retrieve the source file for `id`, or `None` if `id` was not issued by
+  /// this map.
+  pub fn get(&self, id: SourceId) -> Option<&SourceFile> {
+    self.files.get(id.0)
+  }
+
+  /// Iterate over the source files, in the order of their ids.
+  pub fn iter(&self) -> impl Iterator<Item = (SourceId, &SourceFile)> {
+    self
+      .files
+      .iter()
+      .enumerate()
+      .map(|(index, file)| (SourceId(index), file))
+  }
+
+  /// Retrieve the text at `location`.
+  pub fn slice(&self, location: Location) -> Option<&str> {
+    self.get(location.source)?.slice(location.span)
+  }
+
+  /// Convert the start and end of `location` into lines and columns.
+  pub fn line_cols(&self, location: Location, mode: ColumnMode) -> Option<(LineCol, LineCol)> {
+    let file = self.get(location.source)?;
+    Some((
+      file.line_col(location.span.start, mode)?,
+      file.line_col(location.span.end, mode)?,
+    ))
+  }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+  use super::*;
+  use alloc::string::ToString;
+
+  #[test]
+  fn line_col_round_trip() {
+    let file = SourceFile::new("config.toml", "port = 8080\r\nhost = \"hé🦀\"\n");
+
+    assert_eq!(file.line_count(), 3);
+    assert_eq!(file.line_span(1), Some(ByteSpan::new(13, 29)));
+    assert_eq!(file.line_col(0, ColumnMode::Utf8), Some(LineCol::new(0, 0)));
+    assert_eq!(file.line_col(13, ColumnMode::Utf8), Some(LineCol::new(1, 0)));
+    assert_eq!(file.line_col(30, ColumnMode::Utf8), Some(LineCol::new(2, 0)));
+    assert_eq!(file.line_col(31, ColumnMode::Utf8), None);
+
+    // Offset of the closing quote, after a 2-byte and a 4-byte character
+    let offset = 13 + "host = \"hé🦀".len();
+    assert_eq!(file.line_col(offset, ColumnMode::Utf8), Some(LineCol::new(1, 15)));
+    assert_eq!(file.line_col(offset, ColumnMode::Utf16), Some(LineCol::new(1, 12)));
+    assert_eq!(file.offset(LineCol::new(1, 15), ColumnMode::Utf8), Some(offset));
+    assert_eq!(file.offset(LineCol::new(1, 12), ColumnMode::Utf16), Some(offset));
+    // Inside the crab, which is a surrogate pair in UTF-16
+    assert_eq!(file.line_col(offset - 1, ColumnMode::Utf8), None);
+    assert_eq!(file.offset(LineCol::new(1, 11), ColumnMode::Utf16), None);
+    assert_eq!(file.offset(LineCol::new(0, 13), ColumnMode::Utf8), None);
+  }
+
+  #[test]
+  fn source_map_locations() {
+    let mut sources = SourceMap::new();
+    let base = sources.add("base.toml", "port = 8080\n");
+    let local = sources.add("local.toml", "\nport = 443\n");
+    let port = Location::new(local, ByteSpan::new(8, 11));
+
+    assert_ne!(base, local);
+    assert_eq!(sources.get(base).map(SourceFile::name), Some("base.toml"));
+    assert_eq!(sources.slice(port), Some("443"));
+    assert_eq!(
+      sources.line_cols(port, ColumnMode::Utf8),
+      Some((LineCol::new(1, 7), LineCol::new(1, 10)))
+    );
+    assert_eq!(LineCol::new(1, 7).to_string(), "2:8");
+  }
+}
@@ -0,0 +1,260 @@
+//! Side-table storage for metadata, keyed by [`MetaId`].
+//!
+//! A [`MetaStore`] issues a [`MetaId`] for each metadata entry it receives.
+//! Values then only carry the cheap id, and the actual information is resolved
+//! through the store when needed, for example when printing a location.
+//!
+//! Besides its main data, each entry may hold extensions of any type: spans,
+//! source files, comments or origins can be attached to the same id without
+//! having to be known in advance.
+
+use crate::{MetaId, Rich};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::fmt;
+
+/// Arena storing metadata of type `D`, along with typed extensions.
+///
+/// The ids are issued sequentially, starting from `0`. They are unique within
+/// the store that issued them, and must not be used with another store.
+pub struct MetaStore<D> {
+  entries: Vec<D>,
+  /// Extension sub-stores, each of type `BTreeMap<MetaId, E>` for the
+  /// extension type `E` used as the key.
+  extensions: BTreeMap<TypeId, Box<dyn Any>>,
+}
+
+impl<D> MetaStore<D> {
+  /// Create an empty `MetaStore`.
+  pub const fn new() -> Self {
+    Self {
+      entries: Vec::new(),
+      extensions: BTreeMap::new(),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// Store `data` and return the id issued for it.
+  pub fn insert(&mut self, data: D) -> MetaId {
+    let id = MetaId::from_usize(self.entries.len());
+    self.entries.push(data);
+    id
+  }
+
+  /// Retrieve the data stored for `id`, or `None` if `id` was not issued by
+  /// this store.
+  pub fn get(&self, id: MetaId) -> Option<&D> {
+    self.entries.get(id.into_usize())
+  }
+
+  /// Exclusively borrow the data stored for `id`, or `None` if `id` was not
+  /// issued by this store.
+  pub fn get_mut(&mut self, id: MetaId) -> Option<&mut D> {
+    self.entries.get_mut(id.into_usize())
+  }
+
+  /// Iterate over the stored data, in the order of their ids.
+  pub fn iter(&self) -> impl Iterator<Item = (MetaId, &D)> {
+    self
+      .entries
+      .iter()
+      .enumerate()
+      .map(|(index, data)| (MetaId::from_usize(index), data))
+  }
+
+  /// Create a handle resolving the metadata for `id` on access.
+  pub const fn meta_ref(&self, id: MetaId) -> MetaRef<'_, D> {
+    MetaRef { store: self, id }
+  }
+
+  /// Attach the extension `value` to `id`, and return the previous extension
+  /// of the same type.
+  ///
+  /// Fails and gives `value` back if `id` was not issued by this store.
+  pub fn insert_extension<E: Any>(&mut self, id: MetaId, value: E) -> Result<Option<E>, E> {
+    if id.into_usize() >= self.len() {
+      return Err(value);
+    }
+    let previous = self
+      .extensions
+      .entry(TypeId::of::<E>())
+      .or_insert_with(|| Box::new(BTreeMap::<MetaId, E>::new()))
+      .downcast_mut::<BTreeMap<MetaId, E>>()
+      .expect("extension sub-stores are indexed by their type")
+      .insert(id, value);
+    Ok(previous)
+  }
+
+  /// Retrieve the extension of type `E` attached to `id`.
+  pub fn extension<E: Any>(&self, id: MetaId) -> Option<&E> {
+    self.extensions::<E>()?.get(&id)
+  }
+
+  /// Exclusively borrow the extension of type `E` attached to `id`.
+  pub fn extension_mut<E: Any>(&mut self, id: MetaId) -> Option<&mut E> {
+    self
+      .extensions
+      .get_mut(&TypeId::of::<E>())?
+      .downcast_mut::<BTreeMap<MetaId, E>>()
+      .expect("extension sub-stores are indexed by their type")
+      .get_mut(&id)
+  }
+
+  /// Detach the extension of type `E` from `id` and return it.
+  pub fn remove_extension<E: Any>(&mut self, id: MetaId) -> Option<E> {
+    self
+      .extensions
+      .get_mut(&TypeId::of::<E>())?
+      .downcast_mut::<BTreeMap<MetaId, E>>()
+      .expect("extension sub-stores are indexed by their type")
+      .remove(&id)
+  }
+
+  /// Borrow the sub-store holding all the extensions of type `E`.
+  pub fn extensions<E: Any>(&self) -> Option<&BTreeMap<MetaId, E>> {
+    let extensions = self.extensions.get(&TypeId::of::<E>())?;
+    Some(
+      extensions
+        .downcast_ref::<BTreeMap<MetaId, E>>()
+        .expect("extension sub-stores are indexed by their type"),
+    )
+  }
+}
+
+impl<D> Default for MetaStore<D> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<D: fmt::Debug> fmt::Debug for MetaStore<D> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("MetaStore")
+      .field("entries", &self.entries)
+      .field("extensions", &self.extensions.len())
+      .finish()
+  }
+}
+
+/// Handle to the metadata for a [`MetaId`], resolved through its store on
+/// access.
+///
+/// This is a pair of a reference and an id, so it is cheap to copy and can be
+/// used as the metadata of a `Rich<&T, MetaRef<'store, D>>`.
+pub struct MetaRef<'store, D> {
+  store: &'store MetaStore<D>,
+  id: MetaId,
+}
+
+impl<'store, D> MetaRef<'store, D> {
+  pub const fn id(&self) -> MetaId {
+    self.id
+  }
+
+  pub const fn store(&self) -> &'store MetaStore<D> {
+    self.store
+  }
+
+  /// Retrieve the data stored for this id.
+  pub fn get(&self) -> Option<&'store D> {
+    self.store.get(self.id)
+  }
+
+  /// Retrieve the extension of type `E` attached to this id.
+  pub fn extension<E: Any>(&self) -> Option<&'store E> {
+    self.store.extension(self.id)
+  }
+}
+
+impl<D> Clone for MetaRef<'_, D> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<D> Copy for MetaRef<'_, D> {}
+
+impl<D: fmt::Debug> fmt::Debug for MetaRef<'_, D> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("MetaRef")
+      .field("id", &self.id)
+      .field("data", &self.get())
+      .finish()
+  }
+}
+
+impl<T> Rich<T, MetaId> {
+  /// Borrow the value, with a handle resolving its metadata through `store`.
+  pub const fn with_store<'store, D>(&self, store: &'store MetaStore<D>) -> Rich<&T, MetaRef<'store, D>> {
+    Rich::new(&self.value, store.meta_ref(self.meta))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  struct Span {
+    start: usize,
+    end: usize,
+  }
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  struct Comment(&'static str);
+
+  #[test]
+  fn insert_and_resolve() {
+    let mut store = MetaStore::new();
+    let port_id = store.insert(Span { start: 7, end: 11 });
+    let host_id = store.insert(Span { start: 20, end: 31 });
+    store
+      .insert_extension(host_id, Comment("public hostname"))
+      .expect("the id was issued by the store");
+
+    let port = Rich::new(8080u16, port_id);
+    let port = port.with_store(&store);
+
+    assert_eq!(*port.value, 8080);
+    assert_eq!(port.meta.get(), Some(&Span { start: 7, end: 11 }));
+    assert_eq!(port.meta.extension::<Comment>(), None);
+    assert_eq!(store.meta_ref(host_id).extension(), Some(&Comment("public hostname")));
+    assert_eq!(store.get(MetaId::from_usize(2)), None);
+  }
+
+  #[test]
+  fn replace_extension() {
+    let mut store = MetaStore::new();
+    let id = store.insert(());
+
+    assert_eq!(store.insert_extension(id, Comment("first")), Ok(None));
+    assert_eq!(
+      store.insert_extension(id, Comment("second")),
+      Ok(Some(Comment("first")))
+    );
+    assert_eq!(store.remove_extension::<Comment>(id), Some(Comment("second")));
+    assert_eq!(store.extension::<Comment>(id), None);
+  }
+
+  #[test]
+  fn extension_for_unknown_id() {
+    let mut store = MetaStore::new();
+    store.insert(());
+    let unknown = MetaId::from_usize(1);
+
+    assert_eq!(
+      store.insert_extension(unknown, Comment("orphan")),
+      Err(Comment("orphan"))
+    );
+    assert_eq!(store.extension::<Comment>(unknown), None);
+  }
+}
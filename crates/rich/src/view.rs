@@ -0,0 +1,375 @@
+//! Typed views pairing values with their external metadata.
+//!
+//! A [`View`] borrows a value along with its `TreeMeta`, and can step into the
+//! components of the value while keeping track of the matching metadata. This
+//! provides the ergonomics of internal metadata while the value and metadata
+//! are stored separately.
+//!
+//! Metadata may be missing, for example if a value was modified after its
+//! metadata was split off. Views stay usable in this case: the metadata of the
+//! view and of all its components is `None`.
+//!
+//! Each type picks its view through [`ViewMeta`]: collections use `View`
+//! directly and provide `index` or `get` methods, while structs and enums
+//! deriving `MetaView` get a dedicated view type with one method per field.
+
+use crate::{DiscriminantMeta, EnumMetaFor, EnumTreeMeta, Rich, TreeMeta, TreeMetaProjection};
+
+/// Borrowed value of type `T`, along with its metadata if available.
+pub struct View<'a, T, M>
+where
+  T: TreeMetaProjection<M> + ?Sized,
+{
+  value: &'a T,
+  meta: Option<&'a TreeMeta<M, T::TreeMeta>>,
+}
+
+impl<'a, T, M> View<'a, T, M>
+where
+  T: TreeMetaProjection<M> + ?Sized,
+{
+  /// Create a new `View`.
+  pub const fn new(value: &'a T, meta: Option<&'a TreeMeta<M, T::TreeMeta>>) -> Self {
+    Self { value, meta }
+  }
+
+  pub const fn value(&self) -> &'a T {
+    self.value
+  }
+
+  /// Metadata for the value itself.
+  pub fn meta(&self) -> Option<&'a M> {
+    self.meta.map(TreeMeta::meta)
+  }
+
+  /// Metadata for the value itself, or `default` if it is missing.
+  pub fn meta_or(&self, default: &'a M) -> &'a M {
+    self.meta().unwrap_or(default)
+  }
+
+  /// Metadata for the components of the value.
+  pub fn nested(&self) -> Option<&'a T::TreeMeta> {
+    self.meta.map(TreeMeta::nested)
+  }
+
+  pub const fn tree_meta(&self) -> Option<&'a TreeMeta<M, T::TreeMeta>> {
+    self.meta
+  }
+
+  /// Pair the value with the metadata for the value itself.
+  pub fn rich(&self) -> Rich<&'a T, Option<&'a M>> {
+    Rich::new(self.value, self.meta())
+  }
+
+  /// Convert this view into the view type selected by `T`.
+  pub fn typed(self) -> T::View<'a>
+  where
+    T: ViewMeta<M>,
+  {
+    self.into()
+  }
+}
+
+impl<T, M> Clone for View<'_, T, M>
+where
+  T: TreeMetaProjection<M> + ?Sized,
+{
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T, M> Copy for View<'_, T, M> where T: TreeMetaProjection<M> + ?Sized {}
+
+impl<T, M> core::fmt::Debug for View<'_, T, M>
+where
+  T: TreeMetaProjection<M> + core::fmt::Debug + ?Sized,
+  M: core::fmt::Debug,
+  T::TreeMeta: core::fmt::Debug,
+{
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.debug_struct("View")
+      .field("value", &self.value)
+      .field("meta", &self.meta)
+      .finish()
+  }
+}
+
+/// Types providing a typed view over their value and external metadata.
+///
+/// Primitives and collections use [`View`] as their view type. Structs and
+/// enums deriving `MetaView` use a generated view type wrapping `View`, with
+/// one method per field.
+pub trait ViewMeta<M>: TreeMetaProjection<M> {
+  type View<'a>: From<View<'a, Self, M>>
+  where
+    Self: 'a,
+    M: 'a;
+}
+
+impl<T, M> Rich<T, TreeMeta<M, T::TreeMeta>>
+where
+  T: ViewMeta<M>,
+{
+  /// Borrow the value along with its external metadata, as a typed view.
+  pub fn view(&self) -> T::View<'_> {
+    View::new(&self.value, Some(&self.meta)).typed()
+  }
+}
+
+/// View the element at `index` of `values`, with the metadata at the same
+/// index if available.
+fn index_view<'a, T, M>(
+  values: &'a [T],
+  metas: Option<&'a [TreeMeta<M, T::TreeMeta>]>,
+  index: usize,
+) -> Option<T::View<'a>>
+where
+  T: ViewMeta<M>,
+{
+  let value = values.get(index)?;
+  let meta = metas.and_then(|metas| metas.get(index));
+  Some(View::new(value, meta).typed())
+}
+
+impl<M, T, const N: usize> ViewMeta<M> for [T; N]
+where
+  T: TreeMetaProjection<M>,
+{
+  type View<'a>
+    = View<'a, Self, M>
+  where
+    Self: 'a,
+    M: 'a;
+}
+
+impl<'a, T, M, const N: usize> View<'a, [T; N], M>
+where
+  T: ViewMeta<M>,
+{
+  /// View the element at `index`, or `None` if it is out of bounds.
+  pub fn index(&self, index: usize) -> Option<T::View<'a>> {
+    index_view(self.value, self.nested().map(|metas| metas.as_slice()), index)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = T::View<'a>> + use<'a, T, M, N> {
+    let view = *self;
+    (0..N).filter_map(move |index| view.index(index))
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<M, T> ViewMeta<M> for alloc::vec::Vec<T>
+where
+  T: TreeMetaProjection<M>,
+{
+  type View<'a>
+    = View<'a, Self, M>
+  where
+    Self: 'a,
+    M: 'a;
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T, M> View<'a, alloc::vec::Vec<T>, M>
+where
+  T: ViewMeta<M>,
+{
+  pub fn len(&self) -> usize {
+    self.value.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
+  }
+
+  /// View the element at `index`, or `None` if it is out of bounds.
+  ///
+  /// If the metadata is shorter than the value, the extra elements have no
+  /// metadata.
+  pub fn index(&self, index: usize) -> Option<T::View<'a>> {
+    index_view(self.value, self.nested().map(alloc::vec::Vec::as_slice), index)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = T::View<'a>> + use<'a, T, M> {
+    let view = *self;
+    (0..self.value.len()).filter_map(move |index| view.index(index))
+  }
+}
+
+impl<M, T> ViewMeta<M> for Option<T>
+where
+  T: TreeMetaProjection<M>,
+{
+  type View<'a>
+    = View<'a, Self, M>
+  where
+    Self: 'a,
+    M: 'a;
+}
+
+impl<'a, T, M> View<'a, Option<T>, M>
+where
+  T: ViewMeta<M>,
+{
+  /// View the inner value, or `None` if the value is `None`.
+  pub fn inner(&self) -> Option<T::View<'a>> {
+    let value = self.value.as_ref()?;
+    let meta = self.nested().and_then(Option::as_ref);
+    Some(View::new(value, meta).typed())
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<M, T> ViewMeta<M> for alloc::boxed::Box<T>
+where
+  T: TreeMetaProjection<M>,
+{
+  type View<'a>
+    = View<'a, Self, M>
+  where
+    Self: 'a,
+    M: 'a;
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T, M> View<'a, alloc::boxed::Box<T>, M>
+where
+  T: ViewMeta<M>,
+{
+  /// View the boxed value.
+  pub fn inner(&self) -> T::View<'a> {
+    let meta = self.nested().map(|meta| &**meta);
+    View::new(&**self.value, meta).typed()
+  }
+}
+
+#[cfg(feature = "std")]
+impl<M, K, V, S> ViewMeta<M> for std::collections::HashMap<K, V, S>
+where
+  K: TreeMetaProjection<M>,
+  V: TreeMetaProjection<M>,
+{
+  type View<'a>
+    = View<'a, Self, M>
+  where
+    Self: 'a,
+    M: 'a;
+}
+
+#[cfg(feature = "std")]
+impl<'a, K, V, M, S> View<'a, std::collections::HashMap<K, V, S>, M>
+where
+  K: Eq + core::hash::Hash + TreeMetaProjection<M>,
+  V: ViewMeta<M>,
+  S: core::hash::BuildHasher,
+{
+  pub fn len(&self) -> usize {
+    self.value.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
+  }
+
+  /// View the value for `key`, or `None` if the key is missing.
+  pub fn get<Q>(&self, key: &Q) -> Option<V::View<'a>>
+  where
+    K: core::borrow::Borrow<Q>,
+    Q: Eq + core::hash::Hash + ?Sized,
+  {
+    let value = self.value.get(key)?;
+    let meta = self.nested().and_then(|metas| metas.get(key));
+    Some(View::new(value, meta.map(|meta| &meta.value)).typed())
+  }
+
+  /// Metadata for the key `key`.
+  pub fn key_meta<Q>(&self, key: &Q) -> Option<&'a M>
+  where
+    K: core::borrow::Borrow<Q>,
+    Q: Eq + core::hash::Hash + ?Sized,
+  {
+    Some(self.nested()?.get(key)?.key.meta())
+  }
+
+  /// Iterate over the entries, in arbitrary order.
+  pub fn iter(&self) -> impl Iterator<Item = (&'a K, V::View<'a>)> + use<'a, K, V, M, S> {
+    let metas = self.nested();
+    self.value.iter().map(move |(key, value)| {
+      let meta = metas.and_then(|metas| metas.get(key)).map(|meta| &meta.value);
+      (key, View::new(value, meta).typed())
+    })
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<M, K, V> ViewMeta<M> for alloc::collections::BTreeMap<K, V>
+where
+  K: TreeMetaProjection<M>,
+  V: TreeMetaProjection<M>,
+{
+  type View<'a>
+    = View<'a, Self, M>
+  where
+    Self: 'a,
+    M: 'a;
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, K, V, M> View<'a, alloc::collections::BTreeMap<K, V>, M>
+where
+  K: Ord + TreeMetaProjection<M>,
+  V: ViewMeta<M>,
+{
+  pub fn len(&self) -> usize {
+    self.value.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
+  }
+
+  /// View the value for `key`, or `None` if the key is missing.
+  pub fn get<Q>(&self, key: &Q) -> Option<V::View<'a>>
+  where
+    K: core::borrow::Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    let value = self.value.get(key)?;
+    let meta = self.nested().and_then(|metas| metas.get(key));
+    Some(View::new(value, meta.map(|meta| &meta.value)).typed())
+  }
+
+  /// Metadata for the key `key`.
+  pub fn key_meta<Q>(&self, key: &Q) -> Option<&'a M>
+  where
+    K: core::borrow::Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    Some(self.nested()?.get(key)?.key.meta())
+  }
+
+  /// Iterate over the entries, in the order of their keys.
+  pub fn iter(&self) -> impl Iterator<Item = (&'a K, V::View<'a>)> + use<'a, K, V, M> {
+    let metas = self.nested();
+    self.value.iter().map(move |(key, value)| {
+      let meta = metas.and_then(|metas| metas.get(key)).map(|meta| &meta.value);
+      (key, View::new(value, meta).typed())
+    })
+  }
+}
+
+impl<'a, E, M> View<'a, E, M>
+where
+  E: EnumMetaFor<M> + TreeMetaProjection<M, TreeMeta = EnumTreeMeta<E, M>>,
+{
+  /// Metadata for the discriminant of the enum.
+  pub fn discriminant_meta(&self) -> Option<&'a <E::Discriminant as DiscriminantMeta<M>>::Meta> {
+    Some(&self.nested()?.discriminant)
+  }
+
+  /// Metadata for the fields of the variant.
+  pub fn variant_meta(&self) -> Option<&'a E::VariantMeta> {
+    Some(&self.nested()?.variant)
+  }
+}
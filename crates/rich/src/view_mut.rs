@@ -0,0 +1,659 @@
+//! Mutable views keeping values and their external metadata in sync.
+//!
+//! A [`RichMut`] mutably borrows a value along with its `TreeMeta`. Edits made
+//! through it update the metadata as well: elements added to a collection get
+//! synthetic metadata, removed elements drop their metadata, and metadata for
+//! the following elements is shifted the same way as the values.
+//!
+//! Metadata types describe how they represent runtime changes by implementing
+//! [`RuntimeMeta`]. The provided [`Provenance`] wrapper keeps the original
+//! metadata of modified values, and marks values created by the program as
+//! synthetic.
+
+#[cfg(feature = "alloc")]
+use crate::EntryMeta;
+use crate::{EmptyMeta, EnumMeta, TreeMeta, TreeMetaProjection, View};
+
+/// Metadata able to describe values created or modified by the program, as
+/// opposed to values read from some input.
+pub trait RuntimeMeta {
+  /// Metadata for a value created by the program.
+  fn synthetic() -> Self;
+
+  /// Record that the value was modified by the program.
+  fn mark_modified(&mut self);
+}
+
+impl RuntimeMeta for () {
+  fn synthetic() -> Self {}
+
+  fn mark_modified(&mut self) {}
+}
+
+impl RuntimeMeta for EmptyMeta {
+  fn synthetic() -> Self {
+    Self
+  }
+
+  fn mark_modified(&mut self) {}
+}
+
+/// Metadata wrapper tracking whether a value comes from the input or from the
+/// program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Provenance<M> {
+  /// Value read from the input, described by the inner metadata.
+  Input(M),
+  /// Value read from the input and modified at runtime, the inner metadata
+  /// describes the original value.
+  Modified(M),
+  /// Value created at runtime, without any matching input.
+  Synthetic,
+}
+
+impl<M> Provenance<M> {
+  /// Metadata for the value in the input, if it was read from the input.
+  pub const fn input(&self) -> Option<&M> {
+    match self {
+      Self::Input(meta) | Self::Modified(meta) => Some(meta),
+      Self::Synthetic => None,
+    }
+  }
+
+  pub const fn is_modified(&self) -> bool {
+    matches!(self, Self::Modified(_))
+  }
+
+  pub const fn is_synthetic(&self) -> bool {
+    matches!(self, Self::Synthetic)
+  }
+}
+
+impl<M> RuntimeMeta for Provenance<M> {
+  fn synthetic() -> Self {
+    Self::Synthetic
+  }
+
+  fn mark_modified(&mut self) {
+    // Temporarily use `Synthetic` to move the metadata out
+    *self = match core::mem::replace(self, Self::Synthetic) {
+      Self::Input(meta) | Self::Modified(meta) => Self::Modified(meta),
+      Self::Synthetic => Self::Synthetic,
+    };
+  }
+}
+
+/// Types able to build external metadata for values created by the program.
+///
+/// Every node of the metadata tree uses [`RuntimeMeta::synthetic`].
+pub trait SyntheticMeta<M: RuntimeMeta>: TreeMetaProjection<M> {
+  /// Build the nested metadata for the components of `self`.
+  fn synthetic_meta(&self) -> Self::TreeMeta;
+}
+
+/// Build the metadata tree for a value created by the program.
+pub fn synthetic_tree_meta<T, M>(value: &T) -> TreeMeta<M, T::TreeMeta>
+where
+  T: SyntheticMeta<M> + ?Sized,
+  M: RuntimeMeta,
+{
+  TreeMeta::new(M::synthetic(), value.synthetic_meta())
+}
+
+impl<DiscriminantMeta, VariantMeta> EnumMeta<DiscriminantMeta, VariantMeta>
+where
+  DiscriminantMeta: RuntimeMeta,
+{
+  /// Metadata for an enum created by the program, from the synthetic metadata
+  /// for the fields of its active variant.
+  ///
+  /// This is the recommended implementation of `SyntheticMeta` for enums.
+  pub fn synthetic(variant: VariantMeta) -> Self {
+    Self::new(DiscriminantMeta::synthetic(), variant)
+  }
+}
+
+/// Mutably borrowed value of type `T`, along with its metadata if available.
+///
+/// Metadata may be missing, in which case edits only apply to the value.
+pub struct RichMut<'a, T, M>
+where
+  T: TreeMetaProjection<M> + ?Sized,
+{
+  value: &'a mut T,
+  meta: Option<&'a mut TreeMeta<M, T::TreeMeta>>,
+}
+
+impl<'a, T, M> RichMut<'a, T, M>
+where
+  T: TreeMetaProjection<M> + ?Sized,
+{
+  /// Create a new `RichMut`.
+  pub fn new(value: &'a mut T, meta: Option<&'a mut TreeMeta<M, T::TreeMeta>>) -> Self {
+    Self { value, meta }
+  }
+
+  pub fn value(&self) -> &T {
+    self.value
+  }
+
+  /// Metadata for the value itself.
+  pub fn meta(&self) -> Option<&M> {
+    self.meta.as_deref().map(TreeMeta::meta)
+  }
+
+  /// Mutable metadata for the value itself.
+  pub fn meta_mut(&mut self) -> Option<&mut M> {
+    self.meta.as_deref_mut().map(|meta| &mut meta.meta)
+  }
+
+  /// Metadata for the components of the value.
+  pub fn nested(&self) -> Option<&T::TreeMeta> {
+    self.meta.as_deref().map(TreeMeta::nested)
+  }
+
+  /// Borrow as a read-only view.
+  pub fn view(&self) -> View<'_, T, M> {
+    View::new(self.value, self.meta.as_deref())
+  }
+
+  /// Borrow for a shorter lifetime, to keep using `self` afterwards.
+  pub fn reborrow(&mut self) -> RichMut<'_, T, M> {
+    RichMut::new(self.value, self.meta.as_deref_mut())
+  }
+
+  /// Narrow the view to a component of the value, such as a struct field.
+  ///
+  /// `value` and `meta` must select the same component, respectively in the
+  /// value and in the nested metadata.
+  ///
+  /// ```
+  /// use rich::{RichMut, TreeMeta};
+  ///
+  /// let mut point = (1u32, 2u32);
+  /// let mut meta = TreeMeta::new((), (TreeMeta::new((), ()), TreeMeta::new((), ())));
+  /// let mut y = RichMut::new(&mut point, Some(&mut meta)).project(|point| &mut point.1, |meta| &mut meta.1);
+  /// y.set(3);
+  /// assert_eq!(point, (1, 3));
+  /// ```
+  pub fn project<U>(
+    self,
+    value: impl FnOnce(&'a mut T) -> &'a mut U,
+    meta: impl FnOnce(&'a mut T::TreeMeta) -> &'a mut TreeMeta<M, U::TreeMeta>,
+  ) -> RichMut<'a, U, M>
+  where
+    U: TreeMetaProjection<M> + ?Sized,
+  {
+    RichMut::new(value(self.value), self.meta.map(|tree| meta(&mut tree.nested)))
+  }
+
+  /// Mark the value itself as modified by the program.
+  fn mark_modified(&mut self)
+  where
+    M: RuntimeMeta,
+  {
+    if let Some(meta) = self.meta_mut() {
+      meta.mark_modified();
+    }
+  }
+}
+
+impl<T, M> RichMut<'_, T, M>
+where
+  T: SyntheticMeta<M>,
+  M: RuntimeMeta,
+{
+  /// Replace the value, and return the previous one.
+  ///
+  /// The metadata for the value itself is marked as modified, while the
+  /// metadata for its components is replaced by synthetic metadata.
+  pub fn set(&mut self, value: T) -> T {
+    self.mark_modified();
+    if let Some(meta) = self.meta.as_deref_mut() {
+      meta.nested = value.synthetic_meta();
+    }
+    core::mem::replace(self.value, value)
+  }
+}
+
+impl<T, M> core::fmt::Debug for RichMut<'_, T, M>
+where
+  T: TreeMetaProjection<M> + core::fmt::Debug + ?Sized,
+  M: core::fmt::Debug,
+  T::TreeMeta: core::fmt::Debug,
+{
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.debug_struct("RichMut")
+      .field("value", &self.value)
+      .field("meta", &self.meta)
+      .finish()
+  }
+}
+
+impl<M, T, const N: usize> SyntheticMeta<M> for [T; N]
+where
+  T: SyntheticMeta<M>,
+  M: RuntimeMeta,
+{
+  fn synthetic_meta(&self) -> Self::TreeMeta {
+    core::array::from_fn(|index| synthetic_tree_meta(&self[index]))
+  }
+}
+
+impl<T, M, const N: usize> RichMut<'_, [T; N], M>
+where
+  T: TreeMetaProjection<M>,
+{
+  /// Borrow the element at `index`, or `None` if it is out of bounds.
+  pub fn index(&mut self, index: usize) -> Option<RichMut<'_, T, M>> {
+    let value = self.value.get_mut(index)?;
+    let meta = self.meta.as_deref_mut().map(|meta| &mut meta.nested[index]);
+    Some(RichMut::new(value, meta))
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<M, T> SyntheticMeta<M> for alloc::vec::Vec<T>
+where
+  T: SyntheticMeta<M>,
+  M: RuntimeMeta,
+{
+  fn synthetic_meta(&self) -> Self::TreeMeta {
+    self.iter().map(synthetic_tree_meta).collect()
+  }
+}
+
+/// Adding or removing elements marks the `Vec` itself as modified, and
+/// updates the metadata of the elements to match.
+///
+/// If the metadata is shorter than the value, it is only updated for the
+/// elements it covers.
+#[cfg(feature = "alloc")]
+impl<T, M> RichMut<'_, alloc::vec::Vec<T>, M>
+where
+  T: TreeMetaProjection<M>,
+{
+  pub fn len(&self) -> usize {
+    self.value.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
+  }
+
+  /// Borrow the element at `index`, or `None` if it is out of bounds.
+  pub fn index(&mut self, index: usize) -> Option<RichMut<'_, T, M>> {
+    let value = self.value.get_mut(index)?;
+    let meta = self.meta.as_deref_mut().and_then(|meta| meta.nested.get_mut(index));
+    Some(RichMut::new(value, meta))
+  }
+
+  /// Append an element, with synthetic metadata.
+  pub fn push(&mut self, value: T)
+  where
+    T: SyntheticMeta<M>,
+    M: RuntimeMeta,
+  {
+    let len = self.value.len();
+    self.insert(len, value);
+  }
+
+  /// Insert an element at `index`, with synthetic metadata.
+  ///
+  /// Panics if `index` is greater than the length.
+  pub fn insert(&mut self, index: usize, value: T)
+  where
+    T: SyntheticMeta<M>,
+    M: RuntimeMeta,
+  {
+    let item_meta = self.meta.is_some().then(|| synthetic_tree_meta(&value));
+    // Update the value first, so the metadata is untouched if it panics
+    self.value.insert(index, value);
+    self.mark_modified();
+    if let (Some(meta), Some(item_meta)) = (self.meta.as_deref_mut(), item_meta) {
+      let metas = &mut meta.nested;
+      if index <= metas.len() {
+        metas.insert(index, item_meta);
+      }
+    }
+  }
+
+  /// Remove and return the element at `index`, dropping its metadata.
+  ///
+  /// Panics if `index` is out of bounds.
+  pub fn remove(&mut self, index: usize) -> T
+  where
+    M: RuntimeMeta,
+  {
+    // Update the value first, so the metadata is untouched if it panics
+    let value = self.value.remove(index);
+    self.mark_modified();
+    if let Some(meta) = self.meta.as_deref_mut() {
+      let metas = &mut meta.nested;
+      if index < metas.len() {
+        metas.remove(index);
+      }
+    }
+    value
+  }
+
+  /// Remove and return the last element, or `None` if it is empty.
+  pub fn pop(&mut self) -> Option<T>
+  where
+    M: RuntimeMeta,
+  {
+    match self.value.len() {
+      0 => None,
+      len => Some(self.remove(len - 1)),
+    }
+  }
+}
+
+impl<M, T> SyntheticMeta<M> for Option<T>
+where
+  T: SyntheticMeta<M>,
+  M: RuntimeMeta,
+{
+  fn synthetic_meta(&self) -> Self::TreeMeta {
+    self.as_ref().map(synthetic_tree_meta)
+  }
+}
+
+impl<T, M> RichMut<'_, Option<T>, M>
+where
+  T: TreeMetaProjection<M>,
+{
+  /// Borrow the inner value, or `None` if the value is `None`.
+  pub fn inner(&mut self) -> Option<RichMut<'_, T, M>> {
+    let value = self.value.as_mut()?;
+    let meta = self.meta.as_deref_mut().and_then(|meta| meta.nested.as_mut());
+    Some(RichMut::new(value, meta))
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<M, T> SyntheticMeta<M> for alloc::boxed::Box<T>
+where
+  T: SyntheticMeta<M>,
+  M: RuntimeMeta,
+{
+  fn synthetic_meta(&self) -> Self::TreeMeta {
+    alloc::boxed::Box::new(synthetic_tree_meta(&**self))
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, M> RichMut<'_, alloc::boxed::Box<T>, M>
+where
+  T: TreeMetaProjection<M>,
+{
+  /// Borrow the boxed value.
+  pub fn inner(&mut self) -> RichMut<'_, T, M> {
+    let meta = self.meta.as_deref_mut().map(|meta| &mut *meta.nested);
+    RichMut::new(&mut **self.value, meta)
+  }
+}
+
+#[cfg(feature = "std")]
+impl<M, K, V, S> SyntheticMeta<M> for std::collections::HashMap<K, V, S>
+where
+  K: Clone + Eq + core::hash::Hash + SyntheticMeta<M>,
+  V: SyntheticMeta<M>,
+  M: RuntimeMeta,
+  S: Default + core::hash::BuildHasher,
+{
+  fn synthetic_meta(&self) -> Self::TreeMeta {
+    self
+      .iter()
+      .map(|(key, value)| {
+        (
+          key.clone(),
+          EntryMeta::new(synthetic_tree_meta(key), synthetic_tree_meta(value)),
+        )
+      })
+      .collect()
+  }
+}
+
+/// Adding or removing entries marks the map itself as modified, while
+/// replacing the value of an existing entry marks this value as modified.
+#[cfg(feature = "std")]
+impl<K, V, M, S> RichMut<'_, std::collections::HashMap<K, V, S>, M>
+where
+  K: Eq + core::hash::Hash + TreeMetaProjection<M>,
+  V: TreeMetaProjection<M>,
+  S: core::hash::BuildHasher,
+{
+  pub fn len(&self) -> usize {
+    self.value.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
+  }
+
+  /// Borrow the value for `key`, or `None` if the key is missing.
+  pub fn get<Q>(&mut self, key: &Q) -> Option<RichMut<'_, V, M>>
+  where
+    K: core::borrow::Borrow<Q>,
+    Q: Eq + core::hash::Hash + ?Sized,
+  {
+    let value = self.value.get_mut(key)?;
+    let meta = self.meta.as_deref_mut().and_then(|meta| meta.nested.get_mut(key));
+    Some(RichMut::new(value, meta.map(|meta| &mut meta.value)))
+  }
+
+  /// Insert an entry, and return the previous value for `key`.
+  ///
+  /// A new entry gets synthetic metadata for both its key and value.
+  pub fn insert(&mut self, key: K, value: V) -> Option<V>
+  where
+    K: Clone + SyntheticMeta<M>,
+    V: SyntheticMeta<M>,
+    M: RuntimeMeta,
+  {
+    if let Some(mut entry) = self.get(&key) {
+      return Some(entry.set(value));
+    }
+    self.mark_modified();
+    if let Some(meta) = self.meta.as_deref_mut() {
+      let entry = EntryMeta::new(synthetic_tree_meta(&key), synthetic_tree_meta(&value));
+      meta.nested.insert(key.clone(), entry);
+    }
+    self.value.insert(key, value)
+  }
+
+  /// Remove the entry for `key`, and return its value.
+  pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+  where
+    K: core::borrow::Borrow<Q>,
+    Q: Eq + core::hash::Hash + ?Sized,
+    M: RuntimeMeta,
+  {
+    let value = self.value.remove(key)?;
+    self.mark_modified();
+    if let Some(meta) = self.meta.as_deref_mut() {
+      meta.nested.remove(key);
+    }
+    Some(value)
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<M, K, V> SyntheticMeta<M> for alloc::collections::BTreeMap<K, V>
+where
+  K: Clone + Ord + SyntheticMeta<M>,
+  V: SyntheticMeta<M>,
+  M: RuntimeMeta,
+{
+  fn synthetic_meta(&self) -> Self::TreeMeta {
+    self
+      .iter()
+      .map(|(key, value)| {
+        (
+          key.clone(),
+          EntryMeta::new(synthetic_tree_meta(key), synthetic_tree_meta(value)),
+        )
+      })
+      .collect()
+  }
+}
+
+/// Adding or removing entries marks the map itself as modified, while
+/// replacing the value of an existing entry marks this value as modified.
+#[cfg(feature = "alloc")]
+impl<K, V, M> RichMut<'_, alloc::collections::BTreeMap<K, V>, M>
+where
+  K: Ord + TreeMetaProjection<M>,
+  V: TreeMetaProjection<M>,
+{
+  pub fn len(&self) -> usize {
+    self.value.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
+  }
+
+  /// Borrow the value for `key`, or `None` if the key is missing.
+  pub fn get<Q>(&mut self, key: &Q) -> Option<RichMut<'_, V, M>>
+  where
+    K: core::borrow::Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    let value = self.value.get_mut(key)?;
+    let meta = self.meta.as_deref_mut().and_then(|meta| meta.nested.get_mut(key));
+    Some(RichMut::new(value, meta.map(|meta| &mut meta.value)))
+  }
+
+  /// Insert an entry, and return the previous value for `key`.
+  ///
+  /// A new entry gets synthetic metadata for both its key and value.
+  pub fn insert(&mut self, key: K, value: V) -> Option<V>
+  where
+    K: Clone + SyntheticMeta<M>,
+    V: SyntheticMeta<M>,
+    M: RuntimeMeta,
+  {
+    if let Some(mut entry) = self.get(&key) {
+      return Some(entry.set(value));
+    }
+    self.mark_modified();
+    if let Some(meta) = self.meta.as_deref_mut() {
+      let entry = EntryMeta::new(synthetic_tree_meta(&key), synthetic_tree_meta(&value));
+      meta.nested.insert(key.clone(), entry);
+    }
+    self.value.insert(key, value)
+  }
+
+  /// Remove the entry for `key`, and return its value.
+  pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+  where
+    K: core::borrow::Borrow<Q>,
+    Q: Ord + ?Sized,
+    M: RuntimeMeta,
+  {
+    let value = self.value.remove(key)?;
+    self.mark_modified();
+    if let Some(meta) = self.meta.as_deref_mut() {
+      meta.nested.remove(key);
+    }
+    Some(value)
+  }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+  use super::*;
+  use alloc::vec;
+  use alloc::vec::Vec;
+
+  type Meta = Provenance<u32>;
+
+  fn leaf(id: u32) -> TreeMeta<Meta, ()> {
+    TreeMeta::new(Provenance::Input(id), ())
+  }
+
+  #[test]
+  fn vec_edits_shift_meta() {
+    let mut ports: Vec<u16> = vec![80, 443];
+    let mut meta = TreeMeta::new(Provenance::Input(0), vec![leaf(1), leaf(2)]);
+
+    let mut rich = RichMut::new(&mut ports, Some(&mut meta));
+    rich.insert(0, 22);
+    rich.push(8080);
+    assert_eq!(rich.remove(1), 80);
+    assert_eq!(rich.index(1).map(|mut port| port.set(8443)), Some(443));
+
+    assert_eq!(ports, vec![22, 8443, 8080]);
+    assert_eq!(meta.meta(), &Provenance::Modified(0));
+    assert_eq!(
+      meta.nested(),
+      &vec![
+        TreeMeta::new(Provenance::Synthetic, ()),
+        TreeMeta::new(Provenance::Modified(2), ()),
+        TreeMeta::new(Provenance::Synthetic, ()),
+      ]
+    );
+  }
+
+  #[test]
+  #[cfg(feature = "std")]
+  fn vec_out_of_bounds_edits_keep_meta() {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    let mut ports: Vec<u16> = vec![80];
+    // The metadata is longer than the value
+    let mut meta = TreeMeta::new(Provenance::Input(0), vec![leaf(1), leaf(2), leaf(3)]);
+
+    let mut rich = RichMut::new(&mut ports, Some(&mut meta));
+    assert!(catch_unwind(AssertUnwindSafe(|| rich.insert(2, 22))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| rich.remove(2))).is_err());
+
+    assert_eq!(ports, vec![80]);
+    assert_eq!(meta.meta(), &Provenance::Input(0));
+    assert_eq!(meta.nested(), &vec![leaf(1), leaf(2), leaf(3)]);
+  }
+
+  #[test]
+  fn vec_edits_without_meta() {
+    let mut ports: Vec<u16> = vec![80];
+
+    let mut rich = RichMut::<_, Meta>::new(&mut ports, None);
+    rich.push(443);
+    assert_eq!(rich.pop(), Some(443));
+    assert_eq!(rich.index(0).and_then(|port| port.meta().copied()), None);
+
+    assert_eq!(ports, vec![80]);
+  }
+
+  #[test]
+  fn btree_map_insert_and_remove() {
+    use alloc::collections::BTreeMap;
+
+    let mut stock = BTreeMap::from([("krill", 500u32)]);
+    let mut meta = TreeMeta::new(
+      Provenance::Input(0),
+      BTreeMap::from([("krill", EntryMeta::new(leaf(1), leaf(2)))]),
+    );
+
+    let mut rich = RichMut::new(&mut stock, Some(&mut meta));
+    assert_eq!(rich.insert("krill", 400), Some(500));
+    assert_eq!(rich.meta(), Some(&Provenance::Input(0)));
+    assert_eq!(rich.insert("fish", 20), None);
+    assert_eq!(rich.remove("squid"), None);
+
+    assert_eq!(meta.meta(), &Provenance::Modified(0));
+    assert_eq!(
+      meta.nested().get("krill"),
+      Some(&EntryMeta::new(leaf(1), TreeMeta::new(Provenance::Modified(2), ())))
+    );
+    assert_eq!(
+      meta.nested().get("fish"),
+      Some(&EntryMeta::new(
+        TreeMeta::new(Provenance::Synthetic, ()),
+        TreeMeta::new(Provenance::Synthetic, ())
+      ))
+    );
+  }
+}
@@ -10,3 +10,11 @@ pub fn derive_meta_type(input: TokenStream) -> TokenStream {
     rich_derive_impl::meta_type::expand_derive_meta_type(&mut input).unwrap_or_else(syn::Error::into_compile_error);
   proc_macro::TokenStream::from(stream)
 }
+
+#[proc_macro_derive(MetaView, attributes(meta, view))]
+pub fn derive_meta_view(input: TokenStream) -> TokenStream {
+  let mut input = parse_macro_input!(input as DeriveInput);
+  let stream: proc_macro2::TokenStream =
+    rich_derive_impl::meta_view::expand_derive_meta_view(&mut input).unwrap_or_else(syn::Error::into_compile_error);
+  proc_macro::TokenStream::from(stream)
+}
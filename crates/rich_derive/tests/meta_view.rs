@@ -0,0 +1,120 @@
+use rich::{EnumMeta, MetaId, Rich, RichEnum, TreeMeta, View};
+use rich_derive::{MetaType, MetaView};
+
+#[derive(Debug, Clone, PartialEq, MetaType, MetaView)]
+#[meta(rich_twin = RichMascot)]
+struct Mascot {
+  name: String,
+  price: u32,
+  tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, MetaType, MetaView)]
+#[meta(rich_twin = RichOperation)]
+enum Operation {
+  Read,
+  Update { rate_limit: u32 },
+  Delete(bool),
+}
+
+#[test]
+fn view_struct() {
+  let mascot = Rich::new(
+    RichMascot {
+      name: Rich::new(String::from("Ferris"), MetaId::from_usize(1)),
+      price: Rich::new(42, MetaId::from_usize(2)),
+      tags: Rich::new(
+        vec![Rich::new(String::from("crab"), MetaId::from_usize(3))],
+        MetaId::from_usize(4),
+      ),
+    },
+    MetaId::from_usize(5),
+  )
+  .deep_split_meta();
+
+  let view = mascot.view();
+
+  assert_eq!(view.meta(), Some(&MetaId::from_usize(5)));
+  assert_eq!(
+    view.name().rich(),
+    Rich::new(&String::from("Ferris"), Some(&MetaId::from_usize(1)))
+  );
+  assert_eq!(view.price().rich(), Rich::new(&42, Some(&MetaId::from_usize(2))));
+  assert_eq!(view.tags().meta(), Some(&MetaId::from_usize(4)));
+  assert_eq!(
+    view.tags().index(0).map(|tag| tag.rich()),
+    Some(Rich::new(&String::from("crab"), Some(&MetaId::from_usize(3))))
+  );
+}
+
+#[test]
+fn view_struct_missing_meta() {
+  let mascot = Mascot {
+    name: String::from("Ferris"),
+    price: 42,
+    tags: vec![String::from("crab")],
+  };
+
+  let view: MascotView<'_, MetaId> = View::new(&mascot, None).typed();
+
+  assert_eq!(view.meta(), None);
+  assert_eq!(view.price().rich(), Rich::new(&42, None));
+  assert_eq!(
+    view.tags().index(0).map(|tag| tag.rich()),
+    Some(Rich::new(&String::from("crab"), None))
+  );
+}
+
+#[test]
+fn view_enum() {
+  let operation = Rich::new(
+    RichEnum::<Operation, MetaId>::new(
+      MetaId::from_usize(1),
+      RichOperation::Update {
+        rate_limit: Rich::new(10, MetaId::from_usize(2)),
+      },
+    ),
+    MetaId::from_usize(3),
+  )
+  .deep_split_meta();
+
+  let view = operation.view();
+
+  assert_eq!(view.meta(), Some(&MetaId::from_usize(3)));
+  assert_eq!(view.discriminant_meta(), Some(&MetaId::from_usize(1)));
+  match view.variant() {
+    OperationVariantView::Update { rate_limit } => {
+      assert_eq!(rate_limit.rich(), Rich::new(&10, Some(&MetaId::from_usize(2))));
+    }
+    _ => panic!("expected the `Update` variant"),
+  }
+}
+
+#[test]
+fn view_enum_variant_mismatch() {
+  let operation = Rich::new(
+    Operation::Delete(true),
+    TreeMeta::new(
+      MetaId::from_usize(3),
+      EnumMeta::new(
+        MetaId::from_usize(1),
+        OperationMeta::Update {
+          rate_limit: TreeMeta::new(MetaId::from_usize(2), ()),
+        },
+      ),
+    ),
+  );
+
+  let view = operation.view();
+
+  // The metadata of the value itself is still available
+  assert_eq!(view.meta(), Some(&MetaId::from_usize(3)));
+  match view.variant() {
+    OperationVariantView::Delete(confirm) => assert_eq!(confirm.rich(), Rich::new(&true, None)),
+    _ => panic!("expected the `Delete` variant"),
+  }
+  assert!(matches!(
+    View::<Operation, MetaId>::new(&Operation::Read, None).typed().variant(),
+    OperationVariantView::Read
+  ));
+}
@@ -0,0 +1,132 @@
+use rich::partial::align_tree_meta;
+use rich::{EnumMeta, MetaId, Provenance, RichMut, TreeMeta};
+use rich_derive::MetaType;
+
+#[derive(Debug, Clone, PartialEq, MetaType)]
+#[meta(attr(derive(Debug, PartialEq)))]
+struct Plain {
+  name: String,
+  price: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, MetaType)]
+#[meta(attr(derive(Debug, PartialEq)))]
+enum Size {
+  Unknown,
+  Known(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, MetaType)]
+#[meta(bound = "T: rich::TreeMetaProjection<TyMeta>")]
+struct Stack<T> {
+  items: Vec<T>,
+}
+
+type Meta = Provenance<MetaId>;
+
+fn input(id: usize) -> Meta {
+  Provenance::Input(MetaId::from_usize(id))
+}
+
+fn input_plain(id: usize) -> TreeMeta<Meta, PlainMeta<Meta>> {
+  TreeMeta::new(
+    input(id),
+    PlainMeta {
+      name: TreeMeta::new(input(id + 1), ()),
+      price: TreeMeta::new(input(id + 2), ()),
+    },
+  )
+}
+
+fn synthetic_plain() -> TreeMeta<Meta, PlainMeta<Meta>> {
+  TreeMeta::new(
+    Provenance::Synthetic,
+    PlainMeta {
+      name: TreeMeta::new(Provenance::Synthetic, ()),
+      price: TreeMeta::new(Provenance::Synthetic, ()),
+    },
+  )
+}
+
+fn plain(name: &str, price: u32) -> Plain {
+  Plain {
+    name: String::from(name),
+    price,
+  }
+}
+
+#[test]
+fn push_and_insert_plain() {
+  let mut plains = vec![plain("Ferris", 42)];
+  let mut meta = TreeMeta::new(input(1), vec![input_plain(2)]);
+
+  let mut rich = RichMut::new(&mut plains, Some(&mut meta));
+  rich.push(plain("Corro", 10));
+  rich.insert(0, plain("Gopher", 5));
+
+  assert_eq!(plains, [plain("Gopher", 5), plain("Ferris", 42), plain("Corro", 10)]);
+  assert_eq!(meta.meta(), &Provenance::Modified(MetaId::from_usize(1)));
+  assert_eq!(meta.nested(), &[synthetic_plain(), input_plain(2), synthetic_plain()]);
+}
+
+#[test]
+fn align_partial_plain() {
+  let plains = vec![plain("Ferris", 42), plain("Corro", 10)];
+  let mut meta = TreeMeta::new(input(1), vec![input_plain(2)]);
+
+  align_tree_meta(&plains, &mut meta);
+
+  assert_eq!(meta.meta(), &input(1));
+  assert_eq!(meta.nested(), &[input_plain(2), synthetic_plain()]);
+}
+
+#[test]
+fn align_plain_enum_variant() {
+  let sizes = vec![Size::Known(3), Size::Unknown];
+  let mut meta = TreeMeta::new(
+    input(1),
+    vec![
+      TreeMeta::new(
+        input(2),
+        EnumMeta::new(input(3), SizeMeta::Known(TreeMeta::new(input(4), ()))),
+      ),
+      TreeMeta::new(
+        input(5),
+        EnumMeta::new(input(6), SizeMeta::Known(TreeMeta::new(input(7), ()))),
+      ),
+    ],
+  );
+
+  align_tree_meta(&sizes, &mut meta);
+
+  assert_eq!(
+    meta.nested(),
+    &[
+      TreeMeta::new(
+        input(2),
+        EnumMeta::new(input(3), SizeMeta::Known(TreeMeta::new(input(4), ()))),
+      ),
+      TreeMeta::new(input(5), EnumMeta::new(Provenance::Synthetic, SizeMeta::Unknown)),
+    ]
+  );
+}
+
+#[test]
+fn push_stack_with_bound_override() {
+  let mut stacks = vec![];
+  let mut meta = TreeMeta::new(input(1), vec![]);
+
+  RichMut::new(&mut stacks, Some(&mut meta)).push(Stack { items: vec![1u32, 2] });
+
+  let [stack_meta] = meta.nested().as_slice() else {
+    panic!("expected metadata for one stack");
+  };
+  assert_eq!(stack_meta.meta(), &Provenance::Synthetic);
+  assert_eq!(
+    stack_meta.nested().items.nested(),
+    &[
+      TreeMeta::new(Provenance::Synthetic, ()),
+      TreeMeta::new(Provenance::Synthetic, ())
+    ]
+  );
+}
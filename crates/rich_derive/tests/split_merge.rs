@@ -0,0 +1,64 @@
+use rich::{MetaId, Rich, TreeMeta};
+use rich_derive::MetaType;
+
+#[derive(Debug, Clone, PartialEq, MetaType)]
+#[meta(attr(derive(Debug, Clone, PartialEq)))]
+struct UrlParts {
+  scheme: String,
+  host: String,
+}
+
+#[derive(Debug, Clone, PartialEq, MetaType)]
+#[meta(rich_twin = RichServer, attr(derive(Debug, Clone, PartialEq)))]
+#[rich(attr(derive(Debug, Clone, PartialEq)))]
+struct Server {
+  #[meta(rename = name_meta)]
+  name: String,
+  #[meta(rename = url_meta, with = UrlParts)]
+  url: String,
+  #[meta(skip)]
+  cache: Vec<u8>,
+}
+
+fn id(id: usize) -> MetaId {
+  MetaId::from_usize(id)
+}
+
+#[test]
+fn split_merge_round_trip() {
+  let url_meta = TreeMeta::new(
+    id(2),
+    UrlPartsMeta {
+      scheme: TreeMeta::new(id(3), ()),
+      host: TreeMeta::new(id(4), ()),
+    },
+  );
+  let rich = Rich::new(
+    RichServer {
+      name: Rich::new(String::from("api"), id(1)),
+      url: Rich::new(String::from("https://example.com"), url_meta.clone()),
+      cache: vec![1, 2],
+    },
+    id(0),
+  );
+
+  let split = rich.clone().deep_split_meta();
+
+  assert_eq!(
+    split.value,
+    Server {
+      name: String::from("api"),
+      url: String::from("https://example.com"),
+      cache: vec![1, 2],
+    }
+  );
+  assert_eq!(split.meta.meta(), &id(0));
+  assert_eq!(
+    split.meta.nested(),
+    &ServerMeta {
+      name_meta: TreeMeta::new(id(1), ()),
+      url_meta,
+    }
+  );
+  assert_eq!(split.deep_merge_meta(), Ok(rich));
+}
@@ -0,0 +1,92 @@
+//! Helpers to compute the generics and bounds of derived items.
+
+use crate::internals::ast::Container;
+use crate::internals::attr::FieldProjection;
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::ToTokens;
+
+/// Identifier of a generic parameter: its name for type and const parameters,
+/// and the name without the leading `'` for lifetimes.
+pub fn param_ident(param: &syn::GenericParam) -> &Ident {
+  match param {
+    syn::GenericParam::Lifetime(param) => &param.lifetime.ident,
+    syn::GenericParam::Type(param) => &param.ident,
+    syn::GenericParam::Const(param) => &param.ident,
+  }
+}
+
+/// Remove the defaults of type and const parameters.
+///
+/// Parameters with a default must be trailing, so they would prevent adding
+/// extra parameters to the generics of the container.
+pub fn without_defaults(generics: &syn::Generics) -> syn::Generics {
+  let mut generics = generics.clone();
+  for param in generics.params.iter_mut() {
+    match param {
+      syn::GenericParam::Type(param) => {
+        param.eq_token = None;
+        param.default = None;
+      }
+      syn::GenericParam::Const(param) => {
+        param.eq_token = None;
+        param.default = None;
+      }
+      syn::GenericParam::Lifetime(_) => {}
+    }
+  }
+  generics
+}
+
+/// Add `predicates` to the where clause of `generics`.
+pub fn with_where_predicates(generics: &syn::Generics, predicates: &[syn::WherePredicate]) -> syn::Generics {
+  let mut generics = generics.clone();
+  if !predicates.is_empty() {
+    generics
+      .make_where_clause()
+      .predicates
+      .extend(predicates.iter().cloned());
+  }
+  generics
+}
+
+/// Type parameters of the container used by the type of at least one field
+/// getting its metadata from its own type.
+///
+/// Fields with `#[meta(skip)]`, `#[meta(opaque)]` or `#[meta(with = ...)]`
+/// don't require any bound on their type.
+pub fn field_type_params<'a>(container: &'a Container<'a>) -> Vec<&'a Ident> {
+  container
+    .generics
+    .type_params()
+    .map(|param| &param.ident)
+    .filter(|param| {
+      container
+        .data
+        .all_fields()
+        .filter(|field| matches!(field.attributes.projection, FieldProjection::Tree))
+        .any(|field| mentions_any(field.ty.to_token_stream(), &[param]))
+    })
+    .collect()
+}
+
+/// Build the bound `Param: #bound` for each type parameter used by a field.
+///
+/// The field types themselves are left unbounded: a bound on `Vec<T>` would
+/// shadow its impl and prevent the normalization of its associated types.
+pub fn field_type_param_bounds(container: &Container<'_>, bound: &TokenStream) -> Vec<syn::WherePredicate> {
+  field_type_params(container)
+    .into_iter()
+    .map(|param| syn::parse_quote!(#param: #bound))
+    .collect()
+}
+
+/// Test if `tokens` contain any of the identifiers `idents`.
+///
+/// Lifetimes are matched by their name without the leading `'`.
+pub fn mentions_any(tokens: TokenStream, idents: &[&Ident]) -> bool {
+  tokens.into_iter().any(|token| match token {
+    TokenTree::Ident(ident) => idents.contains(&&ident),
+    TokenTree::Group(group) => mentions_any(group.stream(), idents),
+    _ => false,
+  })
+}
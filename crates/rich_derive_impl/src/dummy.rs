@@ -1,23 +1,33 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream};
 use quote::quote;
 use syn::Path;
 
-pub fn wrap_in_const(extern_path: Option<&Path>, local_path: &Path, code: TokenStream) -> TokenStream {
-  let use_rich = match extern_path {
+/// Wrap generated code in an anonymous `const` block, where `local_path`
+/// resolves to the runtime crate `crate_name` (or to `extern_path` if provided).
+pub fn wrap_in_const(
+  crate_name: &str,
+  extern_path: Option<&Path>,
+  local_path: &Path,
+  code: TokenStream,
+) -> TokenStream {
+  let use_crate = match extern_path {
     Some(path) => quote! {
         use #path as #local_path;
     },
-    None => quote! {
-        #[allow(unused_extern_crates, clippy::useless_attribute)]
-        extern crate rich as #local_path;
-    },
+    None => {
+      let crate_name = Ident::new(crate_name, Span::call_site());
+      quote! {
+          #[allow(unused_extern_crates, clippy::useless_attribute)]
+          extern crate #crate_name as #local_path;
+      }
+    }
   };
 
   quote! {
       #[doc(hidden)]
       #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
       const _: () = {
-          #use_rich
+          #use_crate
           #code
       };
   }
@@ -1,7 +1,8 @@
 //! A Serde ast, parsed from the Syn ast and ready to generate Rust code.
 
-use crate::internals::attr::ContainerAttributes;
+use crate::internals::attr::{ContainerAttributes, FieldAttributes, FieldProjection};
 use crate::internals::context::Context;
+use crate::internals::Derive;
 use core::fmt;
 use syn::punctuated::Punctuated;
 use syn::Token;
@@ -64,6 +65,8 @@ impl fmt::Debug for Variant<'_> {
 /// A field of a struct.
 pub struct Field<'a> {
   pub member: syn::Member,
+  /// Attributes on the field.
+  pub attributes: FieldAttributes,
   pub ty: &'a syn::Type,
   pub original: &'a syn::Field,
 }
@@ -72,12 +75,46 @@ impl fmt::Debug for Field<'_> {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     f.debug_struct(core::any::type_name::<Self>())
       .field("member", &"...")
+      .field("attributes", &self.attributes)
       .field("ty", &"...")
       .field("original", &"...")
       .finish()
   }
 }
 
+impl Field<'_> {
+  /// Whether the field has an entry in the metadata type.
+  pub fn has_meta(&self) -> bool {
+    !matches!(self.attributes.projection, FieldProjection::Skip)
+  }
+}
+
+/// Members of `fields` in the metadata type, or `None` for skipped fields.
+///
+/// Named fields may be renamed, and unnamed fields are numbered without the
+/// skipped ones.
+pub fn meta_members(fields: &[Field<'_>]) -> Vec<Option<syn::Member>> {
+  let mut index: u32 = 0;
+  fields
+    .iter()
+    .map(|field| {
+      if !field.has_meta() {
+        return None;
+      }
+      let member = match (&field.member, &field.attributes.rename) {
+        (_, Some(rename)) => syn::Member::Named(rename.clone()),
+        (syn::Member::Named(ident), None) => syn::Member::Named(ident.clone()),
+        (syn::Member::Unnamed(unnamed), None) => syn::Member::Unnamed(syn::Index {
+          index,
+          span: unnamed.span,
+        }),
+      };
+      index += 1;
+      Some(member)
+    })
+    .collect()
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Style {
   /// Named fields.
@@ -92,7 +129,7 @@ pub enum Style {
 
 impl<'a> Container<'a> {
   /// Convert the raw Syn ast into a parsed container object, collecting errors in `cx`.
-  pub fn from_ast(cx: &mut Context, item: &'a syn::DeriveInput) -> Option<Container<'a>> {
+  pub fn from_ast(cx: &mut Context, item: &'a syn::DeriveInput, derive: Derive) -> Option<Container<'a>> {
     let attributes = ContainerAttributes::from_ast(cx, item);
 
     let data = match &item.data {
@@ -105,7 +142,10 @@ impl<'a> Container<'a> {
         Data::Struct(style, fields)
       }
       syn::Data::Union(_) => {
-        cx.error_spanned_by(item, "automatic `MetaType` derive does not support `union` types");
+        cx.error_spanned_by(
+          item,
+          format_args!("automatic `{derive}` derive does not support `union` types"),
+        );
         return None;
       }
     };
@@ -228,7 +268,7 @@ fn struct_from_ast<'a>(
 }
 
 fn fields_from_ast<'a>(
-  _cx: &mut Context,
+  cx: &mut Context,
   fields: &'a Punctuated<syn::Field, Token![,]>,
   // attrs: Option<&attr::Variant>,
   // container_default: &attr::Default,
@@ -241,7 +281,7 @@ fn fields_from_ast<'a>(
         Some(ident) => syn::Member::Named(ident.clone()),
         None => syn::Member::Unnamed(i.into()),
       },
-      // attrs: attr::Field::from_ast(cx, i, field, attrs, container_default),
+      attributes: FieldAttributes::from_ast(cx, field),
       ty: &field.ty,
       original: field,
     })
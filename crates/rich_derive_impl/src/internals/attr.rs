@@ -1,32 +1,117 @@
 use crate::internals::context::Context;
-use crate::internals::symbol::{ATTR, META, NAME};
+use crate::internals::symbol::{
+  ATTR, BOUND, DISCRIMINANT, META, NAME, OPAQUE, RENAME, RICH, RICH_TWIN, SKIP, STYP, VIEW, WITH,
+};
+use core::fmt;
 use proc_macro2::{Ident, TokenStream};
-use quote::ToTokens;
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::Token;
 use syn::parse::ParseBuffer;
+use syn::punctuated::Punctuated;
 
 #[derive(Debug)]
 pub struct ContainerAttributes {
   /// Options for the `MetaType` associated with this container
   pub meta: ContainerMetaAttributes,
+  /// Options for the internal-metadata twin generated by `#[derive(MetaType)]`
+  pub rich: ContainerRichAttributes,
+  /// Options for the structure type generated by `#[derive(StructuralProjection)]`
+  pub styp: ContainerStypAttributes,
+  /// Options for the view type generated by `#[derive(MetaView)]`
+  pub view: ContainerViewAttributes,
 }
 
-#[derive(Debug)]
 pub struct ContainerMetaAttributes {
   pub name: Option<Ident>,
   /// Extra attributes to attach to this container.
   pub attr: Vec<TokenStream>,
+  /// Where predicates replacing the inferred `TreeMetaProjection<TyMeta>`
+  /// bounds on the type parameters (`MergeMeta<TyMeta>` for the impls
+  /// involving the internal-metadata twin). The `SyntheticMeta` and
+  /// `AlignMeta` impls keep their inferred bounds along with these ones.
+  pub bound: Option<Vec<syn::WherePredicate>>,
+  /// Name of the internal-metadata twin, if it should be generated.
+  pub rich_twin: Option<Ident>,
+  /// `DiscriminantMeta` marker of an enum, replacing `WithDiscriminant`.
+  pub discriminant: Option<syn::Type>,
+}
+
+#[derive(Debug)]
+pub struct ContainerRichAttributes {
+  /// Extra attributes to attach to the internal-metadata twin.
+  pub attr: Vec<TokenStream>,
+}
+
+#[derive(Debug)]
+pub struct ContainerStypAttributes {
+  /// Name of the generic structure type.
+  pub name: Option<Ident>,
+  /// Extra attributes to attach to the structure type.
+  pub attr: Vec<TokenStream>,
+}
+
+pub struct ContainerViewAttributes {
+  /// Name of the view type.
+  pub name: Option<Ident>,
+  /// Extra attributes to attach to the view type.
+  pub attr: Vec<TokenStream>,
+  /// Where predicates replacing the inferred `ViewMeta<TyMeta>` bounds on the
+  /// type parameters.
+  pub bound: Option<Vec<syn::WherePredicate>>,
+}
+
+impl fmt::Debug for ContainerMetaAttributes {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct(core::any::type_name::<Self>())
+      .field("name", &self.name)
+      .field("attr", &self.attr)
+      .field("bound", &self.bound.as_ref().map(|_| "..."))
+      .field("rich_twin", &self.rich_twin)
+      .field("discriminant", &self.discriminant.as_ref().map(|_| "..."))
+      .finish()
+  }
+}
+
+impl fmt::Debug for ContainerViewAttributes {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct(core::any::type_name::<Self>())
+      .field("name", &self.name)
+      .field("attr", &self.attr)
+      .field("bound", &self.bound.as_ref().map(|_| "..."))
+      .finish()
+  }
 }
 
 impl ContainerAttributes {
-  /// Extract out the `#[serde(...)]` attributes from an item.
-  pub fn from_ast(_cx: &mut Context, item: &syn::DeriveInput) -> Self {
+  /// Extract out the `#[meta(...)]`, `#[rich(...)]`, `#[styp(...)]` and
+  /// `#[view(...)]` attributes from an item.
+  pub fn from_ast(cx: &mut Context, item: &syn::DeriveInput) -> Self {
     let mut meta_attr: Vec<TokenStream> = Vec::new();
     let mut meta_name: Option<Ident> = None;
+    let mut meta_rich_twin: Option<Ident> = None;
+    let mut meta_discriminant: Option<syn::Type> = None;
+    let mut rich_attr: Vec<TokenStream> = Vec::new();
+    let mut styp_attr: Vec<TokenStream> = Vec::new();
+    let mut styp_name: Option<Ident> = None;
+    let mut view_attr: Vec<TokenStream> = Vec::new();
+    let mut view_name: Option<Ident> = None;
+    let mut meta_bound: Option<Vec<syn::WherePredicate>> = None;
+    let mut view_bound: Option<Vec<syn::WherePredicate>> = None;
 
     for attr in &item.attrs {
-      if attr.path() != META {
+      // The structure type has no bounds to override, and the twin is named
+      // by `#[meta(rich_twin = ...)]`
+      let (attr_out, mut name_out, mut bound_out) = if attr.path() == META {
+        (&mut meta_attr, Some(&mut meta_name), Some(&mut meta_bound))
+      } else if attr.path() == RICH {
+        (&mut rich_attr, None, None)
+      } else if attr.path() == STYP {
+        (&mut styp_attr, Some(&mut styp_name), None)
+      } else if attr.path() == VIEW {
+        (&mut view_attr, Some(&mut view_name), Some(&mut view_bound))
+      } else {
         continue;
-      }
+      };
 
       if let syn::Meta::List(meta) = &attr.meta {
         if meta.tokens.is_empty() {
@@ -34,30 +119,146 @@ impl ContainerAttributes {
         }
       }
 
-      attr
-        .parse_nested_meta(|meta| -> Result<(), syn::Error> {
-          if meta.path == ATTR {
-            let content: ParseBuffer;
-            syn::parenthesized!(content in meta.input);
-            let content = content.parse::<TokenStream>()?;
-            meta_attr.push(content);
-          } else if meta.path == NAME {
-            let value = meta.value()?.parse::<Ident>()?;
-            meta_name = Some(value);
-          } else {
-            let path = meta.path.to_token_stream().to_string().replace(' ', "");
-            return Err(meta.error(format_args!("unknown rich container attribute `{}`", path)));
-          }
-          Ok(())
-        })
-        .expect("invalid nested meta");
+      let parsed = attr.parse_nested_meta(|meta| -> Result<(), syn::Error> {
+        if meta.path == ATTR {
+          let content: ParseBuffer;
+          syn::parenthesized!(content in meta.input);
+          let content = content.parse::<TokenStream>()?;
+          attr_out.push(content);
+        } else if let (true, Some(name_out)) = (meta.path == NAME, name_out.as_mut()) {
+          let value = meta.value()?.parse::<Ident>()?;
+          **name_out = Some(value);
+        } else if attr.path() == META && meta.path == RICH_TWIN {
+          let value = meta.value()?.parse::<Ident>()?;
+          meta_rich_twin = Some(value);
+        } else if attr.path() == META && meta.path == DISCRIMINANT {
+          let value = meta.value()?.parse::<syn::Type>()?;
+          meta_discriminant = Some(value);
+        } else if let (true, Some(bound_out)) = (meta.path == BOUND, bound_out.as_mut()) {
+          let value = meta.value()?.parse::<syn::LitStr>()?;
+          let predicates = value.parse_with(Punctuated::<syn::WherePredicate, Token![,]>::parse_terminated)?;
+          **bound_out = Some(predicates.into_iter().collect());
+        } else {
+          let path = meta.path.to_token_stream().to_string().replace(' ', "");
+          return Err(meta.error(format_args!("unknown rich container attribute `{}`", path)));
+        }
+        Ok(())
+      });
+      if let Err(err) = parsed {
+        cx.syn_error(err);
+      }
     }
 
     Self {
       meta: ContainerMetaAttributes {
         attr: meta_attr,
         name: meta_name,
+        bound: meta_bound,
+        rich_twin: meta_rich_twin,
+        discriminant: meta_discriminant,
+      },
+      rich: ContainerRichAttributes { attr: rich_attr },
+      styp: ContainerStypAttributes {
+        attr: styp_attr,
+        name: styp_name,
       },
+      view: ContainerViewAttributes {
+        attr: view_attr,
+        name: view_name,
+        bound: view_bound,
+      },
+    }
+  }
+}
+
+/// Metadata attached to a field by `#[derive(MetaType)]`.
+pub enum FieldProjection {
+  /// Metadata tree from the `TreeMetaProjection` impl of the field type.
+  Tree,
+  /// No metadata, from `#[meta(skip)]`.
+  Skip,
+  /// Metadata for the field itself, without nested metadata, from
+  /// `#[meta(opaque)]`.
+  Opaque,
+  /// Metadata tree from the `TreeMetaProjection` impl of another type, from
+  /// `#[meta(with = Type)]`.
+  With(Box<syn::Type>),
+}
+
+impl fmt::Debug for FieldProjection {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Tree => f.write_str("Tree"),
+      Self::Skip => f.write_str("Skip"),
+      Self::Opaque => f.write_str("Opaque"),
+      Self::With(_) => f.write_str("With(...)"),
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct FieldAttributes {
+  /// Metadata attached to the field.
+  pub projection: FieldProjection,
+  /// Name of the field in the metadata type.
+  pub rename: Option<Ident>,
+}
+
+impl FieldAttributes {
+  /// Extract out the `#[meta(...)]` attributes from a field.
+  pub fn from_ast(cx: &mut Context, field: &syn::Field) -> Self {
+    let mut projection = FieldProjection::Tree;
+    let mut rename: Option<Ident> = None;
+
+    for attr in &field.attrs {
+      if attr.path() != META {
+        continue;
+      }
+
+      let parsed = attr.parse_nested_meta(|meta| -> Result<(), syn::Error> {
+        let new_projection = if meta.path == SKIP {
+          FieldProjection::Skip
+        } else if meta.path == OPAQUE {
+          FieldProjection::Opaque
+        } else if meta.path == WITH {
+          FieldProjection::With(Box::new(meta.value()?.parse::<syn::Type>()?))
+        } else if meta.path == RENAME {
+          if field.ident.is_none() {
+            return Err(meta.error("`rename` is only supported on named fields"));
+          }
+          rename = Some(meta.value()?.parse::<Ident>()?);
+          return Ok(());
+        } else {
+          let path = meta.path.to_token_stream().to_string().replace(' ', "");
+          return Err(meta.error(format_args!("unknown rich field attribute `{}`", path)));
+        };
+        if !matches!(projection, FieldProjection::Tree) {
+          return Err(meta.error("only one of `skip`, `opaque` and `with` may be used on a field"));
+        }
+        projection = new_projection;
+        Ok(())
+      });
+      if let Err(err) = parsed {
+        cx.syn_error(err);
+      }
+    }
+
+    if rename.is_some() && matches!(projection, FieldProjection::Skip) {
+      cx.error_spanned_by(field, "`rename` has no effect on a skipped field");
+    }
+
+    Self { projection, rename }
+  }
+}
+
+/// Helper rendering attributes forwarded through `attr(...)` onto a generated item.
+#[derive(Debug)]
+pub struct ForwardMeta<'a>(pub &'a [TokenStream]);
+
+impl<'a> ToTokens for ForwardMeta<'a> {
+  fn to_tokens(&self, tokens: &mut TokenStream) {
+    for meta in self.0 {
+      tokens.append_all(quote! { #[#meta]})
     }
   }
 }
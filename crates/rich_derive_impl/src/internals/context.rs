@@ -18,6 +18,10 @@ impl Context {
   /// Create a new context object.
   ///
   /// This object contains no errors, but will still trigger a panic if it is not `check`ed.
+  #[expect(
+    clippy::new_without_default,
+    reason = "a context must be checked, creating one should be explicit"
+  )]
   pub fn new() -> Self {
     Context {
       errors: Vec::new(),
@@ -2,3 +2,25 @@ pub mod ast;
 pub mod attr;
 pub mod context;
 pub mod symbol;
+
+use core::fmt;
+
+/// Derive macro being expanded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Derive {
+  MetaType,
+  MetaView,
+  RichDeserialize,
+  StructuralProjection,
+}
+
+impl fmt::Display for Derive {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Derive::MetaType => "MetaType",
+      Derive::MetaView => "MetaView",
+      Derive::RichDeserialize => "RichDeserialize",
+      Derive::StructuralProjection => "StructuralProjection",
+    })
+  }
+}
@@ -5,9 +5,18 @@ use syn::{Ident, Path};
 pub struct Symbol(&'static str);
 
 pub const ATTR: Symbol = Symbol("attr");
+pub const BOUND: Symbol = Symbol("bound");
+pub const DISCRIMINANT: Symbol = Symbol("discriminant");
 pub const META: Symbol = Symbol("meta");
 pub const NAME: Symbol = Symbol("name");
+pub const OPAQUE: Symbol = Symbol("opaque");
+pub const RENAME: Symbol = Symbol("rename");
 pub const RICH: Symbol = Symbol("rich");
+pub const RICH_TWIN: Symbol = Symbol("rich_twin");
+pub const SKIP: Symbol = Symbol("skip");
+pub const STYP: Symbol = Symbol("styp");
+pub const VIEW: Symbol = Symbol("view");
+pub const WITH: Symbol = Symbol("with");
 
 impl PartialEq<Symbol> for Ident {
   fn eq(&self, other: &Symbol) -> bool {
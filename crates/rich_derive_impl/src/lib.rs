@@ -1,6 +1,9 @@
 //! This crate provides derove macros for `rich` traits.
 
+pub mod bound;
 pub mod dummy;
 pub mod internals;
 pub mod meta_type;
+pub mod meta_view;
 pub mod rich_deserialize;
+pub mod structural_projection;
@@ -1,14 +1,17 @@
+use crate::bound;
 use crate::dummy;
-use crate::internals::ast::{Container, Data, Style};
+use crate::internals::Derive;
+use crate::internals::ast::{Container, Data, Field, Style, meta_members};
+use crate::internals::attr::{FieldProjection, ForwardMeta};
 use crate::internals::context::Context;
-use proc_macro2::{Ident, TokenStream};
-use quote::{quote, ToTokens, TokenStreamExt};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{ToTokens, format_ident, quote};
 use syn::spanned::Spanned;
-use syn::{parse_quote, Path};
+use syn::{Path, parse_quote};
 
 pub fn expand_derive_meta_type(input: &mut syn::DeriveInput) -> syn::Result<TokenStream> {
   let mut cx = Context::new();
-  let container: Container<'_> = match Container::from_ast(&mut cx, input) {
+  let container: Container<'_> = match Container::from_ast(&mut cx, input, Derive::MetaType) {
     Some(cont) => cont,
     None => {
       cx.check()?;
@@ -16,81 +19,731 @@ pub fn expand_derive_meta_type(input: &mut syn::DeriveInput) -> syn::Result<Toke
     }
   };
 
-  let rich: Path = parse_quote!(_rich);
-  let ident: &Ident = &container.ident;
-  let meta_ident: Ident = match container.attributes.meta.name.as_ref() {
-    Some(name) => name.clone(),
-    None => Ident::new(&format!("{ident}Meta"), ident.span()),
-  };
-  let meta_type: TokenStream = match container.data {
-    Data::Enum(_) => meta_struct(&meta_ident),
-    Data::Struct(Style::Unit, _) => meta_unit(&meta_ident, &container),
-    Data::Struct(Style::Newtype, _) => meta_newtype(&meta_ident),
-    Data::Struct(Style::Tuple, _) => meta_newtype(&meta_ident),
-    Data::Struct(Style::Struct, _) => meta_newtype(&meta_ident),
-  };
+  let derived = Derived::new(&container);
+  derived.check_discriminant(&mut cx);
+  derived.check_params(&mut cx);
 
-  let impl_block = quote! {
+  let meta_type = derived.meta_type();
+  let rich_twin = derived.rich_twin();
+  let projection_impl = derived.projection_impl();
+  let split_merge_impls = derived.split_merge_impls();
+  let runtime_impls = derived.runtime_impls();
+
+  cx.check()?;
+
+  let impl_block = dummy::wrap_in_const(
+    "rich",
+    None,
+    &derived.rich,
+    quote! {
+      #projection_impl
+      #split_merge_impls
+      #runtime_impls
+    },
+  );
+
+  Ok(quote! {
     #meta_type
 
-    #[automatically_derived]
-    impl #rich::MetaType for #ident {
-      type Meta = #meta_ident;
-    }
-  };
+    #rich_twin
 
-  cx.check()?;
+    #impl_block
+  })
+}
 
-  Ok(dummy::wrap_in_const(None, &rich, impl_block))
+/// Shared state for the code generated by `#[derive(MetaType)]`.
+struct Derived<'a> {
+  /// Local path to the `rich` crate.
+  rich: Path,
+  container: &'a Container<'a>,
+  /// Name of the metadata type: a struct for structs, and an enum with the
+  /// metadata for the fields of each variant for enums.
+  meta_ident: Ident,
+  /// Type parameter for the metadata.
+  meta_param: Ident,
 }
 
-#[allow(unused)]
-fn meta_enum(meta_ident: &Ident) -> TokenStream {
-  quote! {
-    #[derive(Default)]
-    pub enum #meta_ident {}
+impl<'a> Derived<'a> {
+  fn new(container: &'a Container<'a>) -> Self {
+    let ident = &container.ident;
+    let meta_ident: Ident = match container.attributes.meta.name.as_ref() {
+      Some(name) => name.clone(),
+      None => Ident::new(&format!("{ident}Meta"), ident.span()),
+    };
+    Self {
+      rich: parse_quote!(_rich),
+      container,
+      meta_ident,
+      meta_param: Ident::new("TyMeta", Span::call_site()),
+    }
   }
-}
 
-fn meta_unit(meta_ident: &Ident, container: &Container) -> TokenStream {
-  let meta = ForwardMeta(&container.attributes.meta.attr);
-  quote! {
-    #meta
-    pub struct #meta_ident;
+  /// Report `#[meta(discriminant = ...)]` on structs, which have no
+  /// discriminant.
+  fn check_discriminant(&self, cx: &mut Context) {
+    if let (Data::Struct(..), Some(discriminant)) = (&self.container.data, &self.container.attributes.meta.discriminant)
+    {
+      cx.error_spanned_by(discriminant, "`discriminant` is only supported on enums");
+    }
   }
-}
 
-fn meta_newtype(meta_ident: &Ident) -> TokenStream {
-  quote! {
-    #[derive(Default)]
-    pub struct #meta_ident(u32);
+  /// Report generic parameters of the container clashing with the metadata
+  /// type parameter added by the derive.
+  fn check_params(&self, cx: &mut Context) {
+    for param in &self.container.generics.params {
+      if matches!(param, syn::GenericParam::Lifetime(_)) {
+        continue;
+      }
+      let ident = bound::param_ident(param);
+      if *ident == self.meta_param {
+        cx.error_spanned_by(
+          param,
+          format!("the type parameter name `{ident}` is reserved by the derive"),
+        );
+      }
+    }
   }
-}
 
-#[allow(unused)]
-fn meta_tuple(meta_ident: &Ident) -> TokenStream {
-  quote! {
-    #[derive(Default)]
-    pub struct #meta_ident(u32, u32);
+  /// `DiscriminantMeta` marker of an enum: `WithDiscriminant` unless
+  /// overridden by `#[meta(discriminant = ...)]`.
+  fn discriminant(&self, rich: &TokenStream) -> TokenStream {
+    match self.container.attributes.meta.discriminant.as_ref() {
+      Some(discriminant) => discriminant.to_token_stream(),
+      None => quote! { #rich::WithDiscriminant },
+    }
+  }
+
+  /// Whether the metadata type is generic over the metadata: this is only the
+  /// case if there is at least one field to hold it.
+  fn has_meta_param(&self) -> bool {
+    self.container.data.all_fields().any(Field::has_meta)
   }
-}
 
-fn meta_struct(meta_ident: &Ident) -> TokenStream {
-  quote! {
-    #[derive(Default)]
-    pub struct #meta_ident {
-      foo: u32,
+  /// Bounds required by the metadata types of the fields: either the ones
+  /// from `#[meta(bound = "...")]`, or `TreeMetaProjection<TyMeta>` for each
+  /// type parameter used by a field.
+  fn projection_bounds(&self, rich: &TokenStream) -> Vec<syn::WherePredicate> {
+    if let Some(bound) = self.container.attributes.meta.bound.as_ref() {
+      return bound.clone();
     }
+    let meta_param = &self.meta_param;
+    bound::field_type_param_bounds(self.container, &quote! { #rich::TreeMetaProjection<#meta_param> })
+  }
+
+  /// Build the generics of the metadata type: the generics of the container
+  /// used by the fields, followed by the metadata type parameter.
+  ///
+  /// Unused parameters are dropped, along with the where predicates
+  /// mentioning them, as they are not allowed in a type definition.
+  fn meta_generics(&self, rich: &TokenStream) -> syn::Generics {
+    let mut generics = bound::without_defaults(self.container.generics);
+    let field_tys: Vec<TokenStream> = self
+      .container
+      .data
+      .all_fields()
+      .filter_map(|field| self.field_meta_ty(rich, field))
+      .collect();
+    let (used, unused): (Vec<syn::GenericParam>, Vec<syn::GenericParam>) =
+      generics.params.iter().cloned().partition(|param| {
+        let ident = bound::param_ident(param);
+        field_tys.iter().any(|ty| bound::mentions_any(ty.clone(), &[ident]))
+      });
+    let unused: Vec<&Ident> = unused.iter().map(bound::param_ident).collect();
+    generics.params = used.into_iter().collect();
+    if let Some(where_clause) = generics.where_clause.as_mut() {
+      where_clause.predicates = core::mem::take(&mut where_clause.predicates)
+        .into_iter()
+        .filter(|predicate| !bound::mentions_any(predicate.to_token_stream(), &unused))
+        .collect();
+    }
+    if self.has_meta_param() {
+      let meta_param = &self.meta_param;
+      generics.params.push(parse_quote!(#meta_param));
+    }
+    bound::with_where_predicates(&generics, &self.projection_bounds(rich))
+  }
+
+  /// Bounds required to merge the metadata of the fields: either the ones
+  /// from `#[meta(bound = "...")]`, or `MergeMeta<TyMeta>` for each type
+  /// parameter used by a field.
+  fn merge_bounds(&self, rich: &TokenStream) -> Vec<syn::WherePredicate> {
+    if let Some(bound) = self.container.attributes.meta.bound.as_ref() {
+      return bound.clone();
+    }
+    let meta_param = &self.meta_param;
+    bound::field_type_param_bounds(self.container, &quote! { #rich::MergeMeta<#meta_param> })
+  }
+
+  /// Bounds required to build synthetic metadata or align the metadata of the
+  /// fields: `bound` for each type parameter used by a field, after the ones
+  /// from `#[meta(bound = "...")]`.
+  ///
+  /// The inferred bounds are kept along with an override, as these impls are
+  /// generated for every container: an override written for the metadata type
+  /// alone must not prevent them from compiling.
+  ///
+  /// The metadata must implement `RuntimeMeta`, along with the discriminant
+  /// metadata of enums, and the nested metadata of `#[meta(with = ...)]`
+  /// fields is built with `Default`.
+  fn runtime_bounds(&self, rich: &TokenStream, bound: TokenStream) -> Vec<syn::WherePredicate> {
+    let meta_param = &self.meta_param;
+    let mut bounds = self.container.attributes.meta.bound.clone().unwrap_or_default();
+    bounds.extend(bound::field_type_param_bounds(self.container, &bound));
+    bounds.push(parse_quote!(#meta_param: #rich::RuntimeMeta));
+    if let (Data::Enum(_), Some(discriminant)) = (&self.container.data, &self.container.attributes.meta.discriminant) {
+      bounds.push(parse_quote!(<#discriminant as #rich::DiscriminantMeta<#meta_param>>::Meta: #rich::RuntimeMeta));
+    }
+    for field in self.container.data.all_fields() {
+      if let FieldProjection::With(ty) = &field.attributes.projection {
+        bounds.push(parse_quote!(
+          <#ty as #rich::TreeMetaProjection<#meta_param>>::TreeMeta: ::core::default::Default
+        ));
+      }
+    }
+    bounds
+  }
+
+  /// Build the generics of the internal-metadata twin: the generics of the
+  /// container, followed by the metadata type parameter if there are fields.
+  fn twin_generics(&self, rich: &TokenStream) -> syn::Generics {
+    let mut generics = bound::without_defaults(self.container.generics);
+    if self.has_meta_param() {
+      let meta_param = &self.meta_param;
+      generics.params.push(parse_quote!(#meta_param));
+    }
+    bound::with_where_predicates(&generics, &self.merge_bounds(rich))
+  }
+
+  /// Type of the metadata for a field: its metadata along with the nested
+  /// metadata for its own components, or `None` for skipped fields.
+  fn field_meta_ty(&self, rich: &TokenStream, field: &Field<'_>) -> Option<TokenStream> {
+    let meta_param = &self.meta_param;
+    let projection = match &field.attributes.projection {
+      FieldProjection::Tree => field.ty,
+      FieldProjection::With(ty) => &**ty,
+      FieldProjection::Opaque => return Some(quote! { #meta_param }),
+      FieldProjection::Skip => return None,
+    };
+    Some(quote! { #rich::TreeMeta<#meta_param, <#projection as #rich::TreeMetaProjection<#meta_param>>::TreeMeta> })
+  }
+
+  /// Fields of the metadata type for `fields`, as `(visibility, member, type)`.
+  fn meta_fields<'f>(
+    &self,
+    rich: &TokenStream,
+    fields: &'f [Field<'f>],
+  ) -> Vec<(&'f syn::Visibility, syn::Member, TokenStream)> {
+    fields
+      .iter()
+      .zip(meta_members(fields))
+      .filter_map(|(field, member)| Some((&field.original.vis, member?, self.field_meta_ty(rich, field)?)))
+      .collect()
+  }
+
+  fn meta_type(&self) -> TokenStream {
+    let rich = quote! { ::rich };
+    let meta = ForwardMeta(&self.container.attributes.meta.attr);
+    let vis = &self.container.original.vis;
+    let meta_ident = &self.meta_ident;
+    let generics = self.meta_generics(&rich);
+    let (_, _, where_clause) = generics.split_for_impl();
+    let params = &generics.params;
+    let params = if params.is_empty() {
+      quote! {}
+    } else {
+      quote! { <#params> }
+    };
+    match &self.container.data {
+      Data::Struct(style, fields) => {
+        let meta_fields = self.meta_fields(&rich, fields);
+        let field_vis = meta_fields.iter().map(|(vis, _, _)| vis);
+        let members = meta_fields.iter().map(|(_, member, _)| member);
+        let field_tys = meta_fields.iter().map(|(_, _, ty)| ty);
+        match style {
+          Style::Struct => {
+            quote! {
+              #meta
+              #vis struct #meta_ident #params #where_clause {
+                #(#field_vis #members: #field_tys,)*
+              }
+            }
+          }
+          Style::Tuple | Style::Newtype => quote! {
+            #meta
+            #vis struct #meta_ident #params(#(#field_vis #field_tys),*) #where_clause;
+          },
+          Style::Unit => quote! {
+            #meta
+            #vis struct #meta_ident #params #where_clause;
+          },
+        }
+      }
+      Data::Enum(variants) => {
+        let variants = variants.iter().map(|variant| {
+          let variant_ident = &variant.ident;
+          let meta_fields = self.meta_fields(&rich, &variant.fields);
+          let members = meta_fields.iter().map(|(_, member, _)| member);
+          let field_tys = meta_fields.iter().map(|(_, _, ty)| ty);
+          match variant.style {
+            Style::Struct => quote! { #variant_ident { #(#members: #field_tys),* } },
+            Style::Tuple | Style::Newtype => quote! { #variant_ident(#(#field_tys),*) },
+            Style::Unit => quote! { #variant_ident },
+          }
+        });
+        quote! {
+          #meta
+          #vis enum #meta_ident #params #where_clause {
+            #(#variants,)*
+          }
+        }
+      }
+    }
+  }
+
+  /// Type of a field in the internal-metadata twin: the field with internal
+  /// metadata for its own components, attached to its metadata.
+  ///
+  /// Fields which don't get their metadata from their own type keep their
+  /// type, and are attached to their external metadata if they have some.
+  fn twin_field_ty(&self, rich: &TokenStream, field: &Field<'_>) -> TokenStream {
+    let ty = field.ty;
+    let meta_param = &self.meta_param;
+    match &field.attributes.projection {
+      FieldProjection::Tree => quote! { #rich::Rich<<#ty as #rich::MergeMeta<#meta_param>>::Rich, #meta_param> },
+      FieldProjection::Skip => quote! { #ty },
+      FieldProjection::Opaque | FieldProjection::With(_) => {
+        let meta_ty = self.field_meta_ty(rich, field);
+        quote! { #rich::Rich<#ty, #meta_ty> }
+      }
+    }
+  }
+
+  /// Generate the internal-metadata twin requested with
+  /// `#[meta(rich_twin = ...)]`.
+  ///
+  /// For enums, this is the type of the variant with internal metadata, used
+  /// as `EnumMetaFor::RichVariant`: the discriminant metadata is held by
+  /// `RichEnum`.
+  fn rich_twin(&self) -> TokenStream {
+    let Some(twin_ident) = self.container.attributes.meta.rich_twin.as_ref() else {
+      return quote! {};
+    };
+    let rich = quote! { ::rich };
+    let attr = ForwardMeta(&self.container.attributes.rich.attr);
+    let vis = &self.container.original.vis;
+    let generics = self.twin_generics(&rich);
+    let (_, _, where_clause) = generics.split_for_impl();
+    let params = &generics.params;
+    let params = if params.is_empty() {
+      quote! {}
+    } else {
+      quote! { <#params> }
+    };
+    match &self.container.data {
+      Data::Struct(style, fields) => {
+        let field_vis = fields.iter().map(|field| &field.original.vis);
+        let field_tys = fields.iter().map(|field| self.twin_field_ty(&rich, field));
+        match style {
+          Style::Struct => {
+            let members = fields.iter().map(|field| &field.member);
+            quote! {
+              #attr
+              #vis struct #twin_ident #params #where_clause {
+                #(#field_vis #members: #field_tys,)*
+              }
+            }
+          }
+          Style::Tuple | Style::Newtype => quote! {
+            #attr
+            #vis struct #twin_ident #params(#(#field_vis #field_tys),*) #where_clause;
+          },
+          Style::Unit => quote! {
+            #attr
+            #vis struct #twin_ident #params #where_clause;
+          },
+        }
+      }
+      Data::Enum(variants) => {
+        let variants = variants.iter().map(|variant| {
+          let variant_ident = &variant.ident;
+          let field_tys = variant.fields.iter().map(|field| self.twin_field_ty(&rich, field));
+          match variant.style {
+            Style::Struct => {
+              let members = variant.fields.iter().map(|field| &field.member);
+              quote! { #variant_ident { #(#members: #field_tys),* } }
+            }
+            Style::Tuple | Style::Newtype => quote! { #variant_ident(#(#field_tys),*) },
+            Style::Unit => quote! { #variant_ident },
+          }
+        });
+        quote! {
+          #attr
+          #vis enum #twin_ident #params #where_clause {
+            #(#variants,)*
+          }
+        }
+      }
+    }
+  }
+
+  /// Implement `TreeMetaProjection`. Enums use `EnumMeta` to attach metadata
+  /// to their discriminant, matching `EnumTreeMeta` with their
+  /// `DiscriminantMeta` marker.
+  fn projection_impl(&self) -> TokenStream {
+    let rich = &self.rich;
+    let rich = quote! { #rich };
+    let ident = &self.container.ident;
+    let meta_ident = &self.meta_ident;
+    let meta_param = &self.meta_param;
+    let generics = bound::without_defaults(self.container.generics);
+    let mut generics = bound::with_where_predicates(&generics, &self.projection_bounds(&rich));
+    generics.params.push(parse_quote!(#meta_param));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = self.container.generics.split_for_impl();
+    let meta_generics = self.meta_generics(&rich);
+    let (_, meta_ty_generics, _) = meta_generics.split_for_impl();
+    let tree_meta = match &self.container.data {
+      Data::Struct(..) => quote! { #meta_ident #meta_ty_generics },
+      Data::Enum(_) => {
+        let discriminant_meta = match self.container.attributes.meta.discriminant.as_ref() {
+          Some(discriminant) => quote! { <#discriminant as #rich::DiscriminantMeta<#meta_param>>::Meta },
+          None => quote! { #meta_param },
+        };
+        quote! { #rich::EnumMeta<#discriminant_meta, #meta_ident #meta_ty_generics> }
+      }
+    };
+
+    quote! {
+      #[automatically_derived]
+      impl #impl_generics #rich::TreeMetaProjection<#meta_param> for #ident #ty_generics #where_clause {
+        type TreeMeta = #tree_meta;
+      }
+    }
+  }
+
+  /// Split the fields of the twin bound to `values`.
+  ///
+  /// Returns the statements splitting the fields, and the fields of the value
+  /// and of the metadata, in braces.
+  fn split_fields(&self, fields: &[Field<'_>], values: &[Ident]) -> (TokenStream, TokenStream, TokenStream) {
+    let mut stmts = TokenStream::new();
+    let mut value_fields = Vec::new();
+    let mut meta_fields = Vec::new();
+    for ((field, value), meta_member) in fields.iter().zip(values).zip(meta_members(fields)) {
+      let member = &field.member;
+      match &field.attributes.projection {
+        FieldProjection::Tree => stmts.extend(quote! { let #value = #value.deep_split_meta(); }),
+        FieldProjection::Skip => {
+          value_fields.push(quote! { #member: #value });
+          continue;
+        }
+        FieldProjection::Opaque | FieldProjection::With(_) => {}
+      }
+      value_fields.push(quote! { #member: #value.value });
+      meta_fields.extend(meta_member.map(|meta_member| quote! { #meta_member: #value.meta }));
+    }
+    (
+      stmts,
+      quote! { { #(#value_fields),* } },
+      quote! { { #(#meta_fields),* } },
+    )
+  }
+
+  /// Merge the fields bound to `values` with their metadata.
+  ///
+  /// Returns the pattern binding the metadata to `metas`, and the fields of
+  /// the twin, in braces.
+  fn merge_fields(
+    &self,
+    rich: &TokenStream,
+    fields: &[Field<'_>],
+    values: &[Ident],
+    metas: &[Ident],
+  ) -> (TokenStream, TokenStream) {
+    let mut meta_pattern = Vec::new();
+    let mut twin_fields = Vec::new();
+    for (((field, value), meta), meta_member) in fields.iter().zip(values).zip(metas).zip(meta_members(fields)) {
+      let member = &field.member;
+      meta_pattern.extend(meta_member.map(|meta_member| quote! { #meta_member: #meta }));
+      let merged = match &field.attributes.projection {
+        FieldProjection::Tree => quote! { #rich::Rich::new(#value, #meta).deep_merge_meta()? },
+        FieldProjection::Skip => quote! { #value },
+        FieldProjection::Opaque | FieldProjection::With(_) => quote! { #rich::Rich::new(#value, #meta) },
+      };
+      twin_fields.push(quote! { #member: #merged });
+    }
+    (quote! { { #(#meta_pattern),* } }, quote! { { #(#twin_fields),* } })
+  }
+
+  /// Connect the container and its internal-metadata twin with `SplitMeta`
+  /// and `MergeMeta`. Enums implement `EnumMetaFor`, and use `RichEnum` as
+  /// their rich type.
+  fn split_merge_impls(&self) -> TokenStream {
+    let Some(twin_ident) = self.container.attributes.meta.rich_twin.as_ref() else {
+      return quote! {};
+    };
+    let rich = &self.rich;
+    let rich = quote! { #rich };
+    let ident = &self.container.ident;
+    let meta_ident = &self.meta_ident;
+    let meta_param = &self.meta_param;
+    let generics = bound::without_defaults(self.container.generics);
+    let mut generics = bound::with_where_predicates(&generics, &self.merge_bounds(&rich));
+    generics.params.push(parse_quote!(#meta_param));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = self.container.generics.split_for_impl();
+    let meta_generics = self.meta_generics(&rich);
+    let (_, meta_ty_generics, _) = meta_generics.split_for_impl();
+    let twin_generics = self.twin_generics(&rich);
+    let (_, twin_ty_generics, _) = twin_generics.split_for_impl();
+    let meta_ty = quote! { #meta_ident #meta_ty_generics };
+    let twin_ty = quote! { #twin_ident #twin_ty_generics };
+
+    match &self.container.data {
+      Data::Struct(_, fields) => {
+        let members: Vec<&syn::Member> = fields.iter().map(|field| &field.member).collect();
+        let values: Vec<Ident> = (0..members.len()).map(|i| format_ident!("__value{}", i)).collect();
+        let metas: Vec<Ident> = (0..members.len()).map(|i| format_ident!("__meta{}", i)).collect();
+        let (split_stmts, split_values, split_metas) = self.split_fields(fields, &values);
+        let (meta_pattern, merged) = self.merge_fields(&rich, fields, &values, &metas);
+        quote! {
+          #[automatically_derived]
+          impl #impl_generics #rich::SplitMeta<#meta_param> for #twin_ty #where_clause {
+            type Value = #ident #ty_generics;
+
+            fn split_meta(self) -> #rich::Rich<Self::Value, #meta_ty> {
+              let #twin_ident { #(#members: #values),* } = self;
+              #split_stmts
+              #rich::Rich::new(#ident #split_values, #meta_ident #split_metas)
+            }
+          }
+
+          #[automatically_derived]
+          impl #impl_generics #rich::MergeMeta<#meta_param> for #ident #ty_generics #where_clause {
+            type Rich = #twin_ty;
+
+            fn merge_meta(self, meta: #meta_ty) -> ::core::result::Result<Self::Rich, #rich::MergeMetaError> {
+              let #ident { #(#members: #values),* } = self;
+              let #meta_ident #meta_pattern = meta;
+              ::core::result::Result::Ok(#twin_ident #merged)
+            }
+          }
+        }
+      }
+      Data::Enum(variants) => {
+        let discriminant = self.discriminant(&rich);
+        let split_arms = variants.iter().map(|variant| {
+          let variant_ident = &variant.ident;
+          let members: Vec<&syn::Member> = variant.fields.iter().map(|field| &field.member).collect();
+          let values: Vec<Ident> = (0..members.len()).map(|i| format_ident!("__value{}", i)).collect();
+          let (split_stmts, split_values, split_metas) = self.split_fields(&variant.fields, &values);
+          quote! {
+            #twin_ident::#variant_ident { #(#members: #values),* } => {
+              #split_stmts
+              #rich::Rich::new(#ident::#variant_ident #split_values, #meta_ident::#variant_ident #split_metas)
+            }
+          }
+        });
+        let merge_arms = variants.iter().map(|variant| {
+          let variant_ident = &variant.ident;
+          let members: Vec<&syn::Member> = variant.fields.iter().map(|field| &field.member).collect();
+          let values: Vec<Ident> = (0..members.len()).map(|i| format_ident!("__value{}", i)).collect();
+          let metas: Vec<Ident> = (0..members.len()).map(|i| format_ident!("__meta{}", i)).collect();
+          let (meta_pattern, merged) = self.merge_fields(&rich, &variant.fields, &values, &metas);
+          quote! {
+            (
+              #ident::#variant_ident { #(#members: #values),* },
+              #meta_ident::#variant_ident #meta_pattern,
+            ) => ::core::result::Result::Ok(#twin_ident::#variant_ident #merged)
+          }
+        });
+        let merge_body = if variants.is_empty() {
+          quote! { match self {} }
+        } else {
+          let variant_idents: Vec<&Ident> = variants.iter().map(|variant| &variant.ident).collect();
+          let variant_names: Vec<String> = variant_idents.iter().map(|ident| ident.to_string()).collect();
+          quote! {
+            match (self, meta) {
+              #(#merge_arms,)*
+              #[allow(unreachable_patterns)]
+              (value, meta) => ::core::result::Result::Err(#rich::MergeMetaError::VariantMismatch {
+                value: match value {
+                  #(#ident::#variant_idents { .. } => #variant_names,)*
+                },
+                meta: match meta {
+                  #(#meta_ident::#variant_idents { .. } => #variant_names,)*
+                },
+              }),
+            }
+          }
+        };
+        quote! {
+          #[automatically_derived]
+          impl #impl_generics #rich::EnumMetaFor<#meta_param> for #ident #ty_generics #where_clause {
+            type Discriminant = #discriminant;
+            type VariantMeta = #meta_ty;
+            type RichVariant = #twin_ty;
+
+            fn split_variant_meta(rich: Self::RichVariant) -> #rich::Rich<Self, Self::VariantMeta> {
+              match rich {
+                #(#split_arms)*
+              }
+            }
+
+            fn merge_variant_meta(
+              self,
+              meta: Self::VariantMeta,
+            ) -> ::core::result::Result<Self::RichVariant, #rich::MergeMetaError> {
+              #merge_body
+            }
+          }
+
+          #[automatically_derived]
+          impl #impl_generics #rich::MergeMeta<#meta_param> for #ident #ty_generics #where_clause {
+            type Rich = #rich::RichEnum<Self, #meta_param>;
+
+            fn merge_meta(self, meta: Self::TreeMeta) -> ::core::result::Result<Self::Rich, #rich::MergeMetaError> {
+              #rich::RichEnum::merge(self, meta)
+            }
+          }
+        }
+      }
+    }
+  }
+
+  /// Build the patterns binding `fields` and their metadata, the fields of the
+  /// synthetic metadata, in braces, and the statements aligning the metadata.
+  ///
+  /// Only the fields getting their metadata from their own type are bound:
+  /// the metadata of the other ones doesn't depend on the shape of the value.
+  fn runtime_fields(
+    &self,
+    rich: &TokenStream,
+    fields: &[Field<'_>],
+  ) -> (TokenStream, TokenStream, TokenStream, TokenStream) {
+    let mut value_pattern = Vec::new();
+    let mut meta_pattern = Vec::new();
+    let mut synthetic_fields = Vec::new();
+    let mut align_stmts = TokenStream::new();
+    for (index, (field, meta_member)) in fields.iter().zip(meta_members(fields)).enumerate() {
+      let Some(meta_member) = meta_member else {
+        continue;
+      };
+      let member = &field.member;
+      let value = format_ident!("__value{}", index);
+      let meta = format_ident!("__meta{}", index);
+      let synthetic = match &field.attributes.projection {
+        FieldProjection::Tree => {
+          value_pattern.push(quote! { #member: #value });
+          meta_pattern.push(quote! { #meta_member: #meta });
+          align_stmts.extend(quote! { #rich::partial::align_tree_meta(#value, #meta); });
+          quote! { #rich::view_mut::synthetic_tree_meta(#value) }
+        }
+        FieldProjection::Opaque => quote! { #rich::RuntimeMeta::synthetic() },
+        FieldProjection::With(_) => {
+          quote! { #rich::TreeMeta::new(#rich::RuntimeMeta::synthetic(), ::core::default::Default::default()) }
+        }
+        FieldProjection::Skip => continue,
+      };
+      synthetic_fields.push(quote! { #meta_member: #synthetic });
+    }
+    (
+      quote! { { #(#value_pattern,)* .. } },
+      quote! { { #(#meta_pattern,)* .. } },
+      quote! { { #(#synthetic_fields),* } },
+      align_stmts,
+    )
   }
-}
 
-#[derive(Debug)]
-struct ForwardMeta<'a>(&'a [TokenStream]);
+  /// Implement `SyntheticMeta` and `AlignMeta`, used by `RichMut` to build
+  /// metadata for new values and to merge partial metadata.
+  ///
+  /// Enums use `EnumMeta::synthetic`, and replace the metadata for another
+  /// variant with synthetic metadata when aligning it.
+  fn runtime_impls(&self) -> TokenStream {
+    let rich = &self.rich;
+    let rich = quote! { #rich };
+    let ident = &self.container.ident;
+    let meta_ident = &self.meta_ident;
+    let meta_param = &self.meta_param;
+    let (_, ty_generics, _) = self.container.generics.split_for_impl();
+    let generics = bound::without_defaults(self.container.generics);
+    let mut synthetic_generics = bound::with_where_predicates(
+      &generics,
+      &self.runtime_bounds(&rich, quote! { #rich::SyntheticMeta<#meta_param> }),
+    );
+    synthetic_generics.params.push(parse_quote!(#meta_param));
+    let (synthetic_impl_generics, _, synthetic_where_clause) = synthetic_generics.split_for_impl();
+    let mut align_generics = bound::with_where_predicates(
+      &generics,
+      &self.runtime_bounds(&rich, quote! { #rich::AlignMeta<#meta_param> }),
+    );
+    align_generics.params.push(parse_quote!(#meta_param));
+    let (align_impl_generics, _, align_where_clause) = align_generics.split_for_impl();
 
-impl<'a> ToTokens for ForwardMeta<'a> {
-  fn to_tokens(&self, tokens: &mut TokenStream) {
-    for meta in self.0 {
-      tokens.append_all(quote! { #[#meta]})
+    let (synthetic_body, align_body) = match &self.container.data {
+      Data::Struct(_, fields) => {
+        let (value_pattern, meta_pattern, synthetic_fields, align_stmts) = self.runtime_fields(&rich, fields);
+        (
+          quote! {
+            let #ident #value_pattern = self;
+            #meta_ident #synthetic_fields
+          },
+          quote! {
+            let #ident #value_pattern = self;
+            let #meta_ident #meta_pattern = meta;
+            #align_stmts
+          },
+        )
+      }
+      Data::Enum(variants) if variants.is_empty() => (quote! { match *self {} }, quote! { match *self {} }),
+      Data::Enum(variants) => {
+        let mut synthetic_arms = Vec::new();
+        let mut align_arms = Vec::new();
+        for variant in variants {
+          let variant_ident = &variant.ident;
+          let (value_pattern, meta_pattern, synthetic_fields, align_stmts) =
+            self.runtime_fields(&rich, &variant.fields);
+          synthetic_arms.push(quote! {
+            #ident::#variant_ident #value_pattern => #meta_ident::#variant_ident #synthetic_fields
+          });
+          align_arms.push(quote! {
+            (#ident::#variant_ident #value_pattern, #meta_ident::#variant_ident #meta_pattern) => {
+              #align_stmts
+            }
+          });
+        }
+        (
+          quote! {
+            #rich::EnumMeta::synthetic(match self {
+              #(#synthetic_arms,)*
+            })
+          },
+          quote! {
+            match (self, &mut meta.variant) {
+              #(#align_arms)*
+              #[allow(unreachable_patterns)]
+              _ => *meta = #rich::SyntheticMeta::synthetic_meta(self),
+            }
+          },
+        )
+      }
+    };
+
+    quote! {
+      #[automatically_derived]
+      impl #synthetic_impl_generics #rich::SyntheticMeta<#meta_param> for #ident #ty_generics #synthetic_where_clause {
+        fn synthetic_meta(&self) -> Self::TreeMeta {
+          #synthetic_body
+        }
+      }
+
+      #[automatically_derived]
+      impl #align_impl_generics #rich::AlignMeta<#meta_param> for #ident #ty_generics #align_where_clause {
+        fn align_meta(&self, meta: &mut Self::TreeMeta) {
+          #align_body
+        }
+      }
     }
   }
 }
@@ -98,8 +751,8 @@ impl<'a> ToTokens for ForwardMeta<'a> {
 #[cfg(test)]
 mod test {
   use super::*;
-  use syn::parse2;
   use syn::DeriveInput;
+  use syn::parse2;
 
   #[test]
   fn unit_struct() {
@@ -113,22 +766,896 @@ mod test {
 
     // language=rust
     let expected = quote! {
+      #[derive(Default, Debug)]
+      struct MetaUnit;
+
       #[doc(hidden)]
       #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
       const _: () = {
         #[allow(unused_extern_crates, clippy::useless_attribute)]
         extern crate rich as _rich;
 
-        #[derive(Default, Debug)]
-        pub struct MetaUnit;
+        #[automatically_derived]
+        impl<TyMeta> _rich::TreeMetaProjection<TyMeta> for MyUnit {
+          type TreeMeta = MetaUnit;
+        }
 
         #[automatically_derived]
-        impl _rich::MetaType for MyUnit {
-          type Meta = MetaUnit;
+        impl<TyMeta> _rich::SyntheticMeta<TyMeta> for MyUnit
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            let MyUnit { .. } = self;
+            MetaUnit {}
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::AlignMeta<TyMeta> for MyUnit
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            let MyUnit { .. } = self;
+            let MetaUnit { .. } = meta;
+          }
         }
       };
     };
 
     assert_eq!(actual.to_string(), expected.to_string());
   }
+
+  #[test]
+  fn named_struct() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(attr(derive(Debug)))]
+      pub struct Mascot {
+        pub is_crab: bool,
+        price: u32,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      #[derive(Debug)]
+      pub struct MascotMeta<TyMeta> {
+        pub is_crab: ::rich::TreeMeta<TyMeta, <bool as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+        price: ::rich::TreeMeta<TyMeta, <u32 as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+      }
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::TreeMetaProjection<TyMeta> for Mascot {
+          type TreeMeta = MascotMeta<TyMeta>;
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::SyntheticMeta<TyMeta> for Mascot
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            let Mascot { is_crab: __value0, price: __value1, .. } = self;
+            MascotMeta {
+              is_crab: _rich::view_mut::synthetic_tree_meta(__value0),
+              price: _rich::view_mut::synthetic_tree_meta(__value1)
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::AlignMeta<TyMeta> for Mascot
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            let Mascot { is_crab: __value0, price: __value1, .. } = self;
+            let MascotMeta { is_crab: __meta0, price: __meta1, .. } = meta;
+            _rich::partial::align_tree_meta(__value0, __meta0);
+            _rich::partial::align_tree_meta(__value1, __meta1);
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn tuple_struct() {
+    let mut input: DeriveInput = parse2(quote! {
+      struct Point(pub i32, pub i32);
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      struct PointMeta<TyMeta>(
+        pub ::rich::TreeMeta<TyMeta, <i32 as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+        pub ::rich::TreeMeta<TyMeta, <i32 as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>
+      );
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::TreeMetaProjection<TyMeta> for Point {
+          type TreeMeta = PointMeta<TyMeta>;
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::SyntheticMeta<TyMeta> for Point
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            let Point { 0: __value0, 1: __value1, .. } = self;
+            PointMeta {
+              0: _rich::view_mut::synthetic_tree_meta(__value0),
+              1: _rich::view_mut::synthetic_tree_meta(__value1)
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::AlignMeta<TyMeta> for Point
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            let Point { 0: __value0, 1: __value1, .. } = self;
+            let PointMeta { 0: __meta0, 1: __meta1, .. } = meta;
+            _rich::partial::align_tree_meta(__value0, __meta0);
+            _rich::partial::align_tree_meta(__value1, __meta1);
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn enum_variants() {
+    let mut input: DeriveInput = parse2(quote! {
+      enum Operation {
+        Read,
+        Update { is_admin: bool, rate_limit: u32 },
+        Delete(bool),
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      enum OperationMeta<TyMeta> {
+        Read,
+        Update {
+          is_admin: ::rich::TreeMeta<TyMeta, <bool as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+          rate_limit: ::rich::TreeMeta<TyMeta, <u32 as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>
+        },
+        Delete(::rich::TreeMeta<TyMeta, <bool as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>),
+      }
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::TreeMetaProjection<TyMeta> for Operation {
+          type TreeMeta = _rich::EnumMeta<TyMeta, OperationMeta<TyMeta> >;
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::SyntheticMeta<TyMeta> for Operation
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            _rich::EnumMeta::synthetic(match self {
+              Operation::Read { .. } => OperationMeta::Read {},
+              Operation::Update { is_admin: __value0, rate_limit: __value1, .. } => OperationMeta::Update {
+                is_admin: _rich::view_mut::synthetic_tree_meta(__value0),
+                rate_limit: _rich::view_mut::synthetic_tree_meta(__value1)
+              },
+              Operation::Delete { 0: __value0, .. } => OperationMeta::Delete {
+                0: _rich::view_mut::synthetic_tree_meta(__value0)
+              },
+            })
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::AlignMeta<TyMeta> for Operation
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            match (self, &mut meta.variant) {
+              (Operation::Read { .. }, OperationMeta::Read { .. }) => {}
+              (
+                Operation::Update { is_admin: __value0, rate_limit: __value1, .. },
+                OperationMeta::Update { is_admin: __meta0, rate_limit: __meta1, .. }
+              ) => {
+                _rich::partial::align_tree_meta(__value0, __meta0);
+                _rich::partial::align_tree_meta(__value1, __meta1);
+              }
+              (Operation::Delete { 0: __value0, .. }, OperationMeta::Delete { 0: __meta0, .. }) => {
+                _rich::partial::align_tree_meta(__value0, __meta0);
+              }
+              #[allow(unreachable_patterns)]
+              _ => *meta = _rich::SyntheticMeta::synthetic_meta(self),
+            }
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn generic_struct() {
+    let mut input: DeriveInput = parse2(quote! {
+      struct Labelled<'a, L: Clone, T = u8, const N: usize = 2>
+      where
+        L: Default,
+        T: Copy,
+      {
+        label: &'a L,
+        values: [L; N],
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      struct LabelledMeta<'a, L: Clone, const N: usize, TyMeta>
+      where
+        L: Default,
+        L: ::rich::TreeMetaProjection<TyMeta>
+      {
+        label: ::rich::TreeMeta<TyMeta, <&'a L as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+        values: ::rich::TreeMeta<TyMeta, <[L; N] as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+      }
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<'a, L: Clone, T, const N: usize, TyMeta> _rich::TreeMetaProjection<TyMeta> for Labelled<'a, L, T, N>
+        where
+          L: Default,
+          T: Copy,
+          L: _rich::TreeMetaProjection<TyMeta>
+        {
+          type TreeMeta = LabelledMeta<'a, L, N, TyMeta>;
+        }
+
+        #[automatically_derived]
+        impl<'a, L: Clone, T, const N: usize, TyMeta> _rich::SyntheticMeta<TyMeta> for Labelled<'a, L, T, N>
+        where
+          L: Default,
+          T: Copy,
+          L: _rich::SyntheticMeta<TyMeta>,
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            let Labelled { label: __value0, values: __value1, .. } = self;
+            LabelledMeta {
+              label: _rich::view_mut::synthetic_tree_meta(__value0),
+              values: _rich::view_mut::synthetic_tree_meta(__value1)
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<'a, L: Clone, T, const N: usize, TyMeta> _rich::AlignMeta<TyMeta> for Labelled<'a, L, T, N>
+        where
+          L: Default,
+          T: Copy,
+          L: _rich::AlignMeta<TyMeta>,
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            let Labelled { label: __value0, values: __value1, .. } = self;
+            let LabelledMeta { label: __meta0, values: __meta1, .. } = meta;
+            _rich::partial::align_tree_meta(__value0, __meta0);
+            _rich::partial::align_tree_meta(__value1, __meta1);
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn bound_override() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(bound = "Vec<T>: ::rich::TreeMetaProjection<TyMeta>")]
+      struct Stack<T> {
+        items: Vec<T>,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      struct StackMeta<T, TyMeta>
+      where
+        Vec<T>: ::rich::TreeMetaProjection<TyMeta>
+      {
+        items: ::rich::TreeMeta<TyMeta, <Vec<T> as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+      }
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<T, TyMeta> _rich::TreeMetaProjection<TyMeta> for Stack<T>
+        where
+          Vec<T>: ::rich::TreeMetaProjection<TyMeta>
+        {
+          type TreeMeta = StackMeta<T, TyMeta>;
+        }
+
+        #[automatically_derived]
+        impl<T, TyMeta> _rich::SyntheticMeta<TyMeta> for Stack<T>
+        where
+          Vec<T>: ::rich::TreeMetaProjection<TyMeta>,
+          T: _rich::SyntheticMeta<TyMeta>,
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            let Stack { items: __value0, .. } = self;
+            StackMeta {
+              items: _rich::view_mut::synthetic_tree_meta(__value0)
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<T, TyMeta> _rich::AlignMeta<TyMeta> for Stack<T>
+        where
+          Vec<T>: ::rich::TreeMetaProjection<TyMeta>,
+          T: _rich::AlignMeta<TyMeta>,
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            let Stack { items: __value0, .. } = self;
+            let StackMeta { items: __meta0, .. } = meta;
+            _rich::partial::align_tree_meta(__value0, __meta0);
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn struct_rich_twin() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(rich_twin = RichMascot)]
+      #[rich(attr(derive(Debug)))]
+      pub struct Mascot {
+        pub is_crab: bool,
+        price: u32,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      pub struct MascotMeta<TyMeta> {
+        pub is_crab: ::rich::TreeMeta<TyMeta, <bool as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+        price: ::rich::TreeMeta<TyMeta, <u32 as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+      }
+
+      #[derive(Debug)]
+      pub struct RichMascot<TyMeta> {
+        pub is_crab: ::rich::Rich<<bool as ::rich::MergeMeta<TyMeta>>::Rich, TyMeta>,
+        price: ::rich::Rich<<u32 as ::rich::MergeMeta<TyMeta>>::Rich, TyMeta>,
+      }
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::TreeMetaProjection<TyMeta> for Mascot {
+          type TreeMeta = MascotMeta<TyMeta>;
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::SplitMeta<TyMeta> for RichMascot<TyMeta> {
+          type Value = Mascot;
+
+          fn split_meta(self) -> _rich::Rich<Self::Value, MascotMeta<TyMeta> > {
+            let RichMascot { is_crab: __value0, price: __value1 } = self;
+            let __value0 = __value0.deep_split_meta();
+            let __value1 = __value1.deep_split_meta();
+            _rich::Rich::new(
+              Mascot { is_crab: __value0.value, price: __value1.value },
+              MascotMeta { is_crab: __value0.meta, price: __value1.meta }
+            )
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::MergeMeta<TyMeta> for Mascot {
+          type Rich = RichMascot<TyMeta>;
+
+          fn merge_meta(self, meta: MascotMeta<TyMeta>) -> ::core::result::Result<Self::Rich, _rich::MergeMetaError> {
+            let Mascot { is_crab: __value0, price: __value1 } = self;
+            let MascotMeta { is_crab: __meta0, price: __meta1 } = meta;
+            ::core::result::Result::Ok(RichMascot {
+              is_crab: _rich::Rich::new(__value0, __meta0).deep_merge_meta()?,
+              price: _rich::Rich::new(__value1, __meta1).deep_merge_meta()?
+            })
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::SyntheticMeta<TyMeta> for Mascot
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            let Mascot { is_crab: __value0, price: __value1, .. } = self;
+            MascotMeta {
+              is_crab: _rich::view_mut::synthetic_tree_meta(__value0),
+              price: _rich::view_mut::synthetic_tree_meta(__value1)
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::AlignMeta<TyMeta> for Mascot
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            let Mascot { is_crab: __value0, price: __value1, .. } = self;
+            let MascotMeta { is_crab: __meta0, price: __meta1, .. } = meta;
+            _rich::partial::align_tree_meta(__value0, __meta0);
+            _rich::partial::align_tree_meta(__value1, __meta1);
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn enum_rich_twin() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(rich_twin = RichOperation)]
+      enum Operation {
+        Read,
+        Delete(bool),
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      enum OperationMeta<TyMeta> {
+        Read,
+        Delete(::rich::TreeMeta<TyMeta, <bool as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>),
+      }
+
+      enum RichOperation<TyMeta> {
+        Read,
+        Delete(::rich::Rich<<bool as ::rich::MergeMeta<TyMeta>>::Rich, TyMeta>),
+      }
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::TreeMetaProjection<TyMeta> for Operation {
+          type TreeMeta = _rich::EnumMeta<TyMeta, OperationMeta<TyMeta> >;
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::EnumMetaFor<TyMeta> for Operation {
+          type Discriminant = _rich::WithDiscriminant;
+          type VariantMeta = OperationMeta<TyMeta>;
+          type RichVariant = RichOperation<TyMeta>;
+
+          fn split_variant_meta(rich: Self::RichVariant) -> _rich::Rich<Self, Self::VariantMeta> {
+            match rich {
+              RichOperation::Read {} => {
+                _rich::Rich::new(Operation::Read {}, OperationMeta::Read {})
+              }
+              RichOperation::Delete { 0: __value0 } => {
+                let __value0 = __value0.deep_split_meta();
+                _rich::Rich::new(Operation::Delete { 0: __value0.value }, OperationMeta::Delete { 0: __value0.meta })
+              }
+            }
+          }
+
+          fn merge_variant_meta(
+            self,
+            meta: Self::VariantMeta,
+          ) -> ::core::result::Result<Self::RichVariant, _rich::MergeMetaError> {
+            match (self, meta) {
+              (Operation::Read {}, OperationMeta::Read {},) => ::core::result::Result::Ok(RichOperation::Read {}),
+              (Operation::Delete { 0: __value0 }, OperationMeta::Delete { 0: __meta0 },) => ::core::result::Result::Ok(
+                RichOperation::Delete {
+                  0: _rich::Rich::new(__value0, __meta0).deep_merge_meta()?
+                }
+              ),
+              #[allow(unreachable_patterns)]
+              (value, meta) => ::core::result::Result::Err(_rich::MergeMetaError::VariantMismatch {
+                value: match value {
+                  Operation::Read { .. } => "Read",
+                  Operation::Delete { .. } => "Delete",
+                },
+                meta: match meta {
+                  OperationMeta::Read { .. } => "Read",
+                  OperationMeta::Delete { .. } => "Delete",
+                },
+              }),
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::MergeMeta<TyMeta> for Operation {
+          type Rich = _rich::RichEnum<Self, TyMeta>;
+
+          fn merge_meta(self, meta: Self::TreeMeta) -> ::core::result::Result<Self::Rich, _rich::MergeMetaError> {
+            _rich::RichEnum::merge(self, meta)
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::SyntheticMeta<TyMeta> for Operation
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            _rich::EnumMeta::synthetic(match self {
+              Operation::Read { .. } => OperationMeta::Read {},
+              Operation::Delete { 0: __value0, .. } => OperationMeta::Delete {
+                0: _rich::view_mut::synthetic_tree_meta(__value0)
+              },
+            })
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::AlignMeta<TyMeta> for Operation
+        where
+          TyMeta: _rich::RuntimeMeta
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            match (self, &mut meta.variant) {
+              (Operation::Read { .. }, OperationMeta::Read { .. }) => {}
+              (Operation::Delete { 0: __value0, .. }, OperationMeta::Delete { 0: __meta0, .. }) => {
+                _rich::partial::align_tree_meta(__value0, __meta0);
+              }
+              #[allow(unreachable_patterns)]
+              _ => *meta = _rich::SyntheticMeta::synthetic_meta(self),
+            }
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn enum_discriminant_marker() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(rich_twin = RichLimit, discriminant = rich::WithoutDiscriminant)]
+      enum Limit {
+        Unlimited,
+        Fixed(u32),
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      enum LimitMeta<TyMeta> {
+        Unlimited,
+        Fixed(::rich::TreeMeta<TyMeta, <u32 as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>),
+      }
+
+      enum RichLimit<TyMeta> {
+        Unlimited,
+        Fixed(::rich::Rich<<u32 as ::rich::MergeMeta<TyMeta>>::Rich, TyMeta>),
+      }
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::TreeMetaProjection<TyMeta> for Limit {
+          type TreeMeta = _rich::EnumMeta<
+            <rich::WithoutDiscriminant as _rich::DiscriminantMeta<TyMeta>>::Meta,
+            LimitMeta<TyMeta>
+          >;
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::EnumMetaFor<TyMeta> for Limit {
+          type Discriminant = rich::WithoutDiscriminant;
+          type VariantMeta = LimitMeta<TyMeta>;
+          type RichVariant = RichLimit<TyMeta>;
+
+          fn split_variant_meta(rich: Self::RichVariant) -> _rich::Rich<Self, Self::VariantMeta> {
+            match rich {
+              RichLimit::Unlimited {} => {
+                _rich::Rich::new(Limit::Unlimited {}, LimitMeta::Unlimited {})
+              }
+              RichLimit::Fixed { 0: __value0 } => {
+                let __value0 = __value0.deep_split_meta();
+                _rich::Rich::new(Limit::Fixed { 0: __value0.value }, LimitMeta::Fixed { 0: __value0.meta })
+              }
+            }
+          }
+
+          fn merge_variant_meta(
+            self,
+            meta: Self::VariantMeta,
+          ) -> ::core::result::Result<Self::RichVariant, _rich::MergeMetaError> {
+            match (self, meta) {
+              (Limit::Unlimited {}, LimitMeta::Unlimited {},) => ::core::result::Result::Ok(RichLimit::Unlimited {}),
+              (Limit::Fixed { 0: __value0 }, LimitMeta::Fixed { 0: __meta0 },) => ::core::result::Result::Ok(
+                RichLimit::Fixed {
+                  0: _rich::Rich::new(__value0, __meta0).deep_merge_meta()?
+                }
+              ),
+              #[allow(unreachable_patterns)]
+              (value, meta) => ::core::result::Result::Err(_rich::MergeMetaError::VariantMismatch {
+                value: match value {
+                  Limit::Unlimited { .. } => "Unlimited",
+                  Limit::Fixed { .. } => "Fixed",
+                },
+                meta: match meta {
+                  LimitMeta::Unlimited { .. } => "Unlimited",
+                  LimitMeta::Fixed { .. } => "Fixed",
+                },
+              }),
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::MergeMeta<TyMeta> for Limit {
+          type Rich = _rich::RichEnum<Self, TyMeta>;
+
+          fn merge_meta(self, meta: Self::TreeMeta) -> ::core::result::Result<Self::Rich, _rich::MergeMetaError> {
+            _rich::RichEnum::merge(self, meta)
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::SyntheticMeta<TyMeta> for Limit
+        where
+          TyMeta: _rich::RuntimeMeta,
+          <rich::WithoutDiscriminant as _rich::DiscriminantMeta<TyMeta> >::Meta: _rich::RuntimeMeta
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            _rich::EnumMeta::synthetic(match self {
+              Limit::Unlimited { .. } => LimitMeta::Unlimited {},
+              Limit::Fixed { 0: __value0, .. } => LimitMeta::Fixed {
+                0: _rich::view_mut::synthetic_tree_meta(__value0)
+              },
+            })
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::AlignMeta<TyMeta> for Limit
+        where
+          TyMeta: _rich::RuntimeMeta,
+          <rich::WithoutDiscriminant as _rich::DiscriminantMeta<TyMeta> >::Meta: _rich::RuntimeMeta
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            match (self, &mut meta.variant) {
+              (Limit::Unlimited { .. }, LimitMeta::Unlimited { .. }) => {}
+              (Limit::Fixed { 0: __value0, .. }, LimitMeta::Fixed { 0: __meta0, .. }) => {
+                _rich::partial::align_tree_meta(__value0, __meta0);
+              }
+              #[allow(unreachable_patterns)]
+              _ => *meta = _rich::SyntheticMeta::synthetic_meta(self),
+            }
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn field_attributes() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(rich_twin = RichServer)]
+      struct Server {
+        #[meta(skip)]
+        cache: Vec<u8>,
+        #[meta(opaque)]
+        ports: Vec<u16>,
+        #[meta(rename = url_meta, with = UrlSpans)]
+        url: String,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      struct ServerMeta<TyMeta> {
+        ports: TyMeta,
+        url_meta: ::rich::TreeMeta<TyMeta, <UrlSpans as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta>,
+      }
+
+      struct RichServer<TyMeta> {
+        cache: Vec<u8>,
+        ports: ::rich::Rich<Vec<u16>, TyMeta>,
+        url: ::rich::Rich<String, ::rich::TreeMeta<TyMeta, <UrlSpans as ::rich::TreeMetaProjection<TyMeta>>::TreeMeta> >,
+      }
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::TreeMetaProjection<TyMeta> for Server {
+          type TreeMeta = ServerMeta<TyMeta>;
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::SplitMeta<TyMeta> for RichServer<TyMeta> {
+          type Value = Server;
+
+          fn split_meta(self) -> _rich::Rich<Self::Value, ServerMeta<TyMeta> > {
+            let RichServer { cache: __value0, ports: __value1, url: __value2 } = self;
+            _rich::Rich::new(
+              Server { cache: __value0, ports: __value1.value, url: __value2.value },
+              ServerMeta { ports: __value1.meta, url_meta: __value2.meta }
+            )
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::MergeMeta<TyMeta> for Server {
+          type Rich = RichServer<TyMeta>;
+
+          fn merge_meta(self, meta: ServerMeta<TyMeta>) -> ::core::result::Result<Self::Rich, _rich::MergeMetaError> {
+            let Server { cache: __value0, ports: __value1, url: __value2 } = self;
+            let ServerMeta { ports: __meta1, url_meta: __meta2 } = meta;
+            ::core::result::Result::Ok(RichServer {
+              cache: __value0,
+              ports: _rich::Rich::new(__value1, __meta1),
+              url: _rich::Rich::new(__value2, __meta2)
+            })
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::SyntheticMeta<TyMeta> for Server
+        where
+          TyMeta: _rich::RuntimeMeta,
+          <UrlSpans as _rich::TreeMetaProjection<TyMeta> >::TreeMeta: ::core::default::Default
+        {
+          fn synthetic_meta(&self) -> Self::TreeMeta {
+            let Server { .. } = self;
+            ServerMeta {
+              ports: _rich::RuntimeMeta::synthetic(),
+              url_meta: _rich::TreeMeta::new(_rich::RuntimeMeta::synthetic(), ::core::default::Default::default())
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::AlignMeta<TyMeta> for Server
+        where
+          TyMeta: _rich::RuntimeMeta,
+          <UrlSpans as _rich::TreeMetaProjection<TyMeta> >::TreeMeta: ::core::default::Default
+        {
+          fn align_meta(&self, meta: &mut Self::TreeMeta) {
+            let Server { .. } = self;
+            let ServerMeta { .. } = meta;
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn invalid_attributes() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(unknown)]
+      #[meta(discriminant = rich::WithoutDiscriminant)]
+      struct Point(#[meta(skip, opaque)] i32, #[meta(rename = y)] i32, #[meta(flatten)] i32);
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect_err("derive fails");
+
+    let messages: Vec<String> = actual.into_iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+      messages,
+      [
+        "unknown rich container attribute `unknown`",
+        "only one of `skip`, `opaque` and `with` may be used on a field",
+        "`rename` is only supported on named fields",
+        "unknown rich field attribute `flatten`",
+        "`discriminant` is only supported on enums",
+      ]
+    );
+  }
+
+  #[test]
+  fn reserved_generics() {
+    let mut input: DeriveInput = parse2(quote! {
+      struct Backend<TyMeta> {
+        inner: TyMeta,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_type(&mut input).expect_err("derive fails");
+
+    let messages: Vec<String> = actual.into_iter().map(|err| err.to_string()).collect();
+    assert_eq!(messages, ["the type parameter name `TyMeta` is reserved by the derive"]);
+  }
 }
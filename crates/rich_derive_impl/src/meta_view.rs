@@ -0,0 +1,667 @@
+use crate::bound;
+use crate::dummy;
+use crate::internals::Derive;
+use crate::internals::ast::{Container, Data, Field, Style, Variant, meta_members};
+use crate::internals::attr::{FieldProjection, ForwardMeta};
+use crate::internals::context::Context;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Path, parse_quote};
+
+pub fn expand_derive_meta_view(input: &mut syn::DeriveInput) -> syn::Result<TokenStream> {
+  let mut cx = Context::new();
+  let container: Container<'_> = match Container::from_ast(&mut cx, input, Derive::MetaView) {
+    Some(cont) => cont,
+    None => {
+      cx.check()?;
+      return Err(syn::Error::new(input.span(), "failed to build `Container` ast"));
+    }
+  };
+
+  let derived = Derived::new(&container);
+  derived.check_params(&mut cx);
+  derived.check_accessors(&mut cx);
+
+  let view_types = derived.view_types();
+  let view_impls = derived.view_impls();
+
+  cx.check()?;
+
+  let impl_block = dummy::wrap_in_const("rich", None, &derived.rich, view_impls);
+
+  Ok(quote! {
+    #view_types
+
+    #impl_block
+  })
+}
+
+/// Methods of `View` available on all the generated view types.
+const VIEW_METHODS: &[&str] = &[
+  "value",
+  "meta",
+  "meta_or",
+  "known_meta",
+  "nested",
+  "tree_meta",
+  "rich",
+  "typed",
+];
+
+/// Shared state for the code generated by `#[derive(MetaView)]`.
+struct Derived<'a> {
+  /// Local path to the `rich` crate.
+  rich: Path,
+  container: &'a Container<'a>,
+  /// Name of the view type.
+  view_ident: Ident,
+  /// Name of the enum holding the views for the fields of each variant.
+  variant_view_ident: Ident,
+  /// Name of the metadata type for the variants of an enum.
+  meta_ident: Ident,
+  /// Lifetime of the borrowed value and metadata.
+  lifetime: syn::Lifetime,
+  /// Type parameter for the metadata.
+  meta_param: Ident,
+}
+
+impl<'a> Derived<'a> {
+  fn new(container: &'a Container<'a>) -> Self {
+    let ident = &container.ident;
+    let view_ident: Ident = match container.attributes.view.name.as_ref() {
+      Some(name) => name.clone(),
+      None => Ident::new(&format!("{ident}View"), ident.span()),
+    };
+    let meta_ident: Ident = match container.attributes.meta.name.as_ref() {
+      Some(name) => name.clone(),
+      None => Ident::new(&format!("{ident}Meta"), ident.span()),
+    };
+    Self {
+      rich: parse_quote!(_rich),
+      container,
+      variant_view_ident: Ident::new(&format!("{ident}VariantView"), ident.span()),
+      view_ident,
+      meta_ident,
+      lifetime: parse_quote!('__rich),
+      meta_param: Ident::new("TyMeta", Span::call_site()),
+    }
+  }
+
+  /// Report generic parameters of the container clashing with the metadata
+  /// type parameter or the lifetime added by the derive.
+  fn check_params(&self, cx: &mut Context) {
+    for param in &self.container.generics.params {
+      let ident = bound::param_ident(param);
+      match param {
+        syn::GenericParam::Lifetime(_) if *ident == self.lifetime.ident => {
+          cx.error_spanned_by(
+            param,
+            format!("the lifetime name `{}` is reserved by the derive", self.lifetime),
+          );
+        }
+        syn::GenericParam::Type(_) | syn::GenericParam::Const(_) if *ident == self.meta_param => {
+          cx.error_spanned_by(
+            param,
+            format!("the type parameter name `{ident}` is reserved by the derive"),
+          );
+        }
+        _ => {}
+      }
+    }
+  }
+
+  /// Report struct fields whose accessor would shadow a method of `View`,
+  /// reachable from the view type through `Deref`.
+  fn check_accessors(&self, cx: &mut Context) {
+    let Data::Struct(_, fields) = &self.container.data else {
+      return;
+    };
+    for field in fields {
+      let syn::Member::Named(ident) = &field.member else {
+        continue;
+      };
+      if VIEW_METHODS.iter().any(|method| ident == method) {
+        cx.error_spanned_by(
+          field.original,
+          format!("the accessor for field `{ident}` would shadow the `View::{ident}` method"),
+        );
+      }
+    }
+  }
+
+  fn variants(&self) -> &'a [Variant<'a>] {
+    match &self.container.data {
+      Data::Enum(variants) => variants,
+      Data::Struct(..) => &[],
+    }
+  }
+
+  /// Whether the enum has a variant with fields holding metadata, and gets a
+  /// variant view.
+  fn has_variant_view(&self) -> bool {
+    self
+      .variants()
+      .iter()
+      .flat_map(|variant| &variant.fields)
+      .any(Field::has_meta)
+  }
+
+  /// Bounds required by the views of the fields: either the ones from
+  /// `#[view(bound = "...")]`, or `ViewMeta<TyMeta>` for each type parameter
+  /// used by a field.
+  fn view_bounds(&self, rich: &TokenStream) -> Vec<syn::WherePredicate> {
+    if let Some(bound) = self.container.attributes.view.bound.as_ref() {
+      return bound.clone();
+    }
+    let meta_param = &self.meta_param;
+    bound::field_type_param_bounds(self.container, &quote! { #rich::ViewMeta<#meta_param> })
+  }
+
+  /// Build the generics of the view types: the generics of the container,
+  /// preceded by the view lifetime and followed by the metadata type parameter.
+  ///
+  /// The value is borrowed for the view lifetime, so all the other parameters
+  /// must outlive it.
+  fn view_generics(&self, rich: &TokenStream) -> syn::Generics {
+    let mut generics = bound::without_defaults(self.container.generics);
+    let lifetime = &self.lifetime;
+    let meta_param = &self.meta_param;
+    for param in generics.lifetimes_mut() {
+      param.bounds.push(lifetime.clone());
+    }
+    for param in generics.type_params_mut() {
+      param.bounds.push(parse_quote!(#lifetime));
+    }
+    generics.params.insert(0, parse_quote!(#lifetime));
+    generics.params.push(parse_quote!(#meta_param: #lifetime));
+    bound::with_where_predicates(&generics, &self.view_bounds(rich))
+  }
+
+  /// Type of the view for a field.
+  ///
+  /// Skipped fields are viewed as a plain reference, and fields which don't
+  /// get their metadata from their own type are paired with their metadata.
+  fn field_view_ty(&self, rich: &TokenStream, field: &Field<'_>) -> TokenStream {
+    let ty = field.ty;
+    let lifetime = &self.lifetime;
+    let meta_param = &self.meta_param;
+    match &field.attributes.projection {
+      FieldProjection::Tree => quote! { <#ty as #rich::ViewMeta<#meta_param>>::View<#lifetime> },
+      FieldProjection::Skip => quote! { &#lifetime #ty },
+      FieldProjection::Opaque => {
+        quote! { #rich::Rich<&#lifetime #ty, ::core::option::Option<&#lifetime #meta_param>> }
+      }
+      FieldProjection::With(projection) => quote! {
+        #rich::Rich<
+          &#lifetime #ty,
+          ::core::option::Option<&#lifetime #rich::TreeMeta<#meta_param, <#projection as #rich::TreeMetaProjection<#meta_param>>::TreeMeta>>,
+        >
+      },
+    }
+  }
+
+  /// View a field from a reference to its value and its optional metadata.
+  fn field_view(&self, rich: &TokenStream, field: &Field<'_>, value: TokenStream, meta: TokenStream) -> TokenStream {
+    match &field.attributes.projection {
+      FieldProjection::Tree => quote! { #rich::View::new(#value, #meta).typed() },
+      FieldProjection::Skip => value,
+      FieldProjection::Opaque | FieldProjection::With(_) => quote! { #rich::Rich::new(#value, #meta) },
+    }
+  }
+
+  fn view_types(&self) -> TokenStream {
+    let rich = quote! { ::rich };
+    let meta = ForwardMeta(&self.container.attributes.view.attr);
+    let vis = &self.container.original.vis;
+    let ident = &self.container.ident;
+    let view_ident = &self.view_ident;
+    let lifetime = &self.lifetime;
+    let meta_param = &self.meta_param;
+    let (_, ty_generics, _) = self.container.generics.split_for_impl();
+    let generics = self.view_generics(&rich);
+    let (_, _, where_clause) = generics.split_for_impl();
+    let params = &generics.params;
+
+    let view_type = quote! {
+      #meta
+      #vis struct #view_ident<#params>(#rich::View<#lifetime, #ident #ty_generics, #meta_param>) #where_clause;
+    };
+
+    if !self.has_variant_view() {
+      return view_type;
+    }
+
+    let variant_view_ident = &self.variant_view_ident;
+    let variants = self.variants().iter().map(|variant| {
+      let variant_ident = &variant.ident;
+      let field_tys = variant.fields.iter().map(|field| self.field_view_ty(&rich, field));
+      match variant.style {
+        Style::Struct => {
+          let members = variant.fields.iter().map(|field| &field.member);
+          quote! { #variant_ident { #(#members: #field_tys),* } }
+        }
+        Style::Tuple | Style::Newtype => quote! { #variant_ident(#(#field_tys),*) },
+        Style::Unit => quote! { #variant_ident },
+      }
+    });
+
+    quote! {
+      #view_type
+
+      #vis enum #variant_view_ident<#params> #where_clause {
+        #(#variants,)*
+      }
+    }
+  }
+
+  fn view_impls(&self) -> TokenStream {
+    let rich = &self.rich;
+    let rich = quote! { #rich };
+    let ident = &self.container.ident;
+    let view_ident = &self.view_ident;
+    let lifetime = &self.lifetime;
+    let meta_param = &self.meta_param;
+    let (_, ty_generics, _) = self.container.generics.split_for_impl();
+    let generics = self.view_generics(&rich);
+    let (impl_generics, view_ty_generics, where_clause) = generics.split_for_impl();
+    let view = quote! { #rich::View<#lifetime, #ident #ty_generics, #meta_param> };
+
+    let mut meta_generics = bound::without_defaults(self.container.generics);
+    meta_generics.params.push(parse_quote!(#meta_param));
+    if let Some(where_clause) = where_clause {
+      meta_generics
+        .make_where_clause()
+        .predicates
+        .extend(where_clause.predicates.iter().cloned());
+    }
+    let (meta_impl_generics, _, meta_where_clause) = meta_generics.split_for_impl();
+
+    let accessors = self.accessors(&rich);
+
+    quote! {
+      #[automatically_derived]
+      impl #impl_generics ::core::clone::Clone for #view_ident #view_ty_generics #where_clause {
+        fn clone(&self) -> Self {
+          *self
+        }
+      }
+
+      #[automatically_derived]
+      impl #impl_generics ::core::marker::Copy for #view_ident #view_ty_generics #where_clause {}
+
+      #[automatically_derived]
+      impl #impl_generics ::core::convert::From<#view> for #view_ident #view_ty_generics #where_clause {
+        fn from(view: #view) -> Self {
+          Self(view)
+        }
+      }
+
+      #[automatically_derived]
+      impl #impl_generics ::core::ops::Deref for #view_ident #view_ty_generics #where_clause {
+        type Target = #view;
+
+        fn deref(&self) -> &Self::Target {
+          &self.0
+        }
+      }
+
+      #[automatically_derived]
+      impl #meta_impl_generics #rich::ViewMeta<#meta_param> for #ident #ty_generics #meta_where_clause {
+        type View<#lifetime> = #view_ident #view_ty_generics
+        where
+          Self: #lifetime,
+          #meta_param: #lifetime;
+      }
+
+      #[automatically_derived]
+      impl #impl_generics #view_ident #view_ty_generics #where_clause {
+        #accessors
+      }
+    }
+  }
+
+  /// Methods viewing the fields of a struct, or the variant of an enum.
+  fn accessors(&self, rich: &TokenStream) -> TokenStream {
+    match &self.container.data {
+      Data::Struct(_, fields) => {
+        let accessors = fields.iter().zip(meta_members(fields)).map(|(field, meta_member)| {
+          let member = &field.member;
+          let method = match member {
+            syn::Member::Named(ident) => ident.clone(),
+            syn::Member::Unnamed(index) => format_ident!("_{}", index.index),
+          };
+          let view_ty = self.field_view_ty(rich, field);
+          let value = quote! { &self.0.value().#member };
+          let meta = quote! { self.0.nested().map(|meta| &meta.#meta_member) };
+          let view = self.field_view(rich, field, value, meta);
+          quote! {
+            pub fn #method(&self) -> #view_ty {
+              #view
+            }
+          }
+        });
+        quote! { #(#accessors)* }
+      }
+      Data::Enum(_) if !self.has_variant_view() => quote! {},
+      Data::Enum(variants) => {
+        let ident = &self.container.ident;
+        let meta_ident = &self.meta_ident;
+        let variant_view_ident = &self.variant_view_ident;
+        let view_generics = self.view_generics(rich);
+        let (_, view_ty_generics, _) = view_generics.split_for_impl();
+        let arms = variants.iter().map(|variant| {
+          let variant_ident = &variant.ident;
+          let members: Vec<&syn::Member> = variant.fields.iter().map(|field| &field.member).collect();
+          if members.is_empty() {
+            return quote! {
+              #ident::#variant_ident { .. } => #variant_view_ident::#variant_ident
+            };
+          }
+          let values: Vec<Ident> = (0..members.len()).map(|i| format_ident!("__value{}", i)).collect();
+          let (meta_members, metas): (Vec<syn::Member>, Vec<Ident>) = meta_members(&variant.fields)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, member)| Some((member?, format_ident!("__meta{}", i))))
+            .unzip();
+          let nones = metas.iter().map(|_| quote! { ::core::option::Option::None });
+          let views = variant.fields.iter().enumerate().map(|(i, field)| {
+            let value = &values[i];
+            let meta = format_ident!("__meta{}", i);
+            self.field_view(rich, field, quote! { #value }, quote! { #meta })
+          });
+          // Variants where all fields are skipped have no metadata to extract
+          let meta_bindings = if metas.is_empty() {
+            quote! {}
+          } else {
+            quote! {
+              #[allow(unreachable_patterns)]
+              let (#(#metas,)*) = match variant_meta {
+                ::core::option::Option::Some(#meta_ident::#variant_ident { #(#meta_members: #metas),* }) => {
+                  (#(::core::option::Option::Some(#metas),)*)
+                }
+                _ => (#(#nones,)*),
+              };
+            }
+          };
+          quote! {
+            #ident::#variant_ident { #(#members: #values),* } => {
+              #meta_bindings
+              #variant_view_ident::#variant_ident {
+                #(#members: #views),*
+              }
+            }
+          }
+        });
+        quote! {
+          /// View the fields of the variant of the value.
+          pub fn variant(&self) -> #variant_view_ident #view_ty_generics {
+            let variant_meta = self.0.nested().map(|meta| &meta.variant);
+            match self.0.value() {
+              #(#arms,)*
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use syn::DeriveInput;
+  use syn::parse2;
+
+  fn with_derived<F>(input: TokenStream, f: F) -> TokenStream
+  where
+    F: FnOnce(&Derived<'_>) -> TokenStream,
+  {
+    let input: DeriveInput = parse2(input).expect("parsing succeeds");
+    let mut cx = Context::new();
+    let container = Container::from_ast(&mut cx, &input, Derive::MetaView).expect("container is valid");
+    cx.check().expect("container has no errors");
+    f(&Derived::new(&container))
+  }
+
+  #[test]
+  fn named_struct() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[view(attr(doc = "View of a `Config`"))]
+      pub struct Config {
+        pub name: String,
+        pub port: u16,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_view(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      #[doc = "View of a `Config`"]
+      pub struct ConfigView<'__rich, TyMeta: '__rich>(::rich::View<'__rich, Config, TyMeta>);
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich as _rich;
+
+        #[automatically_derived]
+        impl<'__rich, TyMeta: '__rich> ::core::clone::Clone for ConfigView<'__rich, TyMeta> {
+          fn clone(&self) -> Self {
+            *self
+          }
+        }
+
+        #[automatically_derived]
+        impl<'__rich, TyMeta: '__rich> ::core::marker::Copy for ConfigView<'__rich, TyMeta> {}
+
+        #[automatically_derived]
+        impl<'__rich, TyMeta: '__rich> ::core::convert::From<_rich::View<'__rich, Config, TyMeta> > for ConfigView<'__rich, TyMeta> {
+          fn from(view: _rich::View<'__rich, Config, TyMeta>) -> Self {
+            Self(view)
+          }
+        }
+
+        #[automatically_derived]
+        impl<'__rich, TyMeta: '__rich> ::core::ops::Deref for ConfigView<'__rich, TyMeta> {
+          type Target = _rich::View<'__rich, Config, TyMeta>;
+
+          fn deref(&self) -> &Self::Target {
+            &self.0
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyMeta> _rich::ViewMeta<TyMeta> for Config {
+          type View<'__rich> = ConfigView<'__rich, TyMeta>
+          where
+            Self: '__rich,
+            TyMeta: '__rich;
+        }
+
+        #[automatically_derived]
+        impl<'__rich, TyMeta: '__rich> ConfigView<'__rich, TyMeta> {
+          pub fn name(&self) -> <String as _rich::ViewMeta<TyMeta>>::View<'__rich> {
+            _rich::View::new(&self.0.value().name, self.0.nested().map(|meta| &meta.name)).typed()
+          }
+
+          pub fn port(&self) -> <u16 as _rich::ViewMeta<TyMeta>>::View<'__rich> {
+            _rich::View::new(&self.0.value().port, self.0.nested().map(|meta| &meta.port)).typed()
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn generic_enum() {
+    let input = quote! {
+      enum Operation<T> {
+        Read,
+        Update { is_admin: bool, rate_limit: T },
+        Delete(bool),
+      }
+    };
+
+    let actual = with_derived(input, |derived| {
+      let view_types = derived.view_types();
+      let accessors = derived.accessors(&quote! { _rich });
+      quote! { #view_types #accessors }
+    });
+
+    // language=rust
+    let expected = quote! {
+      struct OperationView<'__rich, T: '__rich, TyMeta: '__rich>(::rich::View<'__rich, Operation<T>, TyMeta>)
+      where
+        T: ::rich::ViewMeta<TyMeta>;
+
+      enum OperationVariantView<'__rich, T: '__rich, TyMeta: '__rich>
+      where
+        T: ::rich::ViewMeta<TyMeta>
+      {
+        Read,
+        Update {
+          is_admin: <bool as ::rich::ViewMeta<TyMeta>>::View<'__rich>,
+          rate_limit: <T as ::rich::ViewMeta<TyMeta>>::View<'__rich>
+        },
+        Delete(<bool as ::rich::ViewMeta<TyMeta>>::View<'__rich>),
+      }
+
+      /// View the fields of the variant of the value.
+      pub fn variant(&self) -> OperationVariantView<'__rich, T, TyMeta> {
+        let variant_meta = self.0.nested().map(|meta| &meta.variant);
+        match self.0.value() {
+          Operation::Read { .. } => OperationVariantView::Read,
+          Operation::Update { is_admin: __value0, rate_limit: __value1 } => {
+            #[allow(unreachable_patterns)]
+            let (__meta0, __meta1,) = match variant_meta {
+              ::core::option::Option::Some(OperationMeta::Update { is_admin: __meta0, rate_limit: __meta1 }) => {
+                (::core::option::Option::Some(__meta0), ::core::option::Option::Some(__meta1),)
+              }
+              _ => (::core::option::Option::None, ::core::option::Option::None,),
+            };
+            OperationVariantView::Update {
+              is_admin: _rich::View::new(__value0, __meta0).typed(),
+              rate_limit: _rich::View::new(__value1, __meta1).typed()
+            }
+          },
+          Operation::Delete { 0: __value0 } => {
+            #[allow(unreachable_patterns)]
+            let (__meta0,) = match variant_meta {
+              ::core::option::Option::Some(OperationMeta::Delete { 0: __meta0 }) => {
+                (::core::option::Option::Some(__meta0),)
+              }
+              _ => (::core::option::Option::None,),
+            };
+            OperationVariantView::Delete {
+              0: _rich::View::new(__value0, __meta0).typed()
+            }
+          },
+        }
+      }
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn bound_override() {
+    let input = quote! {
+      #[view(bound = "Vec<T>: ::rich::ViewMeta<TyMeta>")]
+      struct Stack<T = u8> {
+        items: Vec<T>,
+      }
+    };
+
+    let actual = with_derived(input, |derived| derived.view_types());
+
+    // language=rust
+    let expected = quote! {
+      struct StackView<'__rich, T: '__rich, TyMeta: '__rich>(::rich::View<'__rich, Stack<T>, TyMeta>)
+      where
+        Vec<T>: ::rich::ViewMeta<TyMeta>;
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn field_attributes() {
+    let input = quote! {
+      struct Server {
+        #[meta(skip)]
+        cache: Vec<u8>,
+        #[meta(opaque)]
+        ports: Vec<u16>,
+        #[meta(rename = host_meta)]
+        host: String,
+      }
+    };
+
+    let actual = with_derived(input, |derived| derived.accessors(&quote! { _rich }));
+
+    // language=rust
+    let expected = quote! {
+      pub fn cache(&self) -> &'__rich Vec<u8> {
+        &self.0.value().cache
+      }
+      pub fn ports(&self) -> _rich::Rich<&'__rich Vec<u16>, ::core::option::Option<&'__rich TyMeta>> {
+        _rich::Rich::new(&self.0.value().ports, self.0.nested().map(|meta| &meta.ports))
+      }
+      pub fn host(&self) -> <String as _rich::ViewMeta<TyMeta>>::View<'__rich> {
+        _rich::View::new(&self.0.value().host, self.0.nested().map(|meta| &meta.host_meta)).typed()
+      }
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn shadowed_view_methods() {
+    let mut input: DeriveInput = parse2(quote! {
+      struct Reading {
+        value: f64,
+        meta: String,
+        unit: String,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_view(&mut input).expect_err("derive fails");
+
+    let messages: Vec<String> = actual.into_iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+      messages,
+      [
+        "the accessor for field `value` would shadow the `View::value` method",
+        "the accessor for field `meta` would shadow the `View::meta` method",
+      ]
+    );
+  }
+
+  #[test]
+  fn reserved_generics() {
+    let mut input: DeriveInput = parse2(quote! {
+      struct Backend<'__rich, TyMeta> {
+        inner: &'__rich TyMeta,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_meta_view(&mut input).expect_err("derive fails");
+
+    let messages: Vec<String> = actual.into_iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+      messages,
+      [
+        "the lifetime name `'__rich` is reserved by the derive",
+        "the type parameter name `TyMeta` is reserved by the derive",
+      ]
+    );
+  }
+}
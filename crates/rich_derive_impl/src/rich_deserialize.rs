@@ -1,14 +1,18 @@
+use crate::bound;
 use crate::dummy;
-use crate::internals::ast::{Container, Data, Style};
+use crate::internals::Derive;
+use crate::internals::ast::{Container, Data, Field, Style};
+use crate::internals::attr::FieldProjection;
 use crate::internals::context::Context;
-use proc_macro2::{Ident, TokenStream};
-use quote::{quote, ToTokens, TokenStreamExt};
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{ToTokens, format_ident, quote};
+use syn::ext::IdentExt;
 use syn::spanned::Spanned;
-use syn::{parse_quote, Path};
+use syn::{Path, parse_quote};
 
 pub fn expand_derive_rich_deserialize(input: &mut syn::DeriveInput) -> syn::Result<TokenStream> {
   let mut cx = Context::new();
-  let container: Container<'_> = match Container::from_ast(&mut cx, input) {
+  let container: Container<'_> = match Container::from_ast(&mut cx, input, Derive::RichDeserialize) {
     Some(cont) => cont,
     None => {
       cx.check()?;
@@ -16,95 +20,539 @@ pub fn expand_derive_rich_deserialize(input: &mut syn::DeriveInput) -> syn::Resu
     }
   };
 
-  let rich: Path = parse_quote!(_rich);
-  let ident: &Ident = &container.ident;
-  let meta_ident: Ident = match container.attributes.meta.name.as_ref() {
-    Some(name) => name.clone(),
-    None => Ident::new(&format!("{ident}Meta"), ident.span()),
-  };
-  let meta_type: TokenStream = match container.data {
-    Data::Enum(_) => meta_struct(&meta_ident),
-    Data::Struct(Style::Unit, _) => rich_deserialize_unit_struct(&meta_ident, &container),
-    Data::Struct(Style::Newtype, _) => meta_newtype(&meta_ident),
-    Data::Struct(Style::Tuple, _) => meta_newtype(&meta_ident),
-    Data::Struct(Style::Struct, _) => meta_newtype(&meta_ident),
+  let Some(derived) = Derived::new(&mut cx, &container) else {
+    cx.check()?;
+    return Err(syn::Error::new(input.span(), "failed to check `RichDeserialize` input"));
   };
 
-  let impl_block = quote! {
-    #meta_type
-
-    #[automatically_derived]
-    impl #rich::MetaType for #ident {
-      type Meta = #meta_ident;
-    }
-  };
+  let rich_deserialize_impl = derived.rich_deserialize_impl();
 
   cx.check()?;
 
-  Ok(dummy::wrap_in_const(None, &rich, impl_block))
+  Ok(dummy::wrap_in_const(
+    "rich_serde",
+    None,
+    &derived.rich_serde,
+    rich_deserialize_impl,
+  ))
 }
 
-#[allow(unused)]
-fn meta_enum(meta_ident: &Ident) -> TokenStream {
-  quote! {
-    #[derive(Default)]
-    pub enum #meta_ident {}
-  }
+/// Shared state for the code generated by `#[derive(RichDeserialize)]`.
+struct Derived<'a> {
+  /// Local path to the `rich_serde` crate.
+  rich_serde: Path,
+  container: &'a Container<'a>,
+  /// Style of the struct.
+  style: Style,
+  /// Fields of the struct.
+  fields: &'a [Field<'a>],
+  /// Name of the internal-metadata twin built by the visitor.
+  twin_ident: &'a Ident,
 }
 
-fn rich_deserialize_unit_struct(meta_ident: &Ident, container: &Container) -> TokenStream {
-  let meta = ForwardMeta(&container.attributes.meta.attr);
-  quote! {
-    #meta
-    pub struct #meta_ident;
+impl<'a> Derived<'a> {
+  /// Check that the container is supported.
+  ///
+  /// The deserialized value is the internal-metadata twin generated by
+  /// `#[derive(MetaType)]`, so it must be requested with
+  /// `#[meta(rich_twin = ...)]`.
+  fn new(cx: &mut Context, container: &'a Container<'a>) -> Option<Self> {
+    let (style, fields) = match &container.data {
+      // Like serde, tuple structs with a single field are newtypes.
+      Data::Struct(Style::Tuple, fields) if fields.len() == 1 => (Style::Newtype, fields.as_slice()),
+      Data::Struct(style, fields) => (*style, fields.as_slice()),
+      Data::Enum(_) => {
+        cx.error_spanned_by(&container.ident, "`RichDeserialize` does not support enums");
+        return None;
+      }
+    };
+    for field in fields {
+      if let FieldProjection::With(_) = field.attributes.projection {
+        cx.error_spanned_by(
+          field.original,
+          "`RichDeserialize` does not support `#[meta(with = ...)]` fields",
+        );
+      }
+    }
+    let Some(twin_ident) = container.attributes.meta.rich_twin.as_ref() else {
+      cx.error_spanned_by(
+        &container.ident,
+        "`RichDeserialize` requires an internal-metadata twin, use `#[meta(rich_twin = ...)]`",
+      );
+      return None;
+    };
+    Some(Self {
+      rich_serde: parse_quote!(_rich_serde),
+      container,
+      style,
+      fields,
+      twin_ident,
+    })
   }
-}
 
-fn meta_newtype(meta_ident: &Ident) -> TokenStream {
-  quote! {
-    #[derive(Default)]
-    pub struct #meta_ident(u32);
+  /// Whether the visitor needs the scope: this is the case as soon as a field
+  /// gets some metadata.
+  fn uses_scope(&self) -> bool {
+    self.fields.iter().any(Field::has_meta)
   }
-}
 
-#[allow(unused)]
-fn meta_tuple(meta_ident: &Ident) -> TokenStream {
-  quote! {
-    #[derive(Default)]
-    pub struct #meta_ident(u32, u32);
+  /// Bounds required to deserialize the fields: `RichDeserialize<'de>` for
+  /// each type parameter used by a field getting its metadata from its own
+  /// type, and `Deserialize<'de>` for the other fields.
+  fn deserialize_bounds(&self) -> Vec<syn::WherePredicate> {
+    let rich_serde = &self.rich_serde;
+    let mut bounds = bound::field_type_param_bounds(self.container, &quote! { #rich_serde::RichDeserialize<'de> });
+    for param in self.container.generics.type_params() {
+      let ident = &param.ident;
+      let plain = self
+        .fields
+        .iter()
+        .filter(|field| !matches!(field.attributes.projection, FieldProjection::Tree))
+        .any(|field| bound::mentions_any(field.ty.to_token_stream(), &[ident]));
+      if plain {
+        bounds.push(parse_quote!(#ident: #rich_serde::serde::Deserialize<'de>));
+      }
+    }
+    bounds
+  }
+
+  /// Build the generics of the impl: the `'de` lifetime, followed by the
+  /// generics of the container.
+  fn de_generics(&self) -> syn::Generics {
+    let mut generics = bound::without_defaults(self.container.generics);
+    generics.params.insert(0, parse_quote!('de));
+    bound::with_where_predicates(&generics, &self.deserialize_bounds())
   }
-}
 
-fn meta_struct(meta_ident: &Ident) -> TokenStream {
-  quote! {
-    #[derive(Default)]
-    pub struct #meta_ident {
-      foo: u32,
+  /// Build the generics of the visitor: the `'__scope` lifetime if it holds
+  /// the scope, followed by the generics of the impl.
+  fn visitor_generics(&self) -> syn::Generics {
+    let mut generics = self.de_generics();
+    if self.uses_scope() {
+      generics.params.insert(0, parse_quote!('__scope));
     }
+    generics
   }
-}
 
-#[derive(Debug)]
-struct ForwardMeta<'a>(&'a [TokenStream]);
+  /// How the value of `field` is read from the input, and the conversion of
+  /// the read value bound to `__value` into the field of the twin.
+  ///
+  /// Fields getting their metadata from their own type are read with a
+  /// `RichScopeSerdeSeed`. Opaque fields are read as plain values and get
+  /// their metadata from the scope afterwards. Skipped fields are plain
+  /// values.
+  fn field_read(&self, field: &Field<'_>, error: &TokenStream) -> (FieldRead, TokenStream) {
+    let rich_serde = &self.rich_serde;
+    let ty = field.ty;
+    match &field.attributes.projection {
+      FieldProjection::Tree => (
+        FieldRead::Seed(quote! { #rich_serde::RichScopeSerdeSeed::<#ty>::new(&mut *self.scope) }),
+        quote! { __value },
+      ),
+      FieldProjection::Opaque => (
+        FieldRead::Plain(quote! { #ty }),
+        quote! { self.scope.attach(__value).map_err(<#error as #rich_serde::serde::de::Error>::custom)? },
+      ),
+      FieldProjection::Skip | FieldProjection::With(_) => (FieldRead::Plain(quote! { #ty }), quote! { __value }),
+    }
+  }
+
+  /// Build the twin from the fields bound to `values`.
+  fn twin_value(&self, values: &[Ident]) -> TokenStream {
+    let twin_ident = self.twin_ident;
+    match self.style {
+      Style::Struct => {
+        let members = self.fields.iter().map(|field| &field.member);
+        quote! { #twin_ident { #(#members: #values),* } }
+      }
+      Style::Tuple | Style::Newtype => quote! { #twin_ident(#(#values),*) },
+      Style::Unit => quote! { #twin_ident },
+    }
+  }
+
+  /// Serde name of a field, without the `r#` prefix of raw identifiers.
+  fn field_name(field: &Field<'_>) -> String {
+    match &field.member {
+      syn::Member::Named(ident) => ident.unraw().to_string(),
+      syn::Member::Unnamed(index) => index.index.to_string(),
+    }
+  }
+
+  /// Generate the field identifier enum, its visitor and its `Deserialize`
+  /// impl, matching fields by name or by index. Unknown fields are ignored.
+  fn field_identifier(&self, fields: &[Ident]) -> TokenStream {
+    let rich_serde = &self.rich_serde;
+    let serde = quote! { #rich_serde::serde };
+    let names: Vec<String> = self.fields.iter().map(Self::field_name).collect();
+    let bytes = names.iter().map(|name| Literal::byte_string(name.as_bytes()));
+    let indices = (0..fields.len()).map(|index| Literal::u64_suffixed(index as u64));
+
+    quote! {
+      #[allow(non_camel_case_types)]
+      #[doc(hidden)]
+      enum __Field {
+        #(#fields,)*
+        __ignore,
+      }
+
+      #[doc(hidden)]
+      struct __FieldVisitor;
+
+      #[automatically_derived]
+      impl<'de> #serde::de::Visitor<'de> for __FieldVisitor {
+        type Value = __Field;
 
-impl<'a> ToTokens for ForwardMeta<'a> {
-  fn to_tokens(&self, tokens: &mut TokenStream) {
-    for meta in self.0 {
-      tokens.append_all(quote! { #[#meta]})
+        fn expecting(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+          ::core::fmt::Formatter::write_str(__formatter, "field identifier")
+        }
+
+        fn visit_u64<__E>(self, __value: u64) -> ::core::result::Result<Self::Value, __E>
+        where
+          __E: #serde::de::Error,
+        {
+          match __value {
+            #(#indices => ::core::result::Result::Ok(__Field::#fields),)*
+            _ => ::core::result::Result::Ok(__Field::__ignore),
+          }
+        }
+
+        fn visit_str<__E>(self, __value: &str) -> ::core::result::Result<Self::Value, __E>
+        where
+          __E: #serde::de::Error,
+        {
+          match __value {
+            #(#names => ::core::result::Result::Ok(__Field::#fields),)*
+            _ => ::core::result::Result::Ok(__Field::__ignore),
+          }
+        }
+
+        fn visit_bytes<__E>(self, __value: &[u8]) -> ::core::result::Result<Self::Value, __E>
+        where
+          __E: #serde::de::Error,
+        {
+          match __value {
+            #(#bytes => ::core::result::Result::Ok(__Field::#fields),)*
+            _ => ::core::result::Result::Ok(__Field::__ignore),
+          }
+        }
+      }
+
+      #[automatically_derived]
+      impl<'de> #serde::Deserialize<'de> for __Field {
+        #[inline]
+        fn deserialize<__D>(__deserializer: __D) -> ::core::result::Result<Self, __D::Error>
+        where
+          __D: #serde::Deserializer<'de>,
+        {
+          #serde::Deserializer::deserialize_identifier(__deserializer, __FieldVisitor)
+        }
+      }
     }
   }
+
+  /// Generate `visit_seq`, reading the fields in order.
+  fn visit_seq(&self, values: &[Ident], expecting: &str) -> TokenStream {
+    let rich_serde = &self.rich_serde;
+    let serde = quote! { #rich_serde::serde };
+    let error = quote! { __A::Error };
+    let reads = self
+      .fields
+      .iter()
+      .zip(values)
+      .enumerate()
+      .map(|(index, (field, value))| {
+        let (read, convert) = self.field_read(field, &error);
+        let next = match read {
+          FieldRead::Seed(seed) => quote! { #serde::de::SeqAccess::next_element_seed(&mut __seq, #seed)? },
+          FieldRead::Plain(ty) => quote! { #serde::de::SeqAccess::next_element::<#ty>(&mut __seq)? },
+        };
+        quote! {
+          let #value = match #next {
+            ::core::option::Option::Some(__value) => #convert,
+            ::core::option::Option::None => {
+              return ::core::result::Result::Err(#serde::de::Error::invalid_length(#index, &#expecting));
+            }
+          };
+        }
+      });
+    let twin_value = self.twin_value(values);
+
+    quote! {
+      #[inline]
+      fn visit_seq<__A>(self, mut __seq: __A) -> ::core::result::Result<Self::Value, __A::Error>
+      where
+        __A: #serde::de::SeqAccess<'de>,
+      {
+        #(#reads)*
+        ::core::result::Result::Ok(#twin_value)
+      }
+    }
+  }
+
+  /// Generate `visit_map`, reading the fields by name and rejecting duplicate
+  /// fields.
+  ///
+  /// Missing fields are read from a `MissingFieldDeserializer`: like serde,
+  /// they are `None` for `Option` fields, and an error for other fields.
+  fn visit_map(&self, values: &[Ident]) -> TokenStream {
+    let rich_serde = &self.rich_serde;
+    let serde = quote! { #rich_serde::serde };
+    let error = quote! { __A::Error };
+    let names: Vec<String> = self.fields.iter().map(Self::field_name).collect();
+    let arms = self
+      .fields
+      .iter()
+      .zip(values)
+      .zip(&names)
+      .map(|((field, value), name)| {
+        let (read, convert) = self.field_read(field, &error);
+        let next = match read {
+          FieldRead::Seed(seed) => quote! { #serde::de::MapAccess::next_value_seed(&mut __map, #seed)? },
+          FieldRead::Plain(ty) => quote! { #serde::de::MapAccess::next_value::<#ty>(&mut __map)? },
+        };
+        quote! {
+          __Field::#value => {
+            if ::core::option::Option::is_some(&#value) {
+              return ::core::result::Result::Err(<__A::Error as #serde::de::Error>::duplicate_field(#name));
+            }
+            let __value = #next;
+            #value = ::core::option::Option::Some(#convert);
+          }
+        }
+      });
+    let missing = self
+      .fields
+      .iter()
+      .zip(values)
+      .zip(&names)
+      .map(|((field, value), name)| {
+        let (read, convert) = self.field_read(field, &error);
+        let missing = quote! { #rich_serde::MissingFieldDeserializer::<__A::Error>::new(#name) };
+        let read = match read {
+          FieldRead::Seed(seed) => quote! { #serde::de::DeserializeSeed::deserialize(#seed, #missing)? },
+          FieldRead::Plain(ty) => quote! { <#ty as #serde::Deserialize>::deserialize(#missing)? },
+        };
+        quote! {
+          let #value = match #value {
+            ::core::option::Option::Some(#value) => #value,
+            ::core::option::Option::None => {
+              let __value = #read;
+              #convert
+            }
+          };
+        }
+      });
+    let twin_value = self.twin_value(values);
+
+    quote! {
+      #[inline]
+      fn visit_map<__A>(self, mut __map: __A) -> ::core::result::Result<Self::Value, __A::Error>
+      where
+        __A: #serde::de::MapAccess<'de>,
+      {
+        #(let mut #values = ::core::option::Option::None;)*
+        while let ::core::option::Option::Some(__key) = #serde::de::MapAccess::next_key::<__Field>(&mut __map)? {
+          match __key {
+            #(#arms)*
+            _ => {
+              let _ = #serde::de::MapAccess::next_value::<#serde::de::IgnoredAny>(&mut __map)?;
+            }
+          }
+        }
+        #(#missing)*
+        ::core::result::Result::Ok(#twin_value)
+      }
+    }
+  }
+
+  /// Generate `visit_newtype_struct`, reading the single field directly from
+  /// the deserializer.
+  fn visit_newtype_struct(&self, values: &[Ident]) -> TokenStream {
+    let rich_serde = &self.rich_serde;
+    let serde = quote! { #rich_serde::serde };
+    let field = &self.fields[0];
+    let value = &values[0];
+    let error = quote! { __E::Error };
+    let (read, convert) = self.field_read(field, &error);
+    let next = match read {
+      FieldRead::Seed(seed) => quote! { #serde::de::DeserializeSeed::deserialize(#seed, __e)? },
+      FieldRead::Plain(ty) => quote! { <#ty as #serde::Deserialize>::deserialize(__e)? },
+    };
+    let twin_value = self.twin_value(values);
+
+    quote! {
+      #[inline]
+      fn visit_newtype_struct<__E>(self, __e: __E) -> ::core::result::Result<Self::Value, __E::Error>
+      where
+        __E: #serde::Deserializer<'de>,
+      {
+        let __value = #next;
+        let #value = #convert;
+        ::core::result::Result::Ok(#twin_value)
+      }
+    }
+  }
+
+  /// Generate the `RichDeserialize` impl.
+  ///
+  /// Mirroring `#[derive(Deserialize)]`, the visitor builds the twin from
+  /// the fields read with the matching `Deserializer` method. The struct
+  /// itself then gets its metadata from the scope.
+  fn rich_deserialize_impl(&self) -> TokenStream {
+    let rich_serde = &self.rich_serde;
+    let serde = quote! { #rich_serde::serde };
+    let ident = &self.container.ident;
+    let name = ident.unraw().to_string();
+    let (_, ty_generics, _) = self.container.generics.split_for_impl();
+    let de_generics = self.de_generics();
+    let (impl_generics, _, where_clause) = de_generics.split_for_impl();
+    let visitor_generics = self.visitor_generics();
+    let (visitor_impl_generics, visitor_ty_generics, visitor_where_clause) = visitor_generics.split_for_impl();
+    let values: Vec<Ident> = (0..self.fields.len()).map(|i| format_ident!("__field{}", i)).collect();
+    let len = self.fields.len();
+
+    let (scope_field, scope_init) = if self.uses_scope() {
+      (
+        quote! { scope: &'__scope mut #rich_serde::RichScope, },
+        quote! { scope: &mut *__scope, },
+      )
+    } else {
+      (quote! {}, quote! {})
+    };
+
+    let (expecting, field_identifier, visit_fns, deserialize) = match self.style {
+      Style::Struct => {
+        let expecting = format!("struct {name}");
+        let expecting_seq = format!("struct {name} with {len} element{}", if len == 1 { "" } else { "s" });
+        let field_identifier = self.field_identifier(&values);
+        let visit_seq = self.visit_seq(&values, &expecting_seq);
+        let visit_map = self.visit_map(&values);
+        let names = self.fields.iter().map(Self::field_name);
+        (
+          expecting,
+          quote! {
+            #field_identifier
+
+            #[doc(hidden)]
+            const FIELDS: &'static [&'static str] = &[#(#names),*];
+          },
+          quote! {
+            #visit_seq
+
+            #visit_map
+          },
+          quote! { #serde::Deserializer::deserialize_struct(__deserializer, #name, FIELDS, __visitor)? },
+        )
+      }
+      Style::Tuple => {
+        let expecting = format!("tuple struct {name}");
+        let expecting_seq = format!("tuple struct {name} with {len} elements");
+        let visit_seq = self.visit_seq(&values, &expecting_seq);
+        (
+          expecting,
+          quote! {},
+          visit_seq,
+          quote! { #serde::Deserializer::deserialize_tuple_struct(__deserializer, #name, #len, __visitor)? },
+        )
+      }
+      Style::Newtype => {
+        let expecting = format!("tuple struct {name}");
+        let expecting_seq = format!("tuple struct {name} with 1 element");
+        let visit_newtype_struct = self.visit_newtype_struct(&values);
+        let visit_seq = self.visit_seq(&values, &expecting_seq);
+        (
+          expecting,
+          quote! {},
+          quote! {
+            #visit_newtype_struct
+
+            #visit_seq
+          },
+          quote! { #serde::Deserializer::deserialize_newtype_struct(__deserializer, #name, __visitor)? },
+        )
+      }
+      Style::Unit => {
+        let twin_value = self.twin_value(&values);
+        (
+          format!("unit struct {name}"),
+          quote! {},
+          quote! {
+            #[inline]
+            fn visit_unit<__E>(self) -> ::core::result::Result<Self::Value, __E>
+            where
+              __E: #serde::de::Error,
+            {
+              ::core::result::Result::Ok(#twin_value)
+            }
+          },
+          quote! { #serde::Deserializer::deserialize_unit_struct(__deserializer, #name, __visitor)? },
+        )
+      }
+    };
+
+    quote! {
+      #[automatically_derived]
+      impl #impl_generics #rich_serde::RichDeserialize<'de> for #ident #ty_generics #where_clause {
+        fn rich_deserialize<__D>(
+          __scope: &mut #rich_serde::RichScope,
+          __deserializer: __D,
+        ) -> ::core::result::Result<#rich_serde::rich::Rich<Self::Rich, #rich_serde::rich::ScopedMetaId>, __D::Error>
+        where
+          __D: #serde::Deserializer<'de>,
+        {
+          #field_identifier
+
+          #[doc(hidden)]
+          struct __Visitor #visitor_impl_generics #visitor_where_clause {
+            #scope_field
+            marker: ::core::marker::PhantomData<#ident #ty_generics>,
+            lifetime: ::core::marker::PhantomData<&'de ()>,
+          }
+
+          #[automatically_derived]
+          impl #visitor_impl_generics #serde::de::Visitor<'de> for __Visitor #visitor_ty_generics #visitor_where_clause {
+            type Value = <#ident #ty_generics as #rich_serde::rich::MergeMeta<#rich_serde::rich::ScopedMetaId>>::Rich;
+
+            fn expecting(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+              ::core::fmt::Formatter::write_str(__formatter, #expecting)
+            }
+
+            #visit_fns
+          }
+
+          let __visitor = __Visitor {
+            #scope_init
+            marker: ::core::marker::PhantomData,
+            lifetime: ::core::marker::PhantomData,
+          };
+          let __value = #deserialize;
+          __scope
+            .attach(__value)
+            .map_err(<__D::Error as #serde::de::Error>::custom)
+        }
+      }
+    }
+  }
+}
+
+/// How the value of a field is read from the input.
+enum FieldRead {
+  /// Read with a seed, getting metadata from the scope.
+  Seed(TokenStream),
+  /// Read as a plain value of the given type.
+  Plain(TokenStream),
 }
 
 #[cfg(test)]
 mod test {
   use super::*;
-  use syn::parse2;
   use syn::DeriveInput;
+  use syn::parse2;
 
   #[test]
   fn unit_struct() {
     let mut input: DeriveInput = parse2(quote! {
-      #[meta(name = MetaUnit, attr(derive(Default, Debug)))]
+      #[meta(rich_twin = RichUnit)]
       struct MyUnit;
     })
     .expect("parsing succeeds");
@@ -117,18 +565,364 @@ mod test {
       #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
       const _: () = {
         #[allow(unused_extern_crates, clippy::useless_attribute)]
-        extern crate rich as _rich;
+        extern crate rich_serde as _rich_serde;
+
+        #[automatically_derived]
+        impl<'de> _rich_serde::RichDeserialize<'de> for MyUnit {
+          fn rich_deserialize<__D>(
+            __scope: &mut _rich_serde::RichScope,
+            __deserializer: __D,
+          ) -> ::core::result::Result<_rich_serde::rich::Rich<Self::Rich, _rich_serde::rich::ScopedMetaId>, __D::Error>
+          where
+            __D: _rich_serde::serde::Deserializer<'de>,
+          {
+            #[doc(hidden)]
+            struct __Visitor<'de> {
+              marker: ::core::marker::PhantomData<MyUnit>,
+              lifetime: ::core::marker::PhantomData<&'de ()>,
+            }
 
-        #[derive(Default, Debug)]
-        pub struct MetaUnit;
+            #[automatically_derived]
+            impl<'de> _rich_serde::serde::de::Visitor<'de> for __Visitor<'de> {
+              type Value = <MyUnit as _rich_serde::rich::MergeMeta<_rich_serde::rich::ScopedMetaId>>::Rich;
+
+              fn expecting(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::fmt::Formatter::write_str(__formatter, "unit struct MyUnit")
+              }
+
+              #[inline]
+              fn visit_unit<__E>(self) -> ::core::result::Result<Self::Value, __E>
+              where
+                __E: _rich_serde::serde::de::Error,
+              {
+                ::core::result::Result::Ok(RichUnit)
+              }
+            }
+
+            let __visitor = __Visitor {
+              marker: ::core::marker::PhantomData,
+              lifetime: ::core::marker::PhantomData,
+            };
+            let __value = _rich_serde::serde::Deserializer::deserialize_unit_struct(__deserializer, "MyUnit", __visitor)?;
+            __scope
+              .attach(__value)
+              .map_err(<__D::Error as _rich_serde::serde::de::Error>::custom)
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn named_struct() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(rich_twin = RichNested)]
+      struct Nested {
+        crab: bool,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_rich_deserialize(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich_serde as _rich_serde;
+
+        #[automatically_derived]
+        impl<'de> _rich_serde::RichDeserialize<'de> for Nested {
+          fn rich_deserialize<__D>(
+            __scope: &mut _rich_serde::RichScope,
+            __deserializer: __D,
+          ) -> ::core::result::Result<_rich_serde::rich::Rich<Self::Rich, _rich_serde::rich::ScopedMetaId>, __D::Error>
+          where
+            __D: _rich_serde::serde::Deserializer<'de>,
+          {
+            #[allow(non_camel_case_types)]
+            #[doc(hidden)]
+            enum __Field {
+              __field0,
+              __ignore,
+            }
+
+            #[doc(hidden)]
+            struct __FieldVisitor;
+
+            #[automatically_derived]
+            impl<'de> _rich_serde::serde::de::Visitor<'de> for __FieldVisitor {
+              type Value = __Field;
+
+              fn expecting(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::fmt::Formatter::write_str(__formatter, "field identifier")
+              }
+
+              fn visit_u64<__E>(self, __value: u64) -> ::core::result::Result<Self::Value, __E>
+              where
+                __E: _rich_serde::serde::de::Error,
+              {
+                match __value {
+                  0u64 => ::core::result::Result::Ok(__Field::__field0),
+                  _ => ::core::result::Result::Ok(__Field::__ignore),
+                }
+              }
+
+              fn visit_str<__E>(self, __value: &str) -> ::core::result::Result<Self::Value, __E>
+              where
+                __E: _rich_serde::serde::de::Error,
+              {
+                match __value {
+                  "crab" => ::core::result::Result::Ok(__Field::__field0),
+                  _ => ::core::result::Result::Ok(__Field::__ignore),
+                }
+              }
+
+              fn visit_bytes<__E>(self, __value: &[u8]) -> ::core::result::Result<Self::Value, __E>
+              where
+                __E: _rich_serde::serde::de::Error,
+              {
+                match __value {
+                  b"crab" => ::core::result::Result::Ok(__Field::__field0),
+                  _ => ::core::result::Result::Ok(__Field::__ignore),
+                }
+              }
+            }
+
+            #[automatically_derived]
+            impl<'de> _rich_serde::serde::Deserialize<'de> for __Field {
+              #[inline]
+              fn deserialize<__D>(__deserializer: __D) -> ::core::result::Result<Self, __D::Error>
+              where
+                __D: _rich_serde::serde::Deserializer<'de>,
+              {
+                _rich_serde::serde::Deserializer::deserialize_identifier(__deserializer, __FieldVisitor)
+              }
+            }
+
+            #[doc(hidden)]
+            const FIELDS: &'static [&'static str] = &["crab"];
+
+            #[doc(hidden)]
+            struct __Visitor<'__scope, 'de> {
+              scope: &'__scope mut _rich_serde::RichScope,
+              marker: ::core::marker::PhantomData<Nested>,
+              lifetime: ::core::marker::PhantomData<&'de ()>,
+            }
+
+            #[automatically_derived]
+            impl<'__scope, 'de> _rich_serde::serde::de::Visitor<'de> for __Visitor<'__scope, 'de> {
+              type Value = <Nested as _rich_serde::rich::MergeMeta<_rich_serde::rich::ScopedMetaId>>::Rich;
+
+              fn expecting(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::fmt::Formatter::write_str(__formatter, "struct Nested")
+              }
+
+              #[inline]
+              fn visit_seq<__A>(self, mut __seq: __A) -> ::core::result::Result<Self::Value, __A::Error>
+              where
+                __A: _rich_serde::serde::de::SeqAccess<'de>,
+              {
+                let __field0 = match _rich_serde::serde::de::SeqAccess::next_element_seed(
+                  &mut __seq,
+                  _rich_serde::RichScopeSerdeSeed::<bool>::new(&mut *self.scope)
+                )? {
+                  ::core::option::Option::Some(__value) => __value,
+                  ::core::option::Option::None => {
+                    return ::core::result::Result::Err(_rich_serde::serde::de::Error::invalid_length(
+                      0usize,
+                      &"struct Nested with 1 element"
+                    ));
+                  }
+                };
+                ::core::result::Result::Ok(RichNested { crab: __field0 })
+              }
+
+              #[inline]
+              fn visit_map<__A>(self, mut __map: __A) -> ::core::result::Result<Self::Value, __A::Error>
+              where
+                __A: _rich_serde::serde::de::MapAccess<'de>,
+              {
+                let mut __field0 = ::core::option::Option::None;
+                while let ::core::option::Option::Some(__key) = _rich_serde::serde::de::MapAccess::next_key::<__Field>(&mut __map)? {
+                  match __key {
+                    __Field::__field0 => {
+                      if ::core::option::Option::is_some(&__field0) {
+                        return ::core::result::Result::Err(<__A::Error as _rich_serde::serde::de::Error>::duplicate_field("crab"));
+                      }
+                      let __value = _rich_serde::serde::de::MapAccess::next_value_seed(
+                        &mut __map,
+                        _rich_serde::RichScopeSerdeSeed::<bool>::new(&mut *self.scope)
+                      )?;
+                      __field0 = ::core::option::Option::Some(__value);
+                    }
+                    _ => {
+                      let _ = _rich_serde::serde::de::MapAccess::next_value::<_rich_serde::serde::de::IgnoredAny>(&mut __map)?;
+                    }
+                  }
+                }
+                let __field0 = match __field0 {
+                  ::core::option::Option::Some(__field0) => __field0,
+                  ::core::option::Option::None => {
+                    let __value = _rich_serde::serde::de::DeserializeSeed::deserialize(
+                      _rich_serde::RichScopeSerdeSeed::<bool>::new(&mut *self.scope),
+                      _rich_serde::MissingFieldDeserializer::<__A::Error>::new("crab")
+                    )?;
+                    __value
+                  }
+                };
+                ::core::result::Result::Ok(RichNested { crab: __field0 })
+              }
+            }
+
+            let __visitor = __Visitor {
+              scope: &mut *__scope,
+              marker: ::core::marker::PhantomData,
+              lifetime: ::core::marker::PhantomData,
+            };
+            let __value = _rich_serde::serde::Deserializer::deserialize_struct(__deserializer, "Nested", FIELDS, __visitor)?;
+            __scope
+              .attach(__value)
+              .map_err(<__D::Error as _rich_serde::serde::de::Error>::custom)
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn generic_newtype_struct() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(rich_twin = RichMeters)]
+      struct Meters<T>(#[meta(opaque)] T);
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_rich_deserialize(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate rich_serde as _rich_serde;
 
         #[automatically_derived]
-        impl _rich::MetaType for MyUnit {
-          type Meta = MetaUnit;
+        impl<'de, T> _rich_serde::RichDeserialize<'de> for Meters<T>
+        where
+          T: _rich_serde::serde::Deserialize<'de>
+        {
+          fn rich_deserialize<__D>(
+            __scope: &mut _rich_serde::RichScope,
+            __deserializer: __D,
+          ) -> ::core::result::Result<_rich_serde::rich::Rich<Self::Rich, _rich_serde::rich::ScopedMetaId>, __D::Error>
+          where
+            __D: _rich_serde::serde::Deserializer<'de>,
+          {
+            #[doc(hidden)]
+            struct __Visitor<'__scope, 'de, T>
+            where
+              T: _rich_serde::serde::Deserialize<'de>
+            {
+              scope: &'__scope mut _rich_serde::RichScope,
+              marker: ::core::marker::PhantomData<Meters<T> >,
+              lifetime: ::core::marker::PhantomData<&'de ()>,
+            }
+
+            #[automatically_derived]
+            impl<'__scope, 'de, T> _rich_serde::serde::de::Visitor<'de> for __Visitor<'__scope, 'de, T>
+            where
+              T: _rich_serde::serde::Deserialize<'de>
+            {
+              type Value = <Meters<T> as _rich_serde::rich::MergeMeta<_rich_serde::rich::ScopedMetaId>>::Rich;
+
+              fn expecting(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::fmt::Formatter::write_str(__formatter, "tuple struct Meters")
+              }
+
+              #[inline]
+              fn visit_newtype_struct<__E>(self, __e: __E) -> ::core::result::Result<Self::Value, __E::Error>
+              where
+                __E: _rich_serde::serde::Deserializer<'de>,
+              {
+                let __value = <T as _rich_serde::serde::Deserialize>::deserialize(__e)?;
+                let __field0 = self.scope.attach(__value).map_err(<__E::Error as _rich_serde::serde::de::Error>::custom)?;
+                ::core::result::Result::Ok(RichMeters(__field0))
+              }
+
+              #[inline]
+              fn visit_seq<__A>(self, mut __seq: __A) -> ::core::result::Result<Self::Value, __A::Error>
+              where
+                __A: _rich_serde::serde::de::SeqAccess<'de>,
+              {
+                let __field0 = match _rich_serde::serde::de::SeqAccess::next_element::<T>(&mut __seq)? {
+                  ::core::option::Option::Some(__value) => self
+                    .scope
+                    .attach(__value)
+                    .map_err(<__A::Error as _rich_serde::serde::de::Error>::custom)?,
+                  ::core::option::Option::None => {
+                    return ::core::result::Result::Err(_rich_serde::serde::de::Error::invalid_length(
+                      0usize,
+                      &"tuple struct Meters with 1 element"
+                    ));
+                  }
+                };
+                ::core::result::Result::Ok(RichMeters(__field0))
+              }
+            }
+
+            let __visitor = __Visitor {
+              scope: &mut *__scope,
+              marker: ::core::marker::PhantomData,
+              lifetime: ::core::marker::PhantomData,
+            };
+            let __value = _rich_serde::serde::Deserializer::deserialize_newtype_struct(__deserializer, "Meters", __visitor)?;
+            __scope
+              .attach(__value)
+              .map_err(<__D::Error as _rich_serde::serde::de::Error>::custom)
+          }
         }
       };
     };
 
     assert_eq!(actual.to_string(), expected.to_string());
   }
+
+  #[test]
+  fn unsupported_input() {
+    let mut input: DeriveInput = parse2(quote! {
+      struct Point(#[meta(with = Meters)] i32, i32);
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_rich_deserialize(&mut input).expect_err("derive fails");
+
+    let messages: Vec<String> = actual.into_iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+      messages,
+      [
+        "`RichDeserialize` does not support `#[meta(with = ...)]` fields",
+        "`RichDeserialize` requires an internal-metadata twin, use `#[meta(rich_twin = ...)]`",
+      ]
+    );
+
+    let mut input: DeriveInput = parse2(quote! {
+      #[meta(rich_twin = RichOperation)]
+      enum Operation {
+        Add,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_rich_deserialize(&mut input).expect_err("derive fails");
+
+    assert_eq!(actual.to_string(), "`RichDeserialize` does not support enums");
+  }
 }
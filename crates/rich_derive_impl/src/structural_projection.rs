@@ -0,0 +1,1164 @@
+use crate::dummy;
+use crate::internals::Derive;
+use crate::internals::ast::{Container, Data, Field, Style, Variant};
+use crate::internals::attr::ForwardMeta;
+use crate::internals::context::Context;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{Path, parse_quote};
+
+pub fn expand_derive_structural_projection(input: &mut syn::DeriveInput) -> syn::Result<TokenStream> {
+  let mut cx = Context::new();
+  let container: Container<'_> = match Container::from_ast(&mut cx, input, Derive::StructuralProjection) {
+    Some(cont) => cont,
+    None => {
+      cx.check()?;
+      return Err(syn::Error::new(input.span(), "failed to build `Container` ast"));
+    }
+  };
+
+  let derived = Derived::new(&container);
+  derived.check_params(&mut cx);
+
+  let structure_type = derived.structure_type();
+  let projection_impl = derived.projection_impl();
+  let map_impls = [MapMode::Value, MapMode::Ref, MapMode::Mut]
+    .into_iter()
+    .map(|mode| derived.map_impl(mode));
+  let zip_impl = derived.zip_impl();
+  let reflect_impl = derived.reflect_impl();
+  let soa_impl = derived.soa_impl();
+
+  let impl_block = quote! {
+    #projection_impl
+
+    #(#map_impls)*
+
+    #zip_impl
+
+    #reflect_impl
+
+    #soa_impl
+  };
+
+  cx.check()?;
+
+  let impl_block = dummy::wrap_in_const("styp", None, &derived.styp, impl_block);
+
+  Ok(quote! {
+    #structure_type
+
+    #impl_block
+  })
+}
+
+/// Shared state for the code generated by `#[derive(StructuralProjection)]`.
+struct Derived<'a> {
+  /// Local path to the `styp` crate.
+  styp: Path,
+  container: &'a Container<'a>,
+  /// Name of the generic structure type.
+  structure_ident: Ident,
+  /// Fields of the container, for enums this is the concatenation of the
+  /// fields of all variants.
+  components: Vec<Component<'a>>,
+}
+
+/// A field of the derived type, along with the type parameter holding its
+/// projection in the structure type.
+struct Component<'a> {
+  field: &'a Field<'a>,
+  param: Ident,
+  /// Name of the local variable bound to the field value in generated code.
+  binding: Ident,
+  /// Name of the local variable bound to the projected field value in
+  /// generated code.
+  projection_binding: Ident,
+}
+
+/// Path to a struct or enum variant, along with the path to the matching
+/// struct or variant of the structure type.
+struct Shape<'d, 'a> {
+  path: TokenStream,
+  structure_path: TokenStream,
+  components: &'d [Component<'a>],
+}
+
+/// Access mode for the components in value-level mapping.
+#[derive(Debug, Clone, Copy)]
+enum MapMode {
+  /// `StructuralMap`, consuming the value.
+  Value,
+  /// `StructuralMapRef`, borrowing the value.
+  Ref,
+  /// `StructuralMapMut`, exclusively borrowing the value.
+  Mut,
+}
+
+impl<'a> Derived<'a> {
+  fn new(container: &'a Container<'a>) -> Self {
+    let ident = &container.ident;
+    let structure_ident: Ident = match container.attributes.styp.name.as_ref() {
+      Some(name) => name.clone(),
+      None => Ident::new(&format!("{ident}Structure"), ident.span()),
+    };
+    let components: Vec<Component<'a>> = match &container.data {
+      Data::Struct(_, fields) => components("", fields),
+      Data::Enum(variants) => variants
+        .iter()
+        .flat_map(|variant| components(&variant.ident.unraw().to_string(), &variant.fields))
+        .collect(),
+    };
+    Self {
+      styp: parse_quote!(_styp),
+      container,
+      structure_ident,
+      components,
+    }
+  }
+
+  /// Report fields whose type parameter in the structure type collides with
+  /// the parameter of another field, and container generics colliding with
+  /// the parameters injected in the derived impls.
+  ///
+  /// Parameter names are derived from the variant and field names, so
+  /// `a_b` and `aB` both map to `TyAB`.
+  fn check_params(&self, cx: &mut Context) {
+    for param in self.container.generics.type_params() {
+      if param.ident == "TyProjector" || param.ident == "TyMapper" {
+        cx.error_spanned_by(
+          param,
+          format!("the type parameter name `{}` is reserved by the derive", param.ident),
+        );
+      }
+    }
+    for param in self.container.generics.lifetimes() {
+      if param.lifetime.ident == "__styp" {
+        cx.error_spanned_by(param, "the lifetime name `'__styp` is reserved by the derive");
+      }
+    }
+    let mut seen: Vec<String> = Vec::new();
+    for component in &self.components {
+      let param = component.param.to_string();
+      if seen.contains(&param) {
+        cx.error_spanned_by(
+          component.field.original,
+          format!("the structure type parameter `{param}` for this field is already used"),
+        );
+      } else {
+        seen.push(param);
+      }
+    }
+  }
+
+  fn field_tys(&self) -> Vec<&'a syn::Type> {
+    self.components.iter().map(|component| component.field.ty).collect()
+  }
+
+  /// Whether the container is an enum without variants.
+  fn is_empty_enum(&self) -> bool {
+    matches!(&self.container.data, Data::Enum(variants) if variants.is_empty())
+  }
+
+  /// Pair each variant of an enum with its components.
+  fn variants(&self) -> Vec<(&'a Variant<'a>, &[Component<'a>])> {
+    let Data::Enum(variants) = &self.container.data else {
+      return Vec::new();
+    };
+    let mut remaining: &[Component<'a>] = &self.components;
+    variants
+      .iter()
+      .map(|variant| {
+        let (variant_components, rest) = remaining.split_at(variant.fields.len());
+        remaining = rest;
+        (variant, variant_components)
+      })
+      .collect()
+  }
+
+  /// Build the generics of a derived impl: the generics of the container,
+  /// optionally preceded by a lifetime and followed by extra type parameters.
+  fn impl_generics(
+    &self,
+    lifetime: Option<&syn::Lifetime>,
+    params: &[&str],
+    predicates: Vec<syn::WherePredicate>,
+  ) -> syn::Generics {
+    let mut generics = self.container.generics.clone();
+    if let Some(lifetime) = lifetime {
+      generics.params.insert(0, parse_quote!(#lifetime));
+    }
+    for param in params {
+      let param = Ident::new(param, Span::call_site());
+      generics.params.push(parse_quote!(#param));
+    }
+    if !predicates.is_empty() {
+      generics.make_where_clause().predicates.extend(predicates);
+    }
+    generics
+  }
+
+  fn projector_bound(&self) -> Vec<syn::WherePredicate> {
+    let styp = &self.styp;
+    let field_tys = self.field_tys();
+    if field_tys.is_empty() {
+      return Vec::new();
+    }
+    vec![parse_quote!(TyProjector: #(#styp::Projector<#field_tys>)+*)]
+  }
+
+  fn structure_params(&self) -> TokenStream {
+    if self.components.is_empty() {
+      return quote! {};
+    }
+    let params = self.components.iter().map(|component| &component.param);
+    quote! { <#(#params),*> }
+  }
+
+  fn structure_type(&self) -> TokenStream {
+    let meta = ForwardMeta(&self.container.attributes.styp.attr);
+    let vis = &self.container.original.vis;
+    let structure_ident = &self.structure_ident;
+    let params = self.structure_params();
+    match &self.container.data {
+      Data::Struct(style, _) => {
+        let field_vis = self.components.iter().map(|component| &component.field.original.vis);
+        let field_params = self.components.iter().map(|component| &component.param);
+        match style {
+          Style::Struct => {
+            let field_members = self.components.iter().map(|component| &component.field.member);
+            quote! {
+              #meta
+              #vis struct #structure_ident #params {
+                #(#field_vis #field_members: #field_params,)*
+              }
+            }
+          }
+          Style::Tuple | Style::Newtype => quote! {
+            #meta
+            #vis struct #structure_ident #params(#(#field_vis #field_params),*);
+          },
+          Style::Unit => quote! {
+            #meta
+            #vis struct #structure_ident;
+          },
+        }
+      }
+      Data::Enum(_) => {
+        let variants = self.variants().into_iter().map(|(variant, components)| {
+          let variant_ident = &variant.ident;
+          let field_params = components.iter().map(|component| &component.param);
+          match variant.style {
+            Style::Struct => {
+              let field_members = components.iter().map(|component| &component.field.member);
+              quote! { #variant_ident { #(#field_members: #field_params),* } }
+            }
+            Style::Tuple | Style::Newtype => quote! { #variant_ident(#(#field_params),*) },
+            Style::Unit => quote! { #variant_ident },
+          }
+        });
+        quote! {
+          #meta
+          #vis enum #structure_ident #params {
+            #(#variants,)*
+          }
+        }
+      }
+    }
+  }
+
+  fn projection_impl(&self) -> TokenStream {
+    let styp = &self.styp;
+    let ident = &self.container.ident;
+    let structure_ident = &self.structure_ident;
+    let field_tys = self.field_tys();
+    let generics = self.impl_generics(None, &["TyProjector"], self.projector_bound());
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = self.container.generics.split_for_impl();
+
+    let projection_args = if field_tys.is_empty() {
+      quote! {}
+    } else {
+      quote! { <#(<TyProjector as #styp::Projector<#field_tys>>::Output),*> }
+    };
+
+    quote! {
+      #[automatically_derived]
+      impl #impl_generics #styp::StructuralProjection<TyProjector> for #ident #ty_generics #where_clause {
+        type Projection = #structure_ident #projection_args;
+      }
+    }
+  }
+
+  /// List the shapes a value may take: the struct itself, or each variant of
+  /// the enum.
+  fn shapes(&self) -> Vec<Shape<'_, 'a>> {
+    let ident = &self.container.ident;
+    let structure_ident = &self.structure_ident;
+    match &self.container.data {
+      Data::Struct(..) => vec![Shape {
+        path: quote! { #ident },
+        structure_path: quote! { #structure_ident },
+        components: &self.components,
+      }],
+      Data::Enum(_) => self
+        .variants()
+        .into_iter()
+        .map(|(variant, components)| {
+          let variant_ident = &variant.ident;
+          Shape {
+            path: quote! { #ident::#variant_ident },
+            structure_path: quote! { #structure_ident::#variant_ident },
+            components,
+          }
+        })
+        .collect(),
+    }
+  }
+
+  /// Build a `match` expression over `scrutinee`, with one arm per struct or
+  /// variant. Each arm binds the fields and evaluates `body`, which receives
+  /// the path to the matching struct or variant of the structure type.
+  fn match_components<F>(&self, scrutinee: TokenStream, body: F) -> TokenStream
+  where
+    F: Fn(TokenStream, &[Component<'a>]) -> TokenStream,
+  {
+    let arms = self.shapes().into_iter().map(|shape| {
+      let path = &shape.path;
+      let members = shape.components.iter().map(|component| &component.field.member);
+      let bindings = shape.components.iter().map(|component| &component.binding);
+      let body = body(shape.structure_path, shape.components);
+      quote! { #path { #(#members: #bindings),* } => #body }
+    });
+    quote! {
+      match #scrutinee {
+        #(#arms,)*
+      }
+    }
+  }
+
+  fn map_impl(&self, mode: MapMode) -> TokenStream {
+    let styp = &self.styp;
+    let ident = &self.container.ident;
+    let lifetime: syn::Lifetime = parse_quote!('__styp);
+    let (map_trait, map_fn, mapper_trait, mapper_fn, receiver) = match mode {
+      MapMode::Value => (
+        quote! { #styp::StructuralMap<TyProjector, TyMapper> },
+        quote! { structural_map },
+        quote! { #styp::Mapper<TyProjector, },
+        quote! { map },
+        quote! { self },
+      ),
+      MapMode::Ref => (
+        quote! { #styp::StructuralMapRef<#lifetime, TyProjector, TyMapper> },
+        quote! { structural_map_ref },
+        quote! { #styp::MapperRef<#lifetime, TyProjector, },
+        quote! { map_ref },
+        quote! { &#lifetime self },
+      ),
+      MapMode::Mut => (
+        quote! { #styp::StructuralMapMut<#lifetime, TyProjector, TyMapper> },
+        quote! { structural_map_mut },
+        quote! { #styp::MapperMut<#lifetime, TyProjector, },
+        quote! { map_mut },
+        quote! { &#lifetime mut self },
+      ),
+    };
+
+    let field_tys = self.field_tys();
+    let mut predicates = self.projector_bound();
+    if !field_tys.is_empty() {
+      predicates.push(parse_quote!(TyMapper: #(#mapper_trait #field_tys>)+*));
+    }
+    let generics = self.impl_generics(
+      match mode {
+        MapMode::Value => None,
+        MapMode::Ref | MapMode::Mut => Some(&lifetime),
+      },
+      &["TyProjector", "TyMapper"],
+      predicates,
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = self.container.generics.split_for_impl();
+
+    // Matching a reference to an empty enum with no arms requires an explicit deref.
+    let scrutinee = match (mode, self.is_empty_enum()) {
+      (MapMode::Ref | MapMode::Mut, true) => quote! { *self },
+      _ => quote! { self },
+    };
+    let body = self.match_components(scrutinee, |structure_path, components| {
+      let members = components.iter().map(|component| &component.field.member);
+      let values = components.iter().map(|component| {
+        let ty = component.field.ty;
+        let binding = &component.binding;
+        quote! { <TyMapper as #mapper_trait #ty>>::#mapper_fn(mapper, #binding) }
+      });
+      quote! { #structure_path { #(#members: #values),* } }
+    });
+
+    quote! {
+      #[automatically_derived]
+      impl #impl_generics #map_trait for #ident #ty_generics #where_clause {
+        fn #map_fn(#receiver, mapper: &mut TyMapper) -> Self::Projection {
+          #body
+        }
+      }
+    }
+  }
+
+  fn zip_impl(&self) -> TokenStream {
+    let styp = &self.styp;
+    let ident = &self.container.ident;
+    let generics = self.impl_generics(None, &["TyProjector"], self.projector_bound());
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = self.container.generics.split_for_impl();
+
+    let shapes = self.shapes();
+    let mut zip_arms: Vec<TokenStream> = Vec::new();
+    let mut unzip_arms: Vec<TokenStream> = Vec::new();
+    for shape in &shapes {
+      let path = &shape.path;
+      let structure_path = &shape.structure_path;
+      let members: Vec<&syn::Member> = shape
+        .components
+        .iter()
+        .map(|component| &component.field.member)
+        .collect();
+      let bindings: Vec<&Ident> = shape.components.iter().map(|component| &component.binding).collect();
+      let projection_bindings: Vec<&Ident> = shape
+        .components
+        .iter()
+        .map(|component| &component.projection_binding)
+        .collect();
+      zip_arms.push(quote! {
+        (#path { #(#members: #bindings),* }, #structure_path { #(#members: #projection_bindings),* }) =>
+          ::core::result::Result::Ok(#structure_path { #(#members: (#bindings, #projection_bindings)),* })
+      });
+      unzip_arms.push(quote! {
+        #structure_path { #(#members: (#bindings, #projection_bindings)),* } =>
+          (#path { #(#members: #bindings),* }, #structure_path { #(#members: #projection_bindings),* })
+      });
+    }
+    // Values and projections may only disagree on the variant if there are
+    // at least two of them.
+    if shapes.len() > 1 {
+      zip_arms.push(quote! {
+        _ => ::core::result::Result::Err(#styp::ZipError::VariantMismatch)
+      });
+    }
+    // An empty enum can't be paired with anything: match on the value alone.
+    let zip_scrutinee = if shapes.is_empty() {
+      quote! { self }
+    } else {
+      quote! { (self, projection) }
+    };
+
+    quote! {
+      #[automatically_derived]
+      impl #impl_generics #styp::StructuralZip<TyProjector> for #ident #ty_generics #where_clause {
+        fn structural_zip(
+          self,
+          projection: #styp::Project<Self, TyProjector>,
+        ) -> ::core::result::Result<#styp::Project<Self, #styp::Zipped<TyProjector>>, #styp::ZipError> {
+          match #zip_scrutinee {
+            #(#zip_arms,)*
+          }
+        }
+
+        fn structural_unzip(
+          zipped: #styp::Project<Self, #styp::Zipped<TyProjector>>,
+        ) -> (Self, #styp::Project<Self, TyProjector>) {
+          match zipped {
+            #(#unzip_arms,)*
+          }
+        }
+      }
+    }
+  }
+
+  /// Runtime description of the components. Not available for types with
+  /// lifetime parameters, as `Reflect` requires `'static` types.
+  ///
+  /// `Reflect` is only defined when `styp` has the `alloc` feature, so the
+  /// impl is wrapped in `__with_alloc!` to be checked against the features of
+  /// `styp` rather than the ones of the deriving crate.
+  fn reflect_impl(&self) -> Option<TokenStream> {
+    if self.container.generics.lifetimes().next().is_some() {
+      return None;
+    }
+    let styp = &self.styp;
+    let ident = &self.container.ident;
+    let field_tys = self.field_tys();
+    // The bounds are higher-ranked so they are not trivially false for
+    // concrete field types lacking a `Reflect` impl: the derived type then
+    // simply does not implement `Reflect` instead of failing to compile.
+    let predicates: Vec<syn::WherePredicate> = field_tys
+      .iter()
+      .map(|ty| parse_quote!(for<'__styp> #ty: #styp::Reflect))
+      .collect();
+    let generics = self.impl_generics(None, &[], predicates);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let variant_names: Vec<TokenStream> = match &self.container.data {
+      Data::Struct(..) => vec![quote! { ::core::option::Option::None }; self.components.len()],
+      Data::Enum(_) => self
+        .variants()
+        .into_iter()
+        .flat_map(|(variant, components)| {
+          let name = variant.ident.unraw().to_string();
+          components
+            .iter()
+            .map(move |_| quote! { ::core::option::Option::Some(#name) })
+        })
+        .collect(),
+    };
+    let kinds: Vec<TokenStream> = self
+      .components
+      .iter()
+      .map(|component| match &component.field.member {
+        syn::Member::Named(name) => {
+          let name = name.unraw().to_string();
+          quote! { #styp::ComponentKind::Field(#name) }
+        }
+        syn::Member::Unnamed(index) => {
+          let index = index.index as usize;
+          quote! { #styp::ComponentKind::Index(#index) }
+        }
+      })
+      .collect();
+
+    let scrutinee = if self.is_empty_enum() {
+      quote! { *self }
+    } else {
+      quote! { self }
+    };
+    let body = self.match_components(scrutinee, |_, components| {
+      let visits = components.iter().map(|component| {
+        let binding = &component.binding;
+        let segment = match &component.field.member {
+          syn::Member::Named(name) => {
+            let name = name.unraw().to_string();
+            quote! { #styp::PathSegment::Field(#name) }
+          }
+          syn::Member::Unnamed(index) => {
+            let index = index.index as usize;
+            quote! { #styp::PathSegment::Index(#index) }
+          }
+        };
+        quote! { #styp::reflect::visit(#binding, #segment, path, visitor); }
+      });
+      quote! { { #(#visits)* } }
+    });
+
+    Some(quote! {
+      #styp::__with_alloc! {
+        #[automatically_derived]
+        impl #impl_generics #styp::Reflect for #ident #ty_generics #where_clause {
+          fn components() -> &'static [#styp::ComponentDescriptor] {
+            &[#(#styp::ComponentDescriptor { variant: #variant_names, kind: #kinds }),*]
+          }
+
+          fn visit_components<'__styp>(
+            &'__styp self,
+            path: &mut #styp::FieldPath,
+            visitor: &mut #styp::reflect::Visitor<'_, '__styp>,
+          ) {
+            #body
+          }
+        }
+      }
+    })
+  }
+
+  /// Column operations for `SoaVec`, only available for structs with at least
+  /// one field: the fields of an enum depend on the variant, and a struct
+  /// without fields has no column to store. Like `Reflect`, it requires the
+  /// `alloc` feature of `styp`.
+  fn soa_impl(&self) -> Option<TokenStream> {
+    if !matches!(&self.container.data, Data::Struct(..)) || self.components.is_empty() {
+      return None;
+    }
+    let styp = &self.styp;
+    let ident = &self.container.ident;
+    let structure_ident = &self.structure_ident;
+    let generics = self.impl_generics(None, &[], Vec::new());
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let members: Vec<&syn::Member> = self
+      .components
+      .iter()
+      .map(|component| &component.field.member)
+      .collect();
+
+    Some(quote! {
+      #styp::__with_alloc! {
+        #[automatically_derived]
+        impl #impl_generics #styp::SoaLayout for #ident #ty_generics #where_clause {
+          type Columns = #styp::Project<Self, #styp::VecProjector>;
+          type Ref<'__styp> = #styp::Project<Self, #styp::RefProjector<'__styp>> where Self: '__styp;
+          type Mut<'__styp> = #styp::Project<Self, #styp::RefMutProjector<'__styp>> where Self: '__styp;
+          type Slices<'__styp> = #styp::Project<Self, #styp::SliceProjector<'__styp>> where Self: '__styp;
+
+          fn columns_new() -> Self::Columns {
+            #structure_ident { #(#members: ::core::default::Default::default()),* }
+          }
+
+          fn columns_push(columns: &mut Self::Columns, value: Self) {
+            #(columns.#members.push(value.#members);)*
+          }
+
+          fn columns_get(columns: &Self::Columns, index: usize) -> Self::Ref<'_> {
+            #structure_ident { #(#members: &columns.#members[index]),* }
+          }
+
+          fn columns_get_mut(columns: &mut Self::Columns, index: usize) -> Self::Mut<'_> {
+            #structure_ident { #(#members: &mut columns.#members[index]),* }
+          }
+
+          fn columns_slices(columns: &Self::Columns) -> Self::Slices<'_> {
+            #structure_ident { #(#members: columns.#members.as_slice()),* }
+          }
+
+          fn columns_swap_remove(columns: &mut Self::Columns, index: usize) -> Self {
+            #ident { #(#members: columns.#members.swap_remove(index)),* }
+          }
+
+          fn columns_swap(columns: &mut Self::Columns, a: usize, b: usize) {
+            #(columns.#members.swap(a, b);)*
+          }
+        }
+      }
+    })
+  }
+}
+
+fn components<'a>(prefix: &str, fields: &'a [Field<'a>]) -> Vec<Component<'a>> {
+  fields
+    .iter()
+    .enumerate()
+    .map(|(index, field)| {
+      let name = match &field.member {
+        syn::Member::Named(ident) => upper_camel(&ident.unraw().to_string()),
+        syn::Member::Unnamed(index) => index.index.to_string(),
+      };
+      Component {
+        field,
+        param: Ident::new(&format!("Ty{prefix}{name}"), field.original.span()),
+        binding: Ident::new(&format!("__field{index}"), Span::call_site()),
+        projection_binding: Ident::new(&format!("__projection{index}"), Span::call_site()),
+      }
+    })
+    .collect()
+}
+
+fn upper_camel(snake: &str) -> String {
+  snake
+    .split('_')
+    .map(|part| {
+      let mut chars = part.chars();
+      match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use syn::DeriveInput;
+  use syn::parse2;
+
+  /// Parse `input` and pass the resulting derive state to `f`.
+  fn with_derived<F>(input: TokenStream, f: F) -> TokenStream
+  where
+    F: FnOnce(&Derived<'_>) -> TokenStream,
+  {
+    let input: DeriveInput = parse2(input).expect("parsing succeeds");
+    let mut cx = Context::new();
+    let container = Container::from_ast(&mut cx, &input, Derive::StructuralProjection).expect("container is valid");
+    cx.check().expect("container has no errors");
+    f(&Derived::new(&container))
+  }
+
+  #[test]
+  fn unit_struct() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[styp(name = UnitStructure, attr(derive(Debug)))]
+      pub struct Unit;
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_structural_projection(&mut input).expect("derive succeeds");
+
+    // language=rust
+    let expected = quote! {
+      #[derive(Debug)]
+      pub struct UnitStructure;
+
+      #[doc(hidden)]
+      #[allow(non_upper_case_globals, unused_attributes, unused_qualifications, clippy::absolute_paths)]
+      const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate styp as _styp;
+
+        #[automatically_derived]
+        impl<TyProjector> _styp::StructuralProjection<TyProjector> for Unit {
+          type Projection = UnitStructure;
+        }
+
+        #[automatically_derived]
+        impl<TyProjector, TyMapper> _styp::StructuralMap<TyProjector, TyMapper> for Unit {
+          fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+            match self {
+              Unit {} => UnitStructure {},
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<'__styp, TyProjector, TyMapper> _styp::StructuralMapRef<'__styp, TyProjector, TyMapper> for Unit {
+          fn structural_map_ref(&'__styp self, mapper: &mut TyMapper) -> Self::Projection {
+            match self {
+              Unit {} => UnitStructure {},
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<'__styp, TyProjector, TyMapper> _styp::StructuralMapMut<'__styp, TyProjector, TyMapper> for Unit {
+          fn structural_map_mut(&'__styp mut self, mapper: &mut TyMapper) -> Self::Projection {
+            match self {
+              Unit {} => UnitStructure {},
+            }
+          }
+        }
+
+        #[automatically_derived]
+        impl<TyProjector> _styp::StructuralZip<TyProjector> for Unit {
+          fn structural_zip(
+            self,
+            projection: _styp::Project<Self, TyProjector>,
+          ) -> ::core::result::Result<_styp::Project<Self, _styp::Zipped<TyProjector>>, _styp::ZipError> {
+            match (self, projection) {
+              (Unit {}, UnitStructure {}) => ::core::result::Result::Ok(UnitStructure {}),
+            }
+          }
+
+          fn structural_unzip(
+            zipped: _styp::Project<Self, _styp::Zipped<TyProjector>>,
+          ) -> (Self, _styp::Project<Self, TyProjector>) {
+            match zipped {
+              UnitStructure {} => (Unit {}, UnitStructure {}),
+            }
+          }
+        }
+
+        _styp::__with_alloc! {
+          #[automatically_derived]
+          impl _styp::Reflect for Unit {
+            fn components() -> &'static [_styp::ComponentDescriptor] {
+              &[]
+            }
+
+            fn visit_components<'__styp>(
+              &'__styp self,
+              path: &mut _styp::FieldPath,
+              visitor: &mut _styp::reflect::Visitor<'_, '__styp>,
+            ) {
+              match self {
+                Unit {} => {},
+              }
+            }
+          }
+        }
+      };
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn named_struct() {
+    let input = quote! {
+      #[styp(name = WeatherMeasurementStructure, attr(derive(Debug)))]
+      pub struct WeatherMeasurement {
+        pub temperature: Temperature,
+        pub pressure: Pressure,
+      }
+    };
+
+    let actual = with_derived(input, |derived| {
+      let structure_type = derived.structure_type();
+      let projection_impl = derived.projection_impl();
+      quote! { #structure_type #projection_impl }
+    });
+
+    // language=rust
+    let expected = quote! {
+      #[derive(Debug)]
+      pub struct WeatherMeasurementStructure<TyTemperature, TyPressure> {
+        pub temperature: TyTemperature,
+        pub pressure: TyPressure,
+      }
+
+      #[automatically_derived]
+      impl<TyProjector> _styp::StructuralProjection<TyProjector> for WeatherMeasurement
+      where
+        TyProjector: _styp::Projector<Temperature> + _styp::Projector<Pressure>
+      {
+        type Projection = WeatherMeasurementStructure<
+          <TyProjector as _styp::Projector<Temperature>>::Output,
+          <TyProjector as _styp::Projector<Pressure>>::Output
+        >;
+      }
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn generic_enum() {
+    let input = quote! {
+      enum Operation<T> {
+        Read,
+        Update { username: String, rate_limit: T },
+        Delete(bool),
+      }
+    };
+
+    let actual = with_derived(input, |derived| {
+      let structure_type = derived.structure_type();
+      let projection_impl = derived.projection_impl();
+      quote! { #structure_type #projection_impl }
+    });
+
+    // language=rust
+    let expected = quote! {
+      enum OperationStructure<TyUpdateUsername, TyUpdateRateLimit, TyDelete0> {
+        Read,
+        Update { username: TyUpdateUsername, rate_limit: TyUpdateRateLimit },
+        Delete(TyDelete0),
+      }
+
+      #[automatically_derived]
+      impl<T, TyProjector> _styp::StructuralProjection<TyProjector> for Operation<T>
+      where
+        TyProjector: _styp::Projector<String> + _styp::Projector<T> + _styp::Projector<bool>
+      {
+        type Projection = OperationStructure<
+          <TyProjector as _styp::Projector<String>>::Output,
+          <TyProjector as _styp::Projector<T>>::Output,
+          <TyProjector as _styp::Projector<bool>>::Output
+        >;
+      }
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn generic_enum_map_ref() {
+    let input = quote! {
+      enum Operation<T> {
+        Read,
+        Update { username: String, rate_limit: T },
+        Delete(bool),
+      }
+    };
+
+    let actual = with_derived(input, |derived| derived.map_impl(MapMode::Ref));
+
+    // language=rust
+    let expected = quote! {
+      #[automatically_derived]
+      impl<'__styp, T, TyProjector, TyMapper> _styp::StructuralMapRef<'__styp, TyProjector, TyMapper> for Operation<T>
+      where
+        TyProjector: _styp::Projector<String> + _styp::Projector<T> + _styp::Projector<bool>,
+        TyMapper: _styp::MapperRef<'__styp, TyProjector, String>
+          + _styp::MapperRef<'__styp, TyProjector, T>
+          + _styp::MapperRef<'__styp, TyProjector, bool>
+      {
+        fn structural_map_ref(&'__styp self, mapper: &mut TyMapper) -> Self::Projection {
+          match self {
+            Operation::Read {} => OperationStructure::Read {},
+            Operation::Update { username: __field0, rate_limit: __field1 } => OperationStructure::Update {
+              username: <TyMapper as _styp::MapperRef<'__styp, TyProjector, String>>::map_ref(mapper, __field0),
+              rate_limit: <TyMapper as _styp::MapperRef<'__styp, TyProjector, T>>::map_ref(mapper, __field1)
+            },
+            Operation::Delete { 0: __field0 } => OperationStructure::Delete {
+              0: <TyMapper as _styp::MapperRef<'__styp, TyProjector, bool>>::map_ref(mapper, __field0)
+            },
+          }
+        }
+      }
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn named_struct_soa() {
+    let input = quote! {
+      pub struct WeatherMeasurement {
+        pub temperature: Temperature,
+        pub pressure: Pressure,
+      }
+    };
+
+    let actual = with_derived(input, |derived| derived.soa_impl().expect("structs have a SoA layout"));
+
+    // language=rust
+    let expected = quote! {
+      _styp::__with_alloc! {
+        #[automatically_derived]
+        impl _styp::SoaLayout for WeatherMeasurement {
+          type Columns = _styp::Project<Self, _styp::VecProjector>;
+          type Ref<'__styp> = _styp::Project<Self, _styp::RefProjector<'__styp>> where Self: '__styp;
+          type Mut<'__styp> = _styp::Project<Self, _styp::RefMutProjector<'__styp>> where Self: '__styp;
+          type Slices<'__styp> = _styp::Project<Self, _styp::SliceProjector<'__styp>> where Self: '__styp;
+
+          fn columns_new() -> Self::Columns {
+            WeatherMeasurementStructure {
+              temperature: ::core::default::Default::default(),
+              pressure: ::core::default::Default::default()
+            }
+          }
+
+          fn columns_push(columns: &mut Self::Columns, value: Self) {
+            columns.temperature.push(value.temperature);
+            columns.pressure.push(value.pressure);
+          }
+
+          fn columns_get(columns: &Self::Columns, index: usize) -> Self::Ref<'_> {
+            WeatherMeasurementStructure {
+              temperature: &columns.temperature[index],
+              pressure: &columns.pressure[index]
+            }
+          }
+
+          fn columns_get_mut(columns: &mut Self::Columns, index: usize) -> Self::Mut<'_> {
+            WeatherMeasurementStructure {
+              temperature: &mut columns.temperature[index],
+              pressure: &mut columns.pressure[index]
+            }
+          }
+
+          fn columns_slices(columns: &Self::Columns) -> Self::Slices<'_> {
+            WeatherMeasurementStructure {
+              temperature: columns.temperature.as_slice(),
+              pressure: columns.pressure.as_slice()
+            }
+          }
+
+          fn columns_swap_remove(columns: &mut Self::Columns, index: usize) -> Self {
+            WeatherMeasurement {
+              temperature: columns.temperature.swap_remove(index),
+              pressure: columns.pressure.swap_remove(index)
+            }
+          }
+
+          fn columns_swap(columns: &mut Self::Columns, a: usize, b: usize) {
+            columns.temperature.swap(a, b);
+            columns.pressure.swap(a, b);
+          }
+        }
+      }
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn enum_has_no_soa() {
+    let input = quote! {
+      enum Operation {
+        Read,
+        Delete(bool),
+      }
+    };
+
+    let actual = with_derived(input, |derived| derived.soa_impl().unwrap_or_default());
+
+    assert!(actual.is_empty());
+  }
+
+  #[test]
+  fn generic_enum_zip() {
+    let input = quote! {
+      enum Operation<T> {
+        Read,
+        Delete(T),
+      }
+    };
+
+    let actual = with_derived(input, |derived| derived.zip_impl());
+
+    // language=rust
+    let expected = quote! {
+      #[automatically_derived]
+      impl<T, TyProjector> _styp::StructuralZip<TyProjector> for Operation<T>
+      where
+        TyProjector: _styp::Projector<T>
+      {
+        fn structural_zip(
+          self,
+          projection: _styp::Project<Self, TyProjector>,
+        ) -> ::core::result::Result<_styp::Project<Self, _styp::Zipped<TyProjector>>, _styp::ZipError> {
+          match (self, projection) {
+            (Operation::Read {}, OperationStructure::Read {}) => ::core::result::Result::Ok(OperationStructure::Read {}),
+            (Operation::Delete { 0: __field0 }, OperationStructure::Delete { 0: __projection0 }) =>
+              ::core::result::Result::Ok(OperationStructure::Delete { 0: (__field0, __projection0) }),
+            _ => ::core::result::Result::Err(_styp::ZipError::VariantMismatch),
+          }
+        }
+
+        fn structural_unzip(
+          zipped: _styp::Project<Self, _styp::Zipped<TyProjector>>,
+        ) -> (Self, _styp::Project<Self, TyProjector>) {
+          match zipped {
+            OperationStructure::Read {} => (Operation::Read {}, OperationStructure::Read {}),
+            OperationStructure::Delete { 0: (__field0, __projection0) } =>
+              (Operation::Delete { 0: __field0 }, OperationStructure::Delete { 0: __projection0 }),
+          }
+        }
+      }
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn generic_enum_reflect() {
+    let input = quote! {
+      enum Operation<T> {
+        Read,
+        Update { r#type: String, rate_limit: T },
+        Delete(bool),
+      }
+    };
+
+    let actual = with_derived(input, |derived| derived.reflect_impl().expect("type has no lifetime"));
+
+    // language=rust
+    let expected = quote! {
+      _styp::__with_alloc! {
+        #[automatically_derived]
+        impl<T> _styp::Reflect for Operation<T>
+        where
+          for<'__styp> String: _styp::Reflect,
+          for<'__styp> T: _styp::Reflect,
+          for<'__styp> bool: _styp::Reflect
+        {
+          fn components() -> &'static [_styp::ComponentDescriptor] {
+            &[
+              _styp::ComponentDescriptor {
+                variant: ::core::option::Option::Some("Update"),
+                kind: _styp::ComponentKind::Field("type")
+              },
+              _styp::ComponentDescriptor {
+                variant: ::core::option::Option::Some("Update"),
+                kind: _styp::ComponentKind::Field("rate_limit")
+              },
+              _styp::ComponentDescriptor {
+                variant: ::core::option::Option::Some("Delete"),
+                kind: _styp::ComponentKind::Index(0usize)
+              }
+            ]
+          }
+
+          fn visit_components<'__styp>(
+            &'__styp self,
+            path: &mut _styp::FieldPath,
+            visitor: &mut _styp::reflect::Visitor<'_, '__styp>,
+          ) {
+            match self {
+              Operation::Read {} => {},
+              Operation::Update { r#type: __field0, rate_limit: __field1 } => {
+                _styp::reflect::visit(__field0, _styp::PathSegment::Field("type"), path, visitor);
+                _styp::reflect::visit(__field1, _styp::PathSegment::Field("rate_limit"), path, visitor);
+              },
+              Operation::Delete { 0: __field0 } => {
+                _styp::reflect::visit(__field0, _styp::PathSegment::Index(0usize), path, visitor);
+              },
+            }
+          }
+        }
+      }
+    };
+
+    assert_eq!(actual.to_string(), expected.to_string());
+  }
+
+  #[test]
+  fn lifetime_has_no_reflect() {
+    let input = quote! {
+      struct View<'a> {
+        name: &'a str,
+      }
+    };
+
+    let actual = with_derived(input, |derived| derived.reflect_impl().unwrap_or_default());
+
+    assert!(actual.is_empty());
+  }
+
+  #[test]
+  fn param_collision() {
+    let mut input: DeriveInput = parse2(quote! {
+      #[allow(non_snake_case)]
+      struct Clash {
+        a_b: u8,
+        aB: u16,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_structural_projection(&mut input).expect_err("derive fails");
+
+    assert_eq!(
+      actual.to_string(),
+      "the structure type parameter `TyAB` for this field is already used"
+    );
+
+    let mut input: DeriveInput = parse2(quote! {
+      enum Clash<TyAC> {
+        A { b_c: u8, c: TyAC },
+        AB { c: u32 },
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_structural_projection(&mut input).expect_err("derive fails");
+
+    assert_eq!(
+      actual.to_string(),
+      "the structure type parameter `TyABC` for this field is already used"
+    );
+  }
+
+  #[test]
+  fn reserved_generics() {
+    let mut input: DeriveInput = parse2(quote! {
+      struct Backend<'__styp, TyProjector, TyMapper> {
+        inner: &'__styp TyProjector,
+        mapper: TyMapper,
+      }
+    })
+    .expect("parsing succeeds");
+
+    let actual = expand_derive_structural_projection(&mut input).expect_err("derive fails");
+
+    let messages: Vec<String> = actual.into_iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+      messages,
+      [
+        "the type parameter name `TyProjector` is reserved by the derive",
+        "the type parameter name `TyMapper` is reserved by the derive",
+        "the lifetime name `'__styp` is reserved by the derive",
+      ]
+    );
+  }
+}
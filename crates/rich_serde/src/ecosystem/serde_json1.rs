@@ -1,30 +1,30 @@
 use crate::RichDeserialize;
 use crate::RichScope;
 use crate::RichScopeSerdeSeed;
-use rich::{Meta, WrappedMeta};
-use rich::{Rich};
+use rich::ecosystem::serde_json1::RichValue;
+use rich::{Rich, RichEntry, ScopedMetaId};
+use serde::Deserializer;
 use serde::de::Error;
 use serde::de::MapAccess;
 use serde::de::SeqAccess;
 use serde::de::Visitor;
-use serde::Deserializer;
 use std::collections::BTreeMap;
 
 pub mod value {
   use super::*;
 
   impl<'de> RichDeserialize<'de> for serde_json1::Value {
-    fn rich_deserialize<'scope, D>(
-      scope: &'scope mut RichScope,
+    fn rich_deserialize<D>(
+      scope: &mut RichScope,
       deserializer: D,
-    ) -> Result<Rich<Self, WrappedMeta<Self::Meta>>, D::Error>
+    ) -> Result<Rich<RichValue<ScopedMetaId>, ScopedMetaId>, D::Error>
     where
       D: Deserializer<'de>,
     {
       struct RichVisitor<'scope>(&'scope mut RichScope);
 
       impl<'de, 'scope> Visitor<'de> for RichVisitor<'scope> {
-        type Value = Rich<serde_json1::Value, Option<rich::ecosystem::serde_json1::ValueMeta>>;
+        type Value = RichValue<ScopedMetaId>;
 
         fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
           formatter.write_str("struct serde_json::Value")
@@ -34,86 +34,96 @@ pub mod value {
         where
           E: Error,
         {
-          let value0 = v;
-          let meta0 = ();
-          let rich0 = Rich::new(value0, meta0);
-          let wrapped0 = self.0.wrap(rich0);
-
-          let value = serde_json1::Value::Bool(wrapped0.value);
-          let meta = Some(rich::ecosystem::serde_json1::ValueMeta::Bool(wrapped0.meta));
-
-          Ok(Rich::new(value, meta))
+          Ok(RichValue::Bool(v))
         }
 
-        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
         where
-          A: MapAccess<'de>,
+          E: Error,
         {
-          let mut value = serde_json1::value::Map::new();
-          let mut meta = BTreeMap::<String, WrappedMeta<Meta<::serde_json1::value::Value>>>::new();
-
-          while let Some(key) = map.next_key::<String>()? {
-            let rich = map.next_value_seed(RichScopeSerdeSeed::<serde_json1::Value>::new(self.0))?;
-            value.insert(key.clone(), rich.value);
-            meta.insert(key, rich.meta);
-          }
+          Ok(RichValue::Number(v.into()))
+        }
 
-          let value = serde_json1::Value::Object(value);
-          let meta = Some(rich::ecosystem::serde_json1::ValueMeta::Object(meta));
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+        where
+          E: Error,
+        {
+          Ok(RichValue::Number(v.into()))
+        }
 
-          Ok(Rich::new(value, meta))
+        fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+        where
+          E: Error,
+        {
+          // Like `serde_json::Value`, non-finite numbers become `null`
+          Ok(serde_json1::Number::from_f64(v).map_or(RichValue::Null, RichValue::Number))
         }
 
-        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
         where
-          A: SeqAccess<'de>,
+          E: Error,
         {
-          let mut value = Vec::<serde_json1::Value>::new();
-          let mut meta = Vec::<WrappedMeta<Meta<::serde_json1::value::Value>>>::new();
+          Ok(RichValue::String(String::from(v)))
+        }
 
-          while let Some(rich) = seq.next_element_seed(RichScopeSerdeSeed::<serde_json1::Value>::new(self.0))? {
-            value.push(rich.value);
-            meta.push(rich.meta);
-          }
+        fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+        where
+          E: Error,
+        {
+          Ok(RichValue::String(v))
+        }
 
-          let value = serde_json1::Value::Array(value);
-          let meta = Some(rich::ecosystem::serde_json1::ValueMeta::Array(meta));
+        fn visit_none<E>(self) -> Result<Self::Value, E>
+        where
+          E: Error,
+        {
+          Ok(RichValue::Null)
+        }
 
-          Ok(Rich::new(value, meta))
+        fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+          D: Deserializer<'de>,
+        {
+          deserializer.deserialize_any(self)
         }
 
-        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        fn visit_unit<E>(self) -> Result<Self::Value, E>
         where
           E: Error,
         {
-          let value0 = String::from(v);
-          let meta0 = ();
-          let rich0 = Rich::new(value0, meta0);
-          let wrapped0 = self.0.wrap(rich0);
+          Ok(RichValue::Null)
+        }
 
-          let value = serde_json1::Value::String(wrapped0.value);
-          let meta = Some(rich::ecosystem::serde_json1::ValueMeta::String(wrapped0.meta));
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+          A: MapAccess<'de>,
+        {
+          let mut entries = BTreeMap::new();
+
+          while let Some(key) = map.next_key_seed(RichScopeSerdeSeed::<String>::new(&mut *self.0))? {
+            let value = map.next_value_seed(RichScopeSerdeSeed::<serde_json1::Value>::new(&mut *self.0))?;
+            entries.insert(key.value, RichEntry::new(key.meta, value));
+          }
 
-          Ok(Rich::new(value, meta))
+          Ok(RichValue::Object(entries))
         }
 
-        fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
         where
-          E: Error,
+          A: SeqAccess<'de>,
         {
-          let value0 = v;
-          let meta0 = ();
-          let rich0 = Rich::new(value0, meta0);
-          let wrapped0 = self.0.wrap(rich0);
+          let mut values = Vec::new();
 
-          let value = serde_json1::Value::String(wrapped0.value);
-          let meta = Some(rich::ecosystem::serde_json1::ValueMeta::String(wrapped0.meta));
+          while let Some(rich) = seq.next_element_seed(RichScopeSerdeSeed::<serde_json1::Value>::new(&mut *self.0))? {
+            values.push(rich);
+          }
 
-          Ok(Rich::new(value, meta))
+          Ok(RichValue::Array(values))
         }
       }
 
-      deserializer.deserialize_any(RichVisitor(scope)).map(|v| scope.wrap(v))
+      let value = deserializer.deserialize_any(RichVisitor(&mut *scope))?;
+      scope.attach(value).map_err(D::Error::custom)
     }
   }
 }
@@ -1,25 +1,26 @@
 mod ecosystem;
 
-use rich::{Meta, MetaId, MetaNode, StructuralProjection, Rich, SplitMeta, WrappedMeta};
-use serde::de::{DeserializeSeed, Error, MapAccess, Visitor};
+use rich::{MergeMeta, MetaScope, MetaScopeError, Rich, ScopedMetaId};
+use serde::de::{DeserializeSeed, Error, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 use std::marker::PhantomData;
 
 /// Re-export of the public dependency `rich`.
 pub use rich;
+/// Re-export of the public dependency `serde`, used by the derive macros.
+pub use serde;
 
 /// Trait representing types that can richly deserialized (including
 /// deserialization metadata).
-trait RichDeserialize<'de>
-where
-  Self: StructuralProjection,
-{
-  fn rich_deserialize<'scope, D>(
-    scope: &'scope mut RichScope,
-    deserializer: D,
-  ) -> Result<Rich<Self, WrappedMeta<Self::Meta>>, D::Error>
+///
+/// The value is deserialized into its internal-metadata representation
+/// `Self::Rich`, where each component gets a metadata id from the scope.
+///
+/// Use `#[derive(RichDeserialize)]` from `rich_serde_derive` to implement it
+/// for structs with an internal-metadata twin.
+pub trait RichDeserialize<'de>: MergeMeta<ScopedMetaId> {
+  fn rich_deserialize<D>(scope: &mut RichScope, deserializer: D) -> Result<Rich<Self::Rich, ScopedMetaId>, D::Error>
   where
-    Self: Sized,
     D: Deserializer<'de>;
 }
 
@@ -27,7 +28,7 @@ impl<'de, 'scope, T> DeserializeSeed<'de> for RichScopeSerdeSeed<'scope, T>
 where
   T: RichDeserialize<'de>,
 {
-  type Value = Rich<T, WrappedMeta<T::Meta>>;
+  type Value = Rich<T::Rich, ScopedMetaId>;
 
   fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
   where
@@ -37,345 +38,235 @@ where
   }
 }
 
-
-// impl MergeMeta<()> for u32 {
-//   type Rich = Self;
-//
-//   fn merge_meta(self, _meta: ()) -> Self::Rich {
-//     self
-//   }
-// }
-//
-// impl SplitMeta for bool {
-//   type Value = Self;
-//   type Meta = ();
-//
-//   fn split_meta(self) -> (Self::Value, Self::Meta) {
-//     (self, ())
-//   }
-// }
-//
-// impl MergeMeta<()> for bool {
-//   type Rich = Self;
-//
-//   fn merge_meta(self, _meta: ()) -> Self::Rich {
-//     self
-//   }
-// }
-//
-// impl SplitMeta for String {
-//   type Value = Self;
-//   type Meta = ();
-//
-//   fn split_meta(self) -> (Self::Value, Self::Meta) {
-//     (self, ())
-//   }
-// }
-//
-// impl MergeMeta<()> for String {
-//   type Rich = Self;
-//
-//   fn merge_meta(self, _meta: ()) -> Self::Rich {
-//     self
-//   }
-// }
-
-pub struct RichScope {
-  next_id: usize,
-}
-
-impl RichScope {
-  pub fn new() -> Self {
-    Self { next_id: usize::MIN }
-  }
-
-  pub fn attach<T>(&mut self, value: T) -> Rich<T, MetaId> {
-    let id = self.next_id;
-    self.next_id = self.next_id.saturating_add(1);
-    Rich::new(value, MetaId(id))
-  }
-
-  pub fn wrap<Value, Nested>(&mut self, rich: Rich<Value, Nested>) -> Rich<Value, WrappedMeta<Nested>> {
-    let id = self.next_id;
-    self.next_id = self.next_id.saturating_add(1);
-    Rich::new(rich.value, WrappedMeta::new(rich.meta, MetaId(id)))
-  }
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct Nested {
-  #[allow(unused)]
-  crab: bool,
-}
-
-#[derive(Debug)]
-struct RichNested {
-  crab: Rich<bool, MetaId>,
-}
-
-impl StructuralProjection for Nested {
-  type Meta = MetaNested;
-}
-
-#[derive(Debug)]
-struct MetaNested {
-  #[allow(unused)]
-  crab: MetaNode<()>,
-}
-
-impl SplitMeta for RichNested {
-  type Value = Nested;
-
-  fn split_meta(self) -> Rich<Self::Value, Meta<Self::Value>> {
-    let crab = self.crab.deep_split_meta();
-    Rich::new(Nested { crab: crab.value }, MetaNested { crab: crab.meta })
-  }
-}
-
-// impl SplitMeta for RichNested {
-//   type Value = Nested;
-//   type Meta = MetaNested;
-//
-//   fn split_meta(self) -> (Self::Value, Self::Meta) {
-//     let (crab, meta_crab) = self.crab.deep_split_meta();
-//     (Nested { crab }, MetaNested { crab: meta_crab })
-//   }
-// }
-//
-// impl MergeMeta<MetaNested> for Nested {
-//   type Rich = RichNested;
-//
-//   fn merge_meta(self, meta: MetaNested) -> Self::Rich {
-//     RichNested {
-//       crab: Rich::new(self.crab, meta.crab.meta),
-//     }
-//   }
-// }
-
-// impl SplitMeta for RichConfig {
-//   type Value = Config;
-//   type Meta = MetaConfig;
-//
-//   fn split_meta(self) -> (Self::Value, Self::Meta) {
-//     let (str, meta_str) = self.str.deep_split_meta();
-//     let (num, meta_num) = self.num.deep_split_meta();
-//     let (nested, meta_nested) = self.nested.deep_split_meta();
-//     (
-//       Config { str, num, nested },
-//       MetaConfig {
-//         str: meta_str,
-//         num: meta_num,
-//         nested: meta_nested,
-//       },
-//     )
-//   }
-// }
-//
-// impl MergeMeta<MetaConfig> for Config {
-//   type Rich = RichConfig;
-//
-//   fn merge_meta(self, meta: MetaConfig) -> Self::Rich {
-//     RichConfig {
-//       str: Rich::new(self.str.merge_meta(meta.str.value), meta.str.meta),
-//       num: Rich::new(self.num.merge_meta(meta.num.value), meta.num.meta),
-//       nested: Rich::new(self.nested.merge_meta(meta.nested.value), meta.nested.meta),
-//     }
-//   }
-// }
-
-pub struct RichScopeSerdeSeed<'scope, T> {
-  scope: &'scope mut RichScope,
-  phantom: PhantomData<fn() -> T>,
-}
-
-impl<'scope, T> RichScopeSerdeSeed<'scope, T> {
-  pub fn new(scope: &'scope mut RichScope) -> Self {
-    Self {
-      scope,
-      phantom: PhantomData,
-    }
-  }
+/// Implement `RichDeserialize` for types without any sub-component: they are
+/// deserialized as usual, and then get their metadata id.
+macro_rules! impl_primitive {
+  ($($ty:ty),* $(,)?) => {
+    $(
+      impl<'de> RichDeserialize<'de> for $ty {
+        fn rich_deserialize<D>(scope: &mut RichScope, deserializer: D) -> Result<Rich<Self, ScopedMetaId>, D::Error>
+        where
+          D: Deserializer<'de>,
+        {
+          let value = <$ty>::deserialize(deserializer)?;
+          scope.attach(value).map_err(D::Error::custom)
+        }
+      }
+    )*
+  };
 }
 
-impl<'de, 'scope> DeserializeSeed<'de> for RichScopeSerdeSeed<'scope, RichNested> {
-  type Value = Rich<RichNested, MetaId>;
-
-  fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+impl_primitive!(
+  (),
+  bool,
+  char,
+  u8,
+  u16,
+  u32,
+  u64,
+  u128,
+  usize,
+  i8,
+  i16,
+  i32,
+  i64,
+  i128,
+  isize,
+  f32,
+  f64,
+  String,
+);
+
+impl<'de, T> RichDeserialize<'de> for Option<T>
+where
+  T: RichDeserialize<'de>,
+{
+  fn rich_deserialize<D>(scope: &mut RichScope, deserializer: D) -> Result<Rich<Self::Rich, ScopedMetaId>, D::Error>
   where
     D: Deserializer<'de>,
   {
-    #[derive(Debug)]
-    enum Field {
-      Crab,
-      Other,
+    struct OptionVisitor<'scope, T> {
+      scope: &'scope mut RichScope,
+      phantom: PhantomData<fn() -> T>,
     }
 
-    struct FieldVisitor;
-
-    impl<'de> Visitor<'de> for FieldVisitor {
-      type Value = Field;
+    impl<'de, 'scope, T> Visitor<'de> for OptionVisitor<'scope, T>
+    where
+      T: RichDeserialize<'de>,
+    {
+      type Value = Option<Rich<T::Rich, ScopedMetaId>>;
 
       fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
-        formatter.write_str("field identifier")
+        formatter.write_str("option")
       }
 
-      fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+      fn visit_none<E>(self) -> Result<Self::Value, E>
       where
         E: Error,
       {
-        match v {
-          "crab" => Ok(Field::Crab),
-          _ => Ok(Field::Other),
-        }
+        Ok(None)
       }
-    }
 
-    impl<'de> Deserialize<'de> for Field {
-      fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+      fn visit_unit<E>(self) -> Result<Self::Value, E>
+      where
+        E: Error,
+      {
+        Ok(None)
+      }
+
+      fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
       where
         D: Deserializer<'de>,
       {
-        deserializer.deserialize_identifier(FieldVisitor)
+        T::rich_deserialize(self.scope, deserializer).map(Some)
       }
     }
 
-    struct RichVisitor<'scope>(&'scope mut RichScope);
+    let value = deserializer.deserialize_option(OptionVisitor::<T> {
+      scope: &mut *scope,
+      phantom: PhantomData,
+    })?;
+    scope.attach(value).map_err(D::Error::custom)
+  }
+}
 
-    impl<'de, 'scope> Visitor<'de> for RichVisitor<'scope> {
-      type Value = RichNested;
+impl<'de, T> RichDeserialize<'de> for Vec<T>
+where
+  T: RichDeserialize<'de>,
+{
+  fn rich_deserialize<D>(scope: &mut RichScope, deserializer: D) -> Result<Rich<Self::Rich, ScopedMetaId>, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    struct SeqVisitor<'scope, T> {
+      scope: &'scope mut RichScope,
+      phantom: PhantomData<fn() -> T>,
+    }
+
+    impl<'de, 'scope, T> Visitor<'de> for SeqVisitor<'scope, T>
+    where
+      T: RichDeserialize<'de>,
+    {
+      type Value = Vec<Rich<T::Rich, ScopedMetaId>>;
 
       fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
-        formatter.write_str("struct RichNested")
+        formatter.write_str("a sequence")
       }
 
-      fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+      fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
       where
-        A: MapAccess<'de>,
+        A: SeqAccess<'de>,
       {
-        let mut crab: Option<Rich<bool, MetaId>> = None;
-
-        while let Some(key) = map.next_key::<Field>()? {
-          match key {
-            Field::Crab => {
-              if crab.is_some() {
-                return Err(A::Error::duplicate_field("crab"));
-              }
-              crab = Some(self.0.attach(map.next_value()?))
-            }
-            _ => {}
-          }
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element_seed(RichScopeSerdeSeed::<T>::new(&mut *self.scope))? {
+          values.push(value);
         }
-
-        let crab = match crab {
-          Some(crab) => crab,
-          None => return Err(A::Error::missing_field("crab")),
-        };
-
-        Ok(RichNested { crab })
+        Ok(values)
       }
     }
 
-    deserializer
-      .deserialize_struct("Nested", &["crab"], RichVisitor(self.scope))
-      .map(|v| self.scope.attach(v))
+    let value = deserializer.deserialize_seq(SeqVisitor::<T> {
+      scope: &mut *scope,
+      phantom: PhantomData,
+    })?;
+    scope.attach(value).map_err(D::Error::custom)
   }
 }
 
-#[derive(Debug)]
-pub struct MetaArena {
-  pub positions: Vec<u64>,
-}
-
-impl MetaArena {
-  pub fn new() -> Self {
-    Self { positions: Vec::new() }
+impl<'de, T> RichDeserialize<'de> for Box<T>
+where
+  T: RichDeserialize<'de>,
+{
+  fn rich_deserialize<D>(scope: &mut RichScope, deserializer: D) -> Result<Rich<Self::Rich, ScopedMetaId>, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let value = T::rich_deserialize(scope, deserializer)?;
+    scope.attach(Box::new(value)).map_err(D::Error::custom)
   }
 }
 
-#[allow(unused)]
-struct ConfigMeta<M> {
-  this: M,
-  fields: (M, M, M),
-  values: (M, M, NestedMeta<M>),
+/// Scope issuing the metadata ids of a rich deserialization.
+pub struct RichScope {
+  ids: MetaScope,
 }
 
-#[allow(unused)]
-struct NestedMeta<M> {
-  this: M,
-  fields: (M,),
-  values: (M,),
-}
+impl RichScope {
+  pub fn new() -> Result<Self, MetaScopeError> {
+    Ok(Self { ids: MetaScope::new()? })
+  }
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-  use rich::{Meta, MetaNode, Rich};
-  use rich_derive::MetaType;
-  use ::serde_json1;
-  use rich::ecosystem::serde_json1::value::{ValueView, ValueVisit};
+  pub fn attach<T>(&mut self, value: T) -> Result<Rich<T, ScopedMetaId>, MetaScopeError> {
+    self.ids.attach(value)
+  }
+}
 
-  #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, MetaType)]
-  #[meta(attr(derive(Default, Debug)))]
-  struct Opaque;
+pub struct RichScopeSerdeSeed<'scope, T> {
+  scope: &'scope mut RichScope,
+  phantom: PhantomData<fn() -> T>,
+}
 
-  #[test]
-  fn rich_parse_nested() {
-    // language=json
-    let input = r#"{
-  "crab": true
-}"#;
-    let mut scope = RichScope::new();
-    let seed = RichScopeSerdeSeed::<RichNested> {
-      scope: &mut scope,
+impl<'scope, T> RichScopeSerdeSeed<'scope, T> {
+  pub fn new(scope: &'scope mut RichScope) -> Self {
+    Self {
+      scope,
       phantom: PhantomData,
-    };
+    }
+  }
+}
 
-    let mut de = serde_json1::de::Deserializer::from_str(input);
-    let rich: Rich<RichNested, MetaId> = seed.deserialize(&mut de).unwrap();
+/// Deserializer for a field missing from its struct, used by the derive
+/// macro.
+///
+/// Like serde, missing `Option` fields deserialize to `None`, and other
+/// fields fail with a `missing_field` error.
+#[doc(hidden)]
+pub struct MissingFieldDeserializer<E> {
+  field: &'static str,
+  phantom: PhantomData<fn() -> E>,
+}
 
-    dbg!(&rich);
+impl<E> MissingFieldDeserializer<E> {
+  pub fn new(field: &'static str) -> Self {
+    Self {
+      field,
+      phantom: PhantomData,
+    }
+  }
+}
 
-    assert_eq!(rich.value.crab.value, true);
+impl<'de, E> Deserializer<'de> for MissingFieldDeserializer<E>
+where
+  E: Error,
+{
+  type Error = E;
 
-    let result = add(2, 2);
-    assert_eq!(result, 4);
+  fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    Err(E::missing_field(self.field))
   }
 
-  #[test]
-  fn rich_parse_config() {
-    // language=json
-    let input = r#"{
-  "num": 42,
-  "str": "Hello, World!",
-  "nested": {
-    "crab": true
+  fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_none()
   }
-}"#;
-    let mut scope = RichScope::new();
-    let seed = RichScopeSerdeSeed::<RichConfig> {
-      scope: &mut scope,
-      phantom: PhantomData,
-    };
-
-    let mut de = serde_json1::de::Deserializer::from_str(input);
-    let rich: Rich<RichConfig, MetaId> = seed.deserialize(&mut de).unwrap();
-
-    dbg!(&rich);
 
-    assert_eq!(rich.value.nested.value.crab.value, true);
-
-    let rich: Rich<Config, MetaNode<Meta<Config>>> = rich.deep_split_meta();
-
-    let foo: Meta<Opaque> = Meta::<Opaque>::default();
-
-    // dbg!(&config);
-    // dbg!(&meta);
-    //
-    // let merged = config.merge_meta(meta.value);
-    // dbg!(&merged);
-
-    // assert_eq!(config.nested.crab, true);
+  serde::forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf unit unit_struct newtype_struct seq tuple
+    tuple_struct map struct enum identifier ignored_any
   }
+}
+
+#[cfg(all(test, feature = "serde_json1"))]
+mod tests {
+  use super::*;
+  use ::serde_json1;
+  use rich::MetaId;
 
   #[test]
   fn rich_parse_serde_json_value() {
@@ -385,52 +276,44 @@ mod tests {
   "message": "Hello, World!",
   "list": [true, false]
 }"#;
-    let mut scope = RichScope::new();
-    let seed = RichScopeSerdeSeed::<serde_json1::Value> {
-      scope: &mut scope,
-      phantom: PhantomData,
-    };
+    let mut scope = RichScope::new().unwrap();
+    let seed = RichScopeSerdeSeed::<serde_json1::Value>::new(&mut scope);
 
     let mut de = serde_json1::de::Deserializer::from_str(input);
-    let rich: Rich<serde_json1::Value, WrappedMeta<Option<rich::ecosystem::serde_json1::ValueMeta>>> =
-      seed.deserialize(&mut de).unwrap();
-
-    dbg!(&rich);
+    let rich = seed.deserialize(&mut de).unwrap().deep_split_meta();
 
     assert_eq!(
       rich.value,
       serde_json1::Value::Object({
         let mut obj = serde_json1::value::Map::new();
         obj.insert(String::from("foo"), serde_json1::Value::Bool(true));
-        obj.insert(String::from("message"), serde_json1::Value::String(String::from("Hello, World!")));
-        obj.insert(String::from("list"), serde_json1::Value::Array(vec![serde_json1::Value::Bool(true),serde_json1::Value::Bool(false) ]));
+        obj.insert(
+          String::from("message"),
+          serde_json1::Value::String(String::from("Hello, World!")),
+        );
+        obj.insert(
+          String::from("list"),
+          serde_json1::Value::Array(vec![serde_json1::Value::Bool(true), serde_json1::Value::Bool(false)]),
+        );
         obj
       })
     );
 
-    let view = ValueView::new(rich.as_ref());
-    assert_eq!(view.meta(), MetaId::from_usize(9));
-
-    let ValueVisit::Object(view) = view.visit() else { panic!("expected view visit to return `Object` variant"); };
-
-    let foo: ValueView<'_> = view.get("foo").expect("`foo` view is available");
+    let view = rich.view();
+    assert_eq!(view.meta().map(|id| id.unscoped()), Some(MetaId::from_usize(8)));
 
-    dbg!(foo.value());
-    assert_eq!(foo.meta(), MetaId::from_usize(1));
-
-    // match view.visit() {
-    //   ValueVisit::Bool(view) => {
-    //     dbg!(view);
-    //   }
-    //   ValueVisit::Array(view) => {
-    //     dbg!(view);
-    //   }
-    //   ValueVisit::Object(view) => {
-    //     dbg!(view);
-    //   }
-    // }
-
-    let result = add(2, 2);
-    assert_eq!(result, 4);
+    let foo = view.get("foo").expect("`foo` view is available");
+    assert_eq!(
+      view.key_meta("foo").map(|id| id.unscoped()),
+      Some(MetaId::from_usize(0))
+    );
+    assert_eq!(foo.meta().map(|id| id.unscoped()), Some(MetaId::from_usize(1)));
+
+    let list = view.get("list").expect("`list` view is available");
+    let second = list.index(1).expect("`list[1]` view is available");
+    assert_eq!(second.value(), &serde_json1::Value::Bool(false));
+    assert_eq!(second.meta().map(|id| id.unscoped()), Some(MetaId::from_usize(6)));
+    assert!(list.index(2).is_none());
+    assert!(foo.get("bar").is_none());
   }
 }
@@ -0,0 +1,210 @@
+use rich::{MetaId, Rich, ScopedMetaId};
+use rich_derive::MetaType;
+use rich_serde::serde::de::DeserializeSeed;
+use rich_serde::{RichDeserialize, RichScope, RichScopeSerdeSeed};
+use rich_serde_derive::RichDeserialize;
+
+#[derive(Debug, Clone, PartialEq, MetaType, RichDeserialize)]
+#[meta(rich_twin = RichNested)]
+#[rich(attr(derive(Debug)))]
+struct Nested {
+  crab: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, MetaType, RichDeserialize)]
+#[meta(rich_twin = RichConfig)]
+#[rich(attr(derive(Debug)))]
+struct Config {
+  num: u32,
+  str: String,
+  nested: Nested,
+}
+
+#[derive(Debug, Clone, PartialEq, MetaType, RichDeserialize)]
+#[meta(rich_twin = RichPoint)]
+struct Point(i32, i32);
+
+#[derive(Debug, Clone, PartialEq, MetaType, RichDeserialize)]
+#[meta(rich_twin = RichPort)]
+struct Port(u16);
+
+#[derive(Debug, Clone, PartialEq, MetaType, RichDeserialize)]
+#[meta(rich_twin = RichEmpty)]
+struct Empty;
+
+#[derive(Debug, Clone, PartialEq, MetaType, RichDeserialize)]
+#[meta(rich_twin = RichLabelled)]
+struct Labelled<T> {
+  label: String,
+  #[meta(opaque)]
+  tags: Vec<String>,
+  #[meta(skip)]
+  comment: Option<String>,
+  value: T,
+}
+
+#[derive(Debug, Clone, PartialEq, MetaType, RichDeserialize)]
+#[meta(rich_twin = RichEndpoint)]
+#[rich(attr(derive(Debug)))]
+struct Endpoint {
+  host: String,
+  port: Option<u16>,
+}
+
+fn parse<T>(input: &str) -> Result<Rich<T::Rich, ScopedMetaId>, serde_json1::Error>
+where
+  T: for<'de> RichDeserialize<'de>,
+{
+  let mut scope = RichScope::new().unwrap();
+  let seed = RichScopeSerdeSeed::<T>::new(&mut scope);
+  let mut de = serde_json1::de::Deserializer::from_str(input);
+  seed.deserialize(&mut de)
+}
+
+#[test]
+fn rich_parse_nested() {
+  // language=json
+  let input = r#"{
+"crab": true
+}"#;
+  let rich: Rich<RichNested<ScopedMetaId>, ScopedMetaId> = parse::<Nested>(input).unwrap();
+
+  assert!(rich.value.crab.value);
+  assert_eq!(rich.value.crab.meta.unscoped(), MetaId::from_usize(0));
+  assert_eq!(rich.meta.unscoped(), MetaId::from_usize(1));
+}
+
+#[test]
+fn rich_parse_config() {
+  // language=json
+  let input = r#"{
+"num": 42,
+"str": "Hello, World!",
+"unknown": [1, 2, 3],
+"nested": {
+  "crab": true
+}
+}"#;
+  let rich = parse::<Config>(input).unwrap();
+
+  assert!(rich.value.nested.value.crab.value);
+
+  let rich = rich.deep_split_meta();
+
+  assert_eq!(
+    rich.value,
+    Config {
+      num: 42,
+      str: String::from("Hello, World!"),
+      nested: Nested { crab: true },
+    }
+  );
+  assert_eq!(rich.meta.nested().num.meta().unscoped(), MetaId::from_usize(0));
+  assert_eq!(rich.meta.nested().str.meta().unscoped(), MetaId::from_usize(1));
+  assert_eq!(
+    rich.meta.nested().nested.nested().crab.meta().unscoped(),
+    MetaId::from_usize(2)
+  );
+  assert_eq!(rich.meta.nested().nested.meta().unscoped(), MetaId::from_usize(3));
+  assert_eq!(rich.meta.meta().unscoped(), MetaId::from_usize(4));
+}
+
+#[test]
+fn rich_parse_struct_from_seq() {
+  let rich = parse::<Config>(r#"[1, "seq", {"crab": false}]"#).unwrap();
+
+  assert_eq!(
+    rich.deep_split_meta().value,
+    Config {
+      num: 1,
+      str: String::from("seq"),
+      nested: Nested { crab: false },
+    }
+  );
+}
+
+#[test]
+fn rich_parse_invalid_struct() {
+  let duplicate = parse::<Nested>(r#"{"crab": true, "crab": false}"#).unwrap_err();
+  assert_eq!(duplicate.to_string(), "duplicate field `crab` at line 1 column 21");
+
+  let missing = parse::<Config>(r#"{"num": 1, "str": ""}"#).unwrap_err();
+  assert_eq!(missing.to_string(), "missing field `nested` at line 1 column 21");
+
+  let short = parse::<Config>(r#"[1]"#).unwrap_err();
+  assert_eq!(
+    short.to_string(),
+    "invalid length 1, expected struct Config with 3 elements at line 1 column 3"
+  );
+}
+
+#[test]
+fn rich_parse_tuple_newtype_unit() {
+  let point = parse::<Point>("[3, -4]").unwrap();
+  assert_eq!(point.value.0.value, 3);
+  assert_eq!(point.value.1.value, -4);
+  assert_eq!(point.meta.unscoped(), MetaId::from_usize(2));
+
+  let port = parse::<Port>("8080").unwrap();
+  assert_eq!(port.value.0.value, 8080);
+  assert_eq!(port.value.0.meta.unscoped(), MetaId::from_usize(0));
+  assert_eq!(port.meta.unscoped(), MetaId::from_usize(1));
+
+  let empty = parse::<Empty>("null").unwrap();
+  assert_eq!(empty.meta.unscoped(), MetaId::from_usize(0));
+}
+
+#[test]
+fn rich_parse_field_attributes() {
+  // language=json
+  let input = r#"{
+"label": "answer",
+"tags": ["a", "b"],
+"comment": null,
+"value": [4, 2]
+}"#;
+  let rich = parse::<Labelled<Vec<u8>>>(input).unwrap();
+
+  assert_eq!(rich.value.tags.value, ["a", "b"]);
+  assert_eq!(rich.value.tags.meta.unscoped(), MetaId::from_usize(1));
+  assert_eq!(rich.value.comment, None);
+  assert_eq!(rich.value.value.meta.unscoped(), MetaId::from_usize(4));
+
+  let rich = rich.deep_split_meta();
+
+  assert_eq!(
+    rich.value,
+    Labelled {
+      label: String::from("answer"),
+      tags: vec![String::from("a"), String::from("b")],
+      comment: None,
+      value: vec![4, 2],
+    }
+  );
+  assert_eq!(
+    rich.meta.nested().value.nested()[1].meta().unscoped(),
+    MetaId::from_usize(3)
+  );
+  assert_eq!(rich.meta.meta().unscoped(), MetaId::from_usize(5));
+}
+
+#[test]
+fn rich_parse_missing_option_field() {
+  let rich = parse::<Endpoint>(r#"{"host": "localhost"}"#).unwrap();
+
+  assert_eq!(rich.value.port.value, None);
+  assert_eq!(rich.value.port.meta.unscoped(), MetaId::from_usize(1));
+  assert_eq!(
+    rich.deep_split_meta().value,
+    Endpoint {
+      host: String::from("localhost"),
+      port: None,
+    }
+  );
+
+  let rich = parse::<Labelled<u8>>(r#"{"label": "answer", "tags": [], "value": 42}"#).unwrap();
+  assert_eq!(rich.value.comment, None);
+
+  let missing = parse::<Endpoint>(r#"{"port": 80}"#).unwrap_err();
+  assert_eq!(missing.to_string(), "missing field `host` at line 1 column 12");
+}
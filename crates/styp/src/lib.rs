@@ -3,10 +3,76 @@
 //! `styp` is a helper library for structural typing when using Rust.
 //!
 //! The main feature currently is the [`StructuralProjection`] trait used to
-//! implement mapped types.
+//! implement mapped types. The [`map`] module provides the value-level
+//! counterpart, building projected values out of base values. The [`zip`]
+//! module pairs values with their projections, the [`reflect`] module
+//! describes and walks the components of values, and the [`soa`] module
+//! provides a Structure of Arrays container built on top of projections.
+//!
+//! # Features
+//!
+//! `styp` is a `no_std` crate. The following features are available:
+//! - `alloc`: implementations for `Vec`, `String`, `Box`, `Rc`, `Arc`,
+//!   `VecDeque` and the `BTree` collections, along with the [`reflect`] and
+//!   [`soa`] modules.
+//! - `std` (default): implementations for `HashMap`, `HashSet` and `PathBuf`.
+//!   Implies `alloc`.
+
+#![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, vec::Vec};
 use core::marker::PhantomData;
 
+pub mod map;
+#[cfg(feature = "alloc")]
+pub mod reflect;
+#[cfg(feature = "alloc")]
+pub mod soa;
+pub mod zip;
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use map::ArcMapper;
+#[cfg(feature = "alloc")]
+pub use map::{BoxMapper, VecMapper};
+pub use map::{
+  ComposeMapper, ConstMapper, IdentityMapper, Mapper, MapperMut, MapperRef, OptionMapper, PairMapper, PhantomMapper,
+  RefMapper, RefMutMapper, SliceMapper, StructuralMap, StructuralMapMut, StructuralMapRef,
+};
+#[cfg(feature = "alloc")]
+pub use reflect::{ComponentDescriptor, ComponentKind, FieldPath, PathSegment, Reflect};
+#[cfg(feature = "alloc")]
+pub use soa::{SoaLayout, SoaVec};
+pub use zip::{StructuralZip, ZipError, Zipped};
+
+/// Expand the wrapped items only when the `alloc` feature of `styp` is
+/// enabled.
+///
+/// Used by derived implementations relying on `alloc`, since the feature must
+/// be checked in `styp` rather than in the crate using the derive.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_alloc {
+  ($($item:item)*) => {
+    $($item)*
+  };
+}
+
+/// Expand the wrapped items only when the `alloc` feature of `styp` is
+/// enabled.
+#[cfg(not(feature = "alloc"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_alloc {
+  ($($item:item)*) => {};
+}
+
 /// Trait marking types supporting structural projection.
 ///
 /// Structural projection is type-level operation creating a new type by mapping
@@ -194,6 +260,8 @@ use core::marker::PhantomData;
 /// of the original type.
 ///
 /// ```rust
+/// # #[cfg(feature = "alloc")]
+/// # {
 /// use styp::{Project, SliceProjector, VecProjector};
 ///
 /// # #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -214,6 +282,7 @@ use core::marker::PhantomData;
 /// // These types are equivalent to the manually written ones.
 /// type WeatherMeasurementVec = Project<WeatherMeasurement, VecProjector>;
 /// type WeatherMeasurementSlice<'a> = Project<WeatherMeasurement, SliceProjector<'a>>;
+/// # }
 /// ```
 ///
 /// Another good use-case it to turn a structure into a "view type" of references.
@@ -373,6 +442,8 @@ use core::marker::PhantomData;
 ///
 /// This implementation can be used as follows:
 /// ```rust
+/// # #[cfg(feature = "alloc")]
+/// # {
 /// # use styp::{Projector, StructuralProjection, VecProjector};
 /// #
 /// # #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -405,6 +476,7 @@ use core::marker::PhantomData;
 /// # }
 ///
 /// type WeatherMeasurementVec = <WeatherMeasurement as StructuralProjection<VecProjector>>::Projection;
+/// # }
 /// ```
 ///
 /// For ergonomics, we can define a type alias helper:
@@ -420,6 +492,8 @@ use core::marker::PhantomData;
 ///
 /// And finally get the wanted types:
 /// ```rust
+/// # #[cfg(feature = "alloc")]
+/// # {
 /// # use styp::{Project, Projector, StructuralProjection, VecProjector};
 /// #
 /// # #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -452,8 +526,60 @@ use core::marker::PhantomData;
 /// # }
 ///
 /// type WeatherMeasurementVec = Project<WeatherMeasurement, VecProjector>;
+/// # }
+/// ```
+///
+/// ## Deriving
+///
+/// The generic structure type and the `StructuralProjection` implementation
+/// can be generated with `#[derive(StructuralProjection)]` from the
+/// `styp_derive` crate. The structure type is named `{Type}Structure` by
+/// default; use `#[styp(name = ...)]` to pick another name and
+/// `#[styp(attr(...))]` to attach attributes to it.
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")]
+/// # {
+/// use styp::{Project, RefProjector, VecProjector};
+/// use styp_derive::StructuralProjection;
+///
+/// # #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// # struct Pressure(pub u64);
+/// #
+/// # #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// # enum Temperature {
+/// #   Cold,
+/// #   Nice,
+/// #   Hot,
+/// # }
+/// #
+/// #[derive(StructuralProjection)]
+/// #[styp(name = WeatherMeasurementStructure, attr(derive(Debug, PartialEq)))]
+/// struct WeatherMeasurement {
+///   pub temperature: Temperature,
+///   pub pressure: Pressure,
+/// }
+///
+/// let columns: Project<WeatherMeasurement, VecProjector> = WeatherMeasurementStructure {
+///   temperature: vec![Temperature::Cold, Temperature::Hot],
+///   pressure: vec![Pressure(101250), Pressure(99800)],
+/// };
+///
+/// let first: Project<WeatherMeasurement, RefProjector<'_>> = WeatherMeasurementStructure {
+///   temperature: &columns.temperature[0],
+///   pressure: &columns.pressure[0],
+/// };
+/// assert_eq!(first.temperature, &Temperature::Cold);
+/// # }
 /// ```
 ///
+/// Tuple structs, unit structs and enums are supported too. Each field of each
+/// variant receives its own type parameter in the structure type.
+///
+/// Besides `StructuralProjection`, the derive implements the value-level
+/// [`map`] traits, [`StructuralZip`], [`Reflect`], and [`SoaLayout`] for
+/// structs with fields.
+///
 /// ## Extensions
 ///
 /// With all the scaffolding built in this example, we don't even need the
@@ -494,12 +620,32 @@ use core::marker::PhantomData;
 ///   and should always project to `()`.
 /// - Collections such as `Vec<T>` should behave as a uniform tuple `(T, T, ..., T)`
 ///   so they should be projected into `Vec<Projector::Apply<T>>` or similar.
-/// - A map is a collection of `(K, V)` pairs.
+/// - A map is a collection of `(K, V)` pairs. The built-in impls keep the
+///   original key to index the projection and project each entry into an
+///   [`Entry`]: `HashMap<K, V>` becomes `HashMap<K, Entry<Apply<K>, Apply<V>>>`.
+///   Sets are maps without values: `HashSet<T>` becomes `HashMap<T, Apply<T>>`.
+/// - Smart pointers (`Box<T>`, `Rc<T>`, `Arc<T>`), `Option<T>`, `Result<T, E>`
+///   and arrays `[T; N]` keep their shape and project their content.
 /// - Sometimes it's up to the author to make a judgement call. For example,
 ///   should `String` value be treated as primitives or a collection of `char`?
 /// - Enums are tricky
-/// - Recursion is up to the author. Ideally a type would provide the choice
-///   between single-level or recursive projection.
+/// - Implementations are single-level: `Vec<T>` projects `T` with the
+///   projector and leaves the inner structure of `T` untouched. Use [`Deep`]
+///   to recurse through nested types instead.
+///
+/// ```rust
+/// use styp::{ConstProjector, Entry, Project};
+///
+/// type Line = ConstProjector<usize>;
+///
+/// let port: Project<u16, Line> = ();
+/// let name: Project<Option<&str>, Line> = Some(1);
+/// # #[cfg(feature = "std")]
+/// # {
+/// # use std::collections::HashMap;
+/// let ratios: Project<HashMap<String, f64>, Line> = HashMap::from([(String::from("ratio"), Entry::new(2, 3))]);
+/// # }
+/// ```
 pub trait StructuralProjection<TyProjector> {
   /// Type of extracted metadata.
   ///
@@ -572,9 +718,11 @@ impl<TyInput, T> Projector<TyInput> for ConstProjector<T> {
   type Output = T;
 }
 
+#[cfg(feature = "alloc")]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum VecProjector {}
 
+#[cfg(feature = "alloc")]
 impl<TyInput> Projector<TyInput> for VecProjector {
   type Output = Vec<TyInput>;
 }
@@ -597,22 +745,252 @@ impl<'a, TyInput: 'a> Projector<TyInput> for RefMutProjector<'a> {
   type Output = &'a mut TyInput;
 }
 
-impl<TyProjector> StructuralProjection<TyProjector> for () {
-  type Projection = ();
+/// Structural projector leaving all component types unchanged.
+///
+/// `Project<T, IdentityProjector>` is a generic structure type instantiated
+/// with the original field types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IdentityProjector {}
+
+impl<TyInput> Projector<TyInput> for IdentityProjector {
+  type Output = TyInput;
 }
 
-impl<TyProjector> StructuralProjection<TyProjector> for bool {
-  type Projection = ();
+/// Structural projector mapping each component type `T` to `PhantomData<T>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PhantomProjector {}
+
+impl<TyInput> Projector<TyInput> for PhantomProjector {
+  type Output = PhantomData<TyInput>;
 }
 
-impl<TyProjector> StructuralProjection<TyProjector> for u32 {
-  type Projection = ();
+/// Structural projector making each component optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OptionProjector {}
+
+impl<TyInput> Projector<TyInput> for OptionProjector {
+  type Output = Option<TyInput>;
+}
+
+/// Structural projector boxing each component.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BoxProjector {}
+
+#[cfg(feature = "alloc")]
+impl<TyInput> Projector<TyInput> for BoxProjector {
+  type Output = Box<TyInput>;
+}
+
+/// Structural projector wrapping each component in an `Arc`.
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ArcProjector {}
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+impl<TyInput> Projector<TyInput> for ArcProjector {
+  type Output = alloc::sync::Arc<TyInput>;
+}
+
+/// Structural projector applying `P2`, then `P1`.
+///
+/// This follows function composition: the outer projector comes first, so
+/// `Compose<OptionProjector, RefProjector<'a>>` maps `T` to `Option<&'a T>`.
+///
+/// # Example
+///
+/// ```rust
+/// use styp::{Compose, OptionProjector, Project, RefProjector};
+/// use styp_derive::StructuralProjection;
+///
+/// #[derive(StructuralProjection)]
+/// struct Config {
+///   pub host: String,
+///   pub port: u16,
+/// }
+///
+/// let config = Config {
+///   host: String::from("localhost"),
+///   port: 8080,
+/// };
+///
+/// let partial: Project<Config, Compose<OptionProjector, RefProjector<'_>>> = ConfigStructure {
+///   host: None,
+///   port: Some(&config.port),
+/// };
+/// assert_eq!(partial.port, Some(&8080));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Compose<P1, P2>(PhantomData<(P1, P2)>, core::convert::Infallible);
+
+impl<TyInput, P1, P2> Projector<TyInput> for Compose<P1, P2>
+where
+  P2: Projector<TyInput>,
+  P1: Projector<<P2 as Projector<TyInput>>::Output>,
+{
+  type Output = <P1 as Projector<<P2 as Projector<TyInput>>::Output>>::Output;
 }
 
-impl<TyProjector> StructuralProjection<TyProjector> for String {
+/// Structural projector applying both `P1` and `P2` to each component, and
+/// pairing the results.
+///
+/// ```rust
+/// use styp::{ConstProjector, IdentityProjector, PairProjector, Project};
+///
+/// type Annotated = PairProjector<IdentityProjector, ConstProjector<&'static str>>;
+///
+/// let value: Project<Option<u8>, Annotated> = Some((3, "three"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PairProjector<P1, P2>(PhantomData<(P1, P2)>, core::convert::Infallible);
+
+impl<TyInput, P1, P2> Projector<TyInput> for PairProjector<P1, P2>
+where
+  P1: Projector<TyInput>,
+  P2: Projector<TyInput>,
+{
+  type Output = (<P1 as Projector<TyInput>>::Output, <P2 as Projector<TyInput>>::Output);
+}
+
+/// Operator applied at each level of a recursive projection with [`Deep`].
+///
+/// `TyInput` is the type of the component, and `TyNested` is the recursive
+/// projection of its own sub-components. For primitives, `TyNested` is `()`:
+/// this is the base case of the recursion.
+pub trait DeepProjector<TyInput, TyNested> {
+  type Output;
+}
+
+/// Structural projector recursing through nested types.
+///
+/// Each component type `T` is first projected recursively with `Deep<P>`,
+/// the result is then combined with `T` through the [`DeepProjector`] `P`.
+/// The recursion stops at primitives, whose projection is `()`.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")]
+/// # {
+/// use styp::{Annotate, ConstProjector, Deep, Project};
+///
+/// // Attach a `usize` to each component, at any depth.
+/// type Spans = Deep<Annotate<ConstProjector<usize>>>;
+///
+/// let spans: Project<Vec<Option<u8>>, Spans> = vec![(0, Some((1, ()))), (2, None)];
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deep<P>(PhantomData<P>, core::convert::Infallible);
+
+impl<TyInput, P> Projector<TyInput> for Deep<P>
+where
+  TyInput: StructuralProjection<Deep<P>>,
+  P: DeepProjector<TyInput, <TyInput as StructuralProjection<Deep<P>>>::Projection>,
+{
+  type Output = <P as DeepProjector<TyInput, <TyInput as StructuralProjection<Deep<P>>>::Projection>>::Output;
+}
+
+/// [`DeepProjector`] pairing the output of the regular projector `P` for the
+/// component with the projection of its sub-components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Annotate<P>(PhantomData<P>, core::convert::Infallible);
+
+impl<TyInput, TyNested, P> DeepProjector<TyInput, TyNested> for Annotate<P>
+where
+  P: Projector<TyInput>,
+{
+  type Output = (<P as Projector<TyInput>>::Output, TyNested);
+}
+
+/// Implement `StructuralProjection` for types without any sub-component.
+macro_rules! impl_primitive {
+  ($($ty:ty),* $(,)?) => {
+    $(
+      impl<TyProjector> StructuralProjection<TyProjector> for $ty {
+        type Projection = ();
+      }
+    )*
+  };
+}
+
+impl_primitive!(
+  (),
+  bool,
+  char,
+  u8,
+  u16,
+  u32,
+  u64,
+  u128,
+  usize,
+  i8,
+  i16,
+  i32,
+  i64,
+  i128,
+  isize,
+  f32,
+  f64,
+  core::time::Duration,
+);
+
+#[cfg(feature = "alloc")]
+impl_primitive!(String, Box<str>);
+
+#[cfg(feature = "std")]
+impl_primitive!(std::path::PathBuf);
+
+impl<TyProjector> StructuralProjection<TyProjector> for &str {
   type Projection = ();
 }
 
+impl<TyProjector, T> StructuralProjection<TyProjector> for Option<T>
+where
+  TyProjector: Projector<T>,
+{
+  type Projection = Option<<TyProjector as Projector<T>>::Output>;
+}
+
+impl<TyProjector, T, E> StructuralProjection<TyProjector> for Result<T, E>
+where
+  TyProjector: Projector<T> + Projector<E>,
+{
+  type Projection = Result<<TyProjector as Projector<T>>::Output, <TyProjector as Projector<E>>::Output>;
+}
+
+#[cfg(feature = "alloc")]
+impl<TyProjector, T> StructuralProjection<TyProjector> for Box<T>
+where
+  TyProjector: Projector<T>,
+{
+  type Projection = Box<<TyProjector as Projector<T>>::Output>;
+}
+
+#[cfg(feature = "alloc")]
+impl<TyProjector, T> StructuralProjection<TyProjector> for alloc::rc::Rc<T>
+where
+  TyProjector: Projector<T>,
+{
+  type Projection = alloc::rc::Rc<<TyProjector as Projector<T>>::Output>;
+}
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+impl<TyProjector, T> StructuralProjection<TyProjector> for alloc::sync::Arc<T>
+where
+  TyProjector: Projector<T>,
+{
+  type Projection = alloc::sync::Arc<<TyProjector as Projector<T>>::Output>;
+}
+
+impl<TyProjector, T, const N: usize> StructuralProjection<TyProjector> for [T; N]
+where
+  TyProjector: Projector<T>,
+{
+  type Projection = [<TyProjector as Projector<T>>::Output; N];
+}
+
+#[cfg(feature = "alloc")]
 impl<TyProjector, T> StructuralProjection<TyProjector> for Vec<T>
 where
   TyProjector: Projector<T>,
@@ -620,32 +998,98 @@ where
   type Projection = Vec<<TyProjector as Projector<T>>::Output>;
 }
 
-impl<TyProjector, T0> StructuralProjection<TyProjector> for (T0,)
+#[cfg(feature = "alloc")]
+impl<TyProjector, T> StructuralProjection<TyProjector> for alloc::collections::VecDeque<T>
 where
-  TyProjector: Projector<T0>,
+  TyProjector: Projector<T>,
 {
-  type Projection = (<TyProjector as Projector<T0>>::Output,);
+  type Projection = alloc::collections::VecDeque<<TyProjector as Projector<T>>::Output>;
 }
 
+/// Projection of a map entry, holding the projections of its key and value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entry<K, V> {
+  /// Projection of the key of the entry
+  pub key: K,
+  /// Projection of the value of the entry
+  pub value: V,
+}
 
-impl<TyProjector, T0, T1> StructuralProjection<TyProjector> for (T0, T1)
+impl<K, V> Entry<K, V> {
+  /// Create an [`Entry`] value, by pairing projections for the key and for the value.
+  pub const fn new(key: K, value: V) -> Self {
+    Self { key, value }
+  }
+}
+
+/// Maps are projected entry-wise: the original key is kept to index the
+/// projection, and the entry `(K, V)` is projected into an [`Entry`].
+#[cfg(feature = "std")]
+impl<TyProjector, K, V, S> StructuralProjection<TyProjector> for std::collections::HashMap<K, V, S>
 where
-  TyProjector: Projector<T0> + Projector<T1>,
+  TyProjector: Projector<K> + Projector<V>,
 {
-  type Projection = (
-    <TyProjector as Projector<T0>>::Output,
-    <TyProjector as Projector<T1>>::Output,
-  );
+  type Projection = std::collections::HashMap<
+    K,
+    Entry<<TyProjector as Projector<K>>::Output, <TyProjector as Projector<V>>::Output>,
+    S,
+  >;
 }
 
+/// Maps are projected entry-wise: the original key is kept to index the
+/// projection, and the entry `(K, V)` is projected into an [`Entry`].
+#[cfg(feature = "alloc")]
+impl<TyProjector, K, V> StructuralProjection<TyProjector> for alloc::collections::BTreeMap<K, V>
+where
+  TyProjector: Projector<K> + Projector<V>,
+{
+  type Projection = alloc::collections::BTreeMap<
+    K,
+    Entry<<TyProjector as Projector<K>>::Output, <TyProjector as Projector<V>>::Output>,
+  >;
+}
 
-impl<TyProjector, T0, T1, T2> StructuralProjection<TyProjector> for (T0, T1, T2)
+/// Sets are maps without values: each element is kept as the key of its
+/// projection.
+#[cfg(feature = "std")]
+impl<TyProjector, T, S> StructuralProjection<TyProjector> for std::collections::HashSet<T, S>
 where
-  TyProjector: Projector<T0> + Projector<T1> + Projector<T2>,
+  TyProjector: Projector<T>,
+{
+  type Projection = std::collections::HashMap<T, <TyProjector as Projector<T>>::Output, S>;
+}
+
+/// Sets are maps without values: each element is kept as the key of its
+/// projection.
+#[cfg(feature = "alloc")]
+impl<TyProjector, T> StructuralProjection<TyProjector> for alloc::collections::BTreeSet<T>
+where
+  TyProjector: Projector<T>,
 {
-  type Projection = (
-    <TyProjector as Projector<T0>>::Output,
-    <TyProjector as Projector<T1>>::Output,
-    <TyProjector as Projector<T2>>::Output,
-  );
-}
\ No newline at end of file
+  type Projection = alloc::collections::BTreeMap<T, <TyProjector as Projector<T>>::Output>;
+}
+
+/// Implement `StructuralProjection` for tuples, projecting each component.
+macro_rules! impl_tuple {
+  ($($t:ident),+) => {
+    impl<TyProjector, $($t),+> StructuralProjection<TyProjector> for ($($t,)+)
+    where
+      TyProjector: $(Projector<$t> +)+,
+    {
+      type Projection = ($(<TyProjector as Projector<$t>>::Output,)+);
+    }
+  };
+}
+
+impl_tuple!(T0);
+impl_tuple!(T0, T1);
+impl_tuple!(T0, T1, T2);
+impl_tuple!(T0, T1, T2, T3);
+impl_tuple!(T0, T1, T2, T3, T4);
+impl_tuple!(T0, T1, T2, T3, T4, T5);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+impl_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
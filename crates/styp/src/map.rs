@@ -0,0 +1,846 @@
+//! Value-level counterpart of structural projection.
+//!
+//! [`StructuralProjection`] computes the _type_ of a projection. This module
+//! provides the traits to compute projected _values_: a [`Mapper`] is a
+//! polymorphic function turning each component value into a value of the type
+//! produced by the corresponding [`Projector`], and [`StructuralMap`] applies it
+//! to all the components of a value.
+//!
+//! The traits come in three flavors depending on how the components are
+//! accessed: by value ([`Mapper`], [`StructuralMap`]), by shared reference
+//! ([`MapperRef`], [`StructuralMapRef`]) or by exclusive reference
+//! ([`MapperMut`], [`StructuralMapMut`]).
+//!
+//! # Example
+//!
+//! ```rust
+//! use styp::{ConstMapper, ConstProjector, Project, RefMapper, RefProjector, StructuralMapRef};
+//! use styp_derive::StructuralProjection;
+//!
+//! #[derive(StructuralProjection)]
+//! #[styp(attr(derive(Debug, PartialEq)))]
+//! struct WeatherMeasurement {
+//!   pub temperature: i16,
+//!   pub pressure: u32,
+//! }
+//!
+//! let measure = WeatherMeasurement {
+//!   temperature: 23,
+//!   pressure: 101250,
+//! };
+//!
+//! let view: Project<WeatherMeasurement, RefProjector<'_>> = measure.structural_map_ref(&mut RefMapper);
+//! assert_eq!(view.pressure, &101250);
+//!
+//! let station: Project<WeatherMeasurement, ConstProjector<&str>> = measure.structural_map_ref(&mut ConstMapper("Paris"));
+//! assert_eq!(
+//!   station,
+//!   WeatherMeasurementStructure {
+//!     temperature: "Paris",
+//!     pressure: "Paris",
+//!   }
+//! );
+//! ```
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+use crate::ArcProjector;
+#[cfg(feature = "alloc")]
+use crate::{BoxProjector, Entry, VecProjector};
+use crate::{
+  Compose, ConstProjector, IdentityProjector, OptionProjector, PairProjector, PhantomProjector, Projector,
+  RefMutProjector, RefProjector, SliceProjector, StructuralProjection,
+};
+#[cfg(feature = "alloc")]
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+#[cfg(feature = "alloc")]
+use alloc::rc::Rc;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+use alloc::sync::Arc;
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+
+/// Polymorphic function mapping a component value of type `TyInput` into
+/// the output type of `TyProjector`.
+///
+/// Projectors are uninhabited types used purely for type-level computation;
+/// mappers are the runtime values providing the corresponding transformation.
+pub trait Mapper<TyProjector, TyInput>
+where
+  TyProjector: Projector<TyInput>,
+{
+  fn map(&mut self, input: TyInput) -> <TyProjector as Projector<TyInput>>::Output;
+}
+
+/// Polymorphic function mapping a shared reference to a component into the
+/// output type of `TyProjector`.
+pub trait MapperRef<'a, TyProjector, TyInput>
+where
+  TyProjector: Projector<TyInput>,
+{
+  fn map_ref(&mut self, input: &'a TyInput) -> <TyProjector as Projector<TyInput>>::Output;
+}
+
+/// Polymorphic function mapping an exclusive reference to a component into the
+/// output type of `TyProjector`.
+pub trait MapperMut<'a, TyProjector, TyInput>
+where
+  TyProjector: Projector<TyInput>,
+{
+  fn map_mut(&mut self, input: &'a mut TyInput) -> <TyProjector as Projector<TyInput>>::Output;
+}
+
+/// Value-level structural projection, consuming the base value.
+///
+/// Each component is passed to `mapper`, and the results are assembled into
+/// the projection of `Self`.
+pub trait StructuralMap<TyProjector, TyMapper>: StructuralProjection<TyProjector> + Sized {
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection;
+}
+
+/// Value-level structural projection, borrowing the base value.
+pub trait StructuralMapRef<'a, TyProjector, TyMapper>: StructuralProjection<TyProjector> {
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection;
+}
+
+/// Value-level structural projection, exclusively borrowing the base value.
+pub trait StructuralMapMut<'a, TyProjector, TyMapper>: StructuralProjection<TyProjector> {
+  fn structural_map_mut(&'a mut self, mapper: &mut TyMapper) -> Self::Projection;
+}
+
+/// Mapper for [`ConstProjector`], producing a clone of the wrapped value for
+/// each component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConstMapper<T>(pub T);
+
+impl<TyInput, T: Clone> Mapper<ConstProjector<T>, TyInput> for ConstMapper<T> {
+  fn map(&mut self, _input: TyInput) -> T {
+    self.0.clone()
+  }
+}
+
+impl<'a, TyInput, T: Clone> MapperRef<'a, ConstProjector<T>, TyInput> for ConstMapper<T> {
+  fn map_ref(&mut self, _input: &'a TyInput) -> T {
+    self.0.clone()
+  }
+}
+
+impl<'a, TyInput, T: Clone> MapperMut<'a, ConstProjector<T>, TyInput> for ConstMapper<T> {
+  fn map_mut(&mut self, _input: &'a mut TyInput) -> T {
+    self.0.clone()
+  }
+}
+
+/// Mapper for [`VecProjector`], wrapping each component in a single-element
+/// `Vec`.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VecMapper;
+
+#[cfg(feature = "alloc")]
+impl<TyInput> Mapper<VecProjector, TyInput> for VecMapper {
+  fn map(&mut self, input: TyInput) -> Vec<TyInput> {
+    vec![input]
+  }
+}
+
+/// Mapper for [`SliceProjector`], turning each component into a
+/// single-element slice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SliceMapper;
+
+impl<'a, TyInput: 'a> MapperRef<'a, SliceProjector<'a>, TyInput> for SliceMapper {
+  fn map_ref(&mut self, input: &'a TyInput) -> &'a [TyInput] {
+    core::slice::from_ref(input)
+  }
+}
+
+impl<'a, TyInput: 'a> MapperMut<'a, SliceProjector<'a>, TyInput> for SliceMapper {
+  fn map_mut(&mut self, input: &'a mut TyInput) -> &'a [TyInput] {
+    core::slice::from_ref(input)
+  }
+}
+
+/// Mapper for [`RefProjector`], borrowing each component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RefMapper;
+
+impl<'a, TyInput: 'a> MapperRef<'a, RefProjector<'a>, TyInput> for RefMapper {
+  fn map_ref(&mut self, input: &'a TyInput) -> &'a TyInput {
+    input
+  }
+}
+
+impl<'a, TyInput: 'a> MapperMut<'a, RefProjector<'a>, TyInput> for RefMapper {
+  fn map_mut(&mut self, input: &'a mut TyInput) -> &'a TyInput {
+    input
+  }
+}
+
+/// Mapper for [`RefMutProjector`], exclusively borrowing each component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RefMutMapper;
+
+impl<'a, TyInput: 'a> MapperMut<'a, RefMutProjector<'a>, TyInput> for RefMutMapper {
+  fn map_mut(&mut self, input: &'a mut TyInput) -> &'a mut TyInput {
+    input
+  }
+}
+
+/// Mapper for [`IdentityProjector`], returning each component unchanged.
+///
+/// Borrowed components are cloned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IdentityMapper;
+
+impl<TyInput> Mapper<IdentityProjector, TyInput> for IdentityMapper {
+  fn map(&mut self, input: TyInput) -> TyInput {
+    input
+  }
+}
+
+impl<'a, TyInput: Clone> MapperRef<'a, IdentityProjector, TyInput> for IdentityMapper {
+  fn map_ref(&mut self, input: &'a TyInput) -> TyInput {
+    input.clone()
+  }
+}
+
+impl<'a, TyInput: Clone> MapperMut<'a, IdentityProjector, TyInput> for IdentityMapper {
+  fn map_mut(&mut self, input: &'a mut TyInput) -> TyInput {
+    input.clone()
+  }
+}
+
+/// Mapper for [`PhantomProjector`], discarding each component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PhantomMapper;
+
+impl<TyInput> Mapper<PhantomProjector, TyInput> for PhantomMapper {
+  fn map(&mut self, _input: TyInput) -> PhantomData<TyInput> {
+    PhantomData
+  }
+}
+
+impl<'a, TyInput> MapperRef<'a, PhantomProjector, TyInput> for PhantomMapper {
+  fn map_ref(&mut self, _input: &'a TyInput) -> PhantomData<TyInput> {
+    PhantomData
+  }
+}
+
+impl<'a, TyInput> MapperMut<'a, PhantomProjector, TyInput> for PhantomMapper {
+  fn map_mut(&mut self, _input: &'a mut TyInput) -> PhantomData<TyInput> {
+    PhantomData
+  }
+}
+
+/// Implement `Mapper` for a mapper wrapping each component in a container.
+///
+/// These mappers consume their input; combine them with a borrowing mapper
+/// through [`ComposeMapper`] to wrap references instead.
+macro_rules! impl_wrap_mapper {
+  ($(#[$attr:meta])* $mapper:ident, $projector:ident, $wrap:expr) => {
+    $(#[$attr])*
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct $mapper;
+
+    impl<TyInput> Mapper<$projector, TyInput> for $mapper {
+      fn map(&mut self, input: TyInput) -> <$projector as Projector<TyInput>>::Output {
+        $wrap(input)
+      }
+    }
+  };
+}
+
+impl_wrap_mapper!(
+  /// Mapper for [`OptionProjector`], wrapping each component in `Some`.
+  OptionMapper,
+  OptionProjector,
+  Some
+);
+#[cfg(feature = "alloc")]
+impl_wrap_mapper!(
+  /// Mapper for [`BoxProjector`], boxing each component.
+  BoxMapper,
+  BoxProjector,
+  Box::new
+);
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+impl_wrap_mapper!(
+  /// Mapper for [`ArcProjector`], moving each component into an `Arc`.
+  ArcMapper,
+  ArcProjector,
+  alloc::sync::Arc::new
+);
+
+/// Mapper for [`Compose`], applying `M2` then `M1`.
+///
+/// The inner mapper `M2` receives the component in the requested flavor (by
+/// value, by shared reference or by exclusive reference), the outer mapper
+/// `M1` always receives the intermediate value by value.
+///
+/// ```rust
+/// use styp::{Compose, ComposeMapper, OptionMapper, OptionProjector, Project, RefMapper, RefProjector, StructuralMapRef};
+///
+/// let pair: (u8, String) = (3, String::from("three"));
+/// let partial: Project<(u8, String), Compose<OptionProjector, RefProjector<'_>>> =
+///   pair.structural_map_ref(&mut ComposeMapper(OptionMapper, RefMapper));
+/// assert_eq!(partial, (Some(&3), Some(&String::from("three"))));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ComposeMapper<M1, M2>(pub M1, pub M2);
+
+impl<TyInput, P1, P2, M1, M2> Mapper<Compose<P1, P2>, TyInput> for ComposeMapper<M1, M2>
+where
+  P2: Projector<TyInput>,
+  P1: Projector<<P2 as Projector<TyInput>>::Output>,
+  M2: Mapper<P2, TyInput>,
+  M1: Mapper<P1, <P2 as Projector<TyInput>>::Output>,
+{
+  fn map(&mut self, input: TyInput) -> <Compose<P1, P2> as Projector<TyInput>>::Output {
+    self.0.map(self.1.map(input))
+  }
+}
+
+impl<'a, TyInput, P1, P2, M1, M2> MapperRef<'a, Compose<P1, P2>, TyInput> for ComposeMapper<M1, M2>
+where
+  P2: Projector<TyInput>,
+  P1: Projector<<P2 as Projector<TyInput>>::Output>,
+  M2: MapperRef<'a, P2, TyInput>,
+  M1: Mapper<P1, <P2 as Projector<TyInput>>::Output>,
+{
+  fn map_ref(&mut self, input: &'a TyInput) -> <Compose<P1, P2> as Projector<TyInput>>::Output {
+    self.0.map(self.1.map_ref(input))
+  }
+}
+
+impl<'a, TyInput, P1, P2, M1, M2> MapperMut<'a, Compose<P1, P2>, TyInput> for ComposeMapper<M1, M2>
+where
+  P2: Projector<TyInput>,
+  P1: Projector<<P2 as Projector<TyInput>>::Output>,
+  M2: MapperMut<'a, P2, TyInput>,
+  M1: Mapper<P1, <P2 as Projector<TyInput>>::Output>,
+{
+  fn map_mut(&mut self, input: &'a mut TyInput) -> <Compose<P1, P2> as Projector<TyInput>>::Output {
+    self.0.map(self.1.map_mut(input))
+  }
+}
+
+/// Mapper for [`PairProjector`], applying both `M1` and `M2` to each component.
+///
+/// Mapping by value clones each component. There is no exclusive reference
+/// flavor since the component can't be lent to both mappers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PairMapper<M1, M2>(pub M1, pub M2);
+
+impl<TyInput, P1, P2, M1, M2> Mapper<PairProjector<P1, P2>, TyInput> for PairMapper<M1, M2>
+where
+  TyInput: Clone,
+  P1: Projector<TyInput>,
+  P2: Projector<TyInput>,
+  M1: Mapper<P1, TyInput>,
+  M2: Mapper<P2, TyInput>,
+{
+  fn map(&mut self, input: TyInput) -> <PairProjector<P1, P2> as Projector<TyInput>>::Output {
+    (self.0.map(input.clone()), self.1.map(input))
+  }
+}
+
+impl<'a, TyInput, P1, P2, M1, M2> MapperRef<'a, PairProjector<P1, P2>, TyInput> for PairMapper<M1, M2>
+where
+  P1: Projector<TyInput>,
+  P2: Projector<TyInput>,
+  M1: MapperRef<'a, P1, TyInput>,
+  M2: MapperRef<'a, P2, TyInput>,
+{
+  fn map_ref(&mut self, input: &'a TyInput) -> <PairProjector<P1, P2> as Projector<TyInput>>::Output {
+    (self.0.map_ref(input), self.1.map_ref(input))
+  }
+}
+
+/// Implement the `StructuralMap` traits for types without any sub-component.
+macro_rules! impl_primitive {
+  ($($ty:ty),* $(,)?) => {
+    $(
+      impl<TyProjector, TyMapper> StructuralMap<TyProjector, TyMapper> for $ty {
+        fn structural_map(self, _mapper: &mut TyMapper) {}
+      }
+
+      impl<'a, TyProjector, TyMapper> StructuralMapRef<'a, TyProjector, TyMapper> for $ty {
+        fn structural_map_ref(&'a self, _mapper: &mut TyMapper) {}
+      }
+
+      impl<'a, TyProjector, TyMapper> StructuralMapMut<'a, TyProjector, TyMapper> for $ty {
+        fn structural_map_mut(&'a mut self, _mapper: &mut TyMapper) {}
+      }
+    )*
+  };
+}
+
+impl_primitive!(
+  (),
+  bool,
+  char,
+  u8,
+  u16,
+  u32,
+  u64,
+  u128,
+  usize,
+  i8,
+  i16,
+  i32,
+  i64,
+  i128,
+  isize,
+  f32,
+  f64,
+  core::time::Duration,
+  &str,
+);
+
+#[cfg(feature = "alloc")]
+impl_primitive!(String, Box<str>);
+
+#[cfg(feature = "std")]
+impl_primitive!(std::path::PathBuf);
+
+impl<TyProjector, TyMapper, T> StructuralMap<TyProjector, TyMapper> for Option<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: Mapper<TyProjector, T>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    self.map(|value| mapper.map(value))
+  }
+}
+
+impl<'a, TyProjector, TyMapper, T> StructuralMapRef<'a, TyProjector, TyMapper> for Option<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperRef<'a, TyProjector, T>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    self.as_ref().map(|value| mapper.map_ref(value))
+  }
+}
+
+impl<'a, TyProjector, TyMapper, T> StructuralMapMut<'a, TyProjector, TyMapper> for Option<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperMut<'a, TyProjector, T>,
+{
+  fn structural_map_mut(&'a mut self, mapper: &mut TyMapper) -> Self::Projection {
+    self.as_mut().map(|value| mapper.map_mut(value))
+  }
+}
+
+impl<TyProjector, TyMapper, T, E> StructuralMap<TyProjector, TyMapper> for Result<T, E>
+where
+  TyProjector: Projector<T> + Projector<E>,
+  TyMapper: Mapper<TyProjector, T> + Mapper<TyProjector, E>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    match self {
+      Ok(value) => Ok(<TyMapper as Mapper<TyProjector, T>>::map(mapper, value)),
+      Err(error) => Err(<TyMapper as Mapper<TyProjector, E>>::map(mapper, error)),
+    }
+  }
+}
+
+impl<'a, TyProjector, TyMapper, T, E> StructuralMapRef<'a, TyProjector, TyMapper> for Result<T, E>
+where
+  TyProjector: Projector<T> + Projector<E>,
+  TyMapper: MapperRef<'a, TyProjector, T> + MapperRef<'a, TyProjector, E>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    match self {
+      Ok(value) => Ok(<TyMapper as MapperRef<'a, TyProjector, T>>::map_ref(mapper, value)),
+      Err(error) => Err(<TyMapper as MapperRef<'a, TyProjector, E>>::map_ref(mapper, error)),
+    }
+  }
+}
+
+impl<'a, TyProjector, TyMapper, T, E> StructuralMapMut<'a, TyProjector, TyMapper> for Result<T, E>
+where
+  TyProjector: Projector<T> + Projector<E>,
+  TyMapper: MapperMut<'a, TyProjector, T> + MapperMut<'a, TyProjector, E>,
+{
+  fn structural_map_mut(&'a mut self, mapper: &mut TyMapper) -> Self::Projection {
+    match self {
+      Ok(value) => Ok(<TyMapper as MapperMut<'a, TyProjector, T>>::map_mut(mapper, value)),
+      Err(error) => Err(<TyMapper as MapperMut<'a, TyProjector, E>>::map_mut(mapper, error)),
+    }
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<TyProjector, TyMapper, T> StructuralMap<TyProjector, TyMapper> for Box<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: Mapper<TyProjector, T>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    Box::new(mapper.map(*self))
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, TyProjector, TyMapper, T> StructuralMapRef<'a, TyProjector, TyMapper> for Box<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperRef<'a, TyProjector, T>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    Box::new(mapper.map_ref(self))
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, TyProjector, TyMapper, T> StructuralMapMut<'a, TyProjector, TyMapper> for Box<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperMut<'a, TyProjector, T>,
+{
+  fn structural_map_mut(&'a mut self, mapper: &mut TyMapper) -> Self::Projection {
+    Box::new(mapper.map_mut(self))
+  }
+}
+
+impl<TyProjector, TyMapper, T, const N: usize> StructuralMap<TyProjector, TyMapper> for [T; N]
+where
+  TyProjector: Projector<T>,
+  TyMapper: Mapper<TyProjector, T>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    self.map(|item| mapper.map(item))
+  }
+}
+
+impl<'a, TyProjector, TyMapper, T, const N: usize> StructuralMapRef<'a, TyProjector, TyMapper> for [T; N]
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperRef<'a, TyProjector, T>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    self.each_ref().map(|item| mapper.map_ref(item))
+  }
+}
+
+impl<'a, TyProjector, TyMapper, T, const N: usize> StructuralMapMut<'a, TyProjector, TyMapper> for [T; N]
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperMut<'a, TyProjector, T>,
+{
+  fn structural_map_mut(&'a mut self, mapper: &mut TyMapper) -> Self::Projection {
+    self.each_mut().map(|item| mapper.map_mut(item))
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<TyProjector, TyMapper, T> StructuralMap<TyProjector, TyMapper> for Vec<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: Mapper<TyProjector, T>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    self.into_iter().map(|item| mapper.map(item)).collect()
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, TyProjector, TyMapper, T> StructuralMapRef<'a, TyProjector, TyMapper> for Vec<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperRef<'a, TyProjector, T>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    self.iter().map(|item| mapper.map_ref(item)).collect()
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, TyProjector, TyMapper, T> StructuralMapMut<'a, TyProjector, TyMapper> for Vec<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperMut<'a, TyProjector, T>,
+{
+  fn structural_map_mut(&'a mut self, mapper: &mut TyMapper) -> Self::Projection {
+    self.iter_mut().map(|item| mapper.map_mut(item)).collect()
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<TyProjector, TyMapper, T> StructuralMap<TyProjector, TyMapper> for VecDeque<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: Mapper<TyProjector, T>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    self.into_iter().map(|item| mapper.map(item)).collect()
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, TyProjector, TyMapper, T> StructuralMapRef<'a, TyProjector, TyMapper> for VecDeque<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperRef<'a, TyProjector, T>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    self.iter().map(|item| mapper.map_ref(item)).collect()
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, TyProjector, TyMapper, T> StructuralMapMut<'a, TyProjector, TyMapper> for VecDeque<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperMut<'a, TyProjector, T>,
+{
+  fn structural_map_mut(&'a mut self, mapper: &mut TyMapper) -> Self::Projection {
+    self.iter_mut().map(|item| mapper.map_mut(item)).collect()
+  }
+}
+
+/// The shared value is cloned if there are other references to it.
+#[cfg(feature = "alloc")]
+impl<TyProjector, TyMapper, T> StructuralMap<TyProjector, TyMapper> for Rc<T>
+where
+  T: Clone,
+  TyProjector: Projector<T>,
+  TyMapper: Mapper<TyProjector, T>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    Rc::new(mapper.map(Rc::unwrap_or_clone(self)))
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, TyProjector, TyMapper, T> StructuralMapRef<'a, TyProjector, TyMapper> for Rc<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperRef<'a, TyProjector, T>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    Rc::new(mapper.map_ref(self))
+  }
+}
+
+/// The shared value is cloned if there are other references to it.
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+impl<TyProjector, TyMapper, T> StructuralMap<TyProjector, TyMapper> for Arc<T>
+where
+  T: Clone,
+  TyProjector: Projector<T>,
+  TyMapper: Mapper<TyProjector, T>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    Arc::new(mapper.map(Arc::unwrap_or_clone(self)))
+  }
+}
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+impl<'a, TyProjector, TyMapper, T> StructuralMapRef<'a, TyProjector, TyMapper> for Arc<T>
+where
+  TyProjector: Projector<T>,
+  TyMapper: MapperRef<'a, TyProjector, T>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    Arc::new(mapper.map_ref(self))
+  }
+}
+
+/// Keys are cloned to index the projection, and the key and value of each
+/// entry are both mapped into an [`Entry`](crate::Entry).
+///
+/// There is no exclusive reference flavor for maps and sets, since their keys
+/// can't be borrowed mutably.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use styp::{ConstMapper, ConstProjector, Entry, Project, StructuralMapRef};
+///
+/// let stock: HashMap<&str, u32> = HashMap::from([("krill", 500)]);
+/// let lines: Project<HashMap<&str, u32>, ConstProjector<usize>> = stock.structural_map_ref(&mut ConstMapper(3));
+/// assert_eq!(lines, HashMap::from([("krill", Entry::new(3, 3))]));
+/// ```
+#[cfg(feature = "std")]
+impl<TyProjector, TyMapper, K, V, S> StructuralMap<TyProjector, TyMapper> for HashMap<K, V, S>
+where
+  K: Clone + Eq + Hash,
+  S: BuildHasher + Default,
+  TyProjector: Projector<K> + Projector<V>,
+  TyMapper: Mapper<TyProjector, K> + Mapper<TyProjector, V>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    self
+      .into_iter()
+      .map(|(key, value)| {
+        let projected_key = <TyMapper as Mapper<TyProjector, K>>::map(mapper, key.clone());
+        let projected_value = <TyMapper as Mapper<TyProjector, V>>::map(mapper, value);
+        (key, Entry::new(projected_key, projected_value))
+      })
+      .collect()
+  }
+}
+
+#[cfg(feature = "std")]
+impl<'a, TyProjector, TyMapper, K, V, S> StructuralMapRef<'a, TyProjector, TyMapper> for HashMap<K, V, S>
+where
+  K: Clone + Eq + Hash,
+  S: BuildHasher + Default,
+  TyProjector: Projector<K> + Projector<V>,
+  TyMapper: MapperRef<'a, TyProjector, K> + MapperRef<'a, TyProjector, V>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    self
+      .iter()
+      .map(|(key, value)| {
+        let projected_key = <TyMapper as MapperRef<'a, TyProjector, K>>::map_ref(mapper, key);
+        let projected_value = <TyMapper as MapperRef<'a, TyProjector, V>>::map_ref(mapper, value);
+        (key.clone(), Entry::new(projected_key, projected_value))
+      })
+      .collect()
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<TyProjector, TyMapper, K, V> StructuralMap<TyProjector, TyMapper> for BTreeMap<K, V>
+where
+  K: Clone + Ord,
+  TyProjector: Projector<K> + Projector<V>,
+  TyMapper: Mapper<TyProjector, K> + Mapper<TyProjector, V>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    self
+      .into_iter()
+      .map(|(key, value)| {
+        let projected_key = <TyMapper as Mapper<TyProjector, K>>::map(mapper, key.clone());
+        let projected_value = <TyMapper as Mapper<TyProjector, V>>::map(mapper, value);
+        (key, Entry::new(projected_key, projected_value))
+      })
+      .collect()
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, TyProjector, TyMapper, K, V> StructuralMapRef<'a, TyProjector, TyMapper> for BTreeMap<K, V>
+where
+  K: Clone + Ord,
+  TyProjector: Projector<K> + Projector<V>,
+  TyMapper: MapperRef<'a, TyProjector, K> + MapperRef<'a, TyProjector, V>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    self
+      .iter()
+      .map(|(key, value)| {
+        let projected_key = <TyMapper as MapperRef<'a, TyProjector, K>>::map_ref(mapper, key);
+        let projected_value = <TyMapper as MapperRef<'a, TyProjector, V>>::map_ref(mapper, value);
+        (key.clone(), Entry::new(projected_key, projected_value))
+      })
+      .collect()
+  }
+}
+
+/// Elements are cloned to index the projection.
+#[cfg(feature = "std")]
+impl<TyProjector, TyMapper, T, S> StructuralMap<TyProjector, TyMapper> for HashSet<T, S>
+where
+  T: Clone + Eq + Hash,
+  S: BuildHasher + Default,
+  TyProjector: Projector<T>,
+  TyMapper: Mapper<TyProjector, T>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    self.into_iter().map(|item| (item.clone(), mapper.map(item))).collect()
+  }
+}
+
+#[cfg(feature = "std")]
+impl<'a, TyProjector, TyMapper, T, S> StructuralMapRef<'a, TyProjector, TyMapper> for HashSet<T, S>
+where
+  T: Clone + Eq + Hash,
+  S: BuildHasher + Default,
+  TyProjector: Projector<T>,
+  TyMapper: MapperRef<'a, TyProjector, T>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    self.iter().map(|item| (item.clone(), mapper.map_ref(item))).collect()
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<TyProjector, TyMapper, T> StructuralMap<TyProjector, TyMapper> for BTreeSet<T>
+where
+  T: Clone + Ord,
+  TyProjector: Projector<T>,
+  TyMapper: Mapper<TyProjector, T>,
+{
+  fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+    self.into_iter().map(|item| (item.clone(), mapper.map(item))).collect()
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, TyProjector, TyMapper, T> StructuralMapRef<'a, TyProjector, TyMapper> for BTreeSet<T>
+where
+  T: Clone + Ord,
+  TyProjector: Projector<T>,
+  TyMapper: MapperRef<'a, TyProjector, T>,
+{
+  fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+    self.iter().map(|item| (item.clone(), mapper.map_ref(item))).collect()
+  }
+}
+
+/// Implement the `StructuralMap` traits for tuples, mapping each component.
+macro_rules! impl_tuple {
+  ($($t:ident $index:tt),+) => {
+    impl<TyProjector, TyMapper, $($t),+> StructuralMap<TyProjector, TyMapper> for ($($t,)+)
+    where
+      TyProjector: $(Projector<$t> +)+,
+      TyMapper: $(Mapper<TyProjector, $t> +)+,
+    {
+      fn structural_map(self, mapper: &mut TyMapper) -> Self::Projection {
+        ($(<TyMapper as Mapper<TyProjector, $t>>::map(mapper, self.$index),)+)
+      }
+    }
+
+    impl<'a, TyProjector, TyMapper, $($t),+> StructuralMapRef<'a, TyProjector, TyMapper> for ($($t,)+)
+    where
+      TyProjector: $(Projector<$t> +)+,
+      TyMapper: $(MapperRef<'a, TyProjector, $t> +)+,
+    {
+      fn structural_map_ref(&'a self, mapper: &mut TyMapper) -> Self::Projection {
+        ($(<TyMapper as MapperRef<'a, TyProjector, $t>>::map_ref(mapper, &self.$index),)+)
+      }
+    }
+
+    impl<'a, TyProjector, TyMapper, $($t),+> StructuralMapMut<'a, TyProjector, TyMapper> for ($($t,)+)
+    where
+      TyProjector: $(Projector<$t> +)+,
+      TyMapper: $(MapperMut<'a, TyProjector, $t> +)+,
+    {
+      fn structural_map_mut(&'a mut self, mapper: &mut TyMapper) -> Self::Projection {
+        ($(<TyMapper as MapperMut<'a, TyProjector, $t>>::map_mut(mapper, &mut self.$index),)+)
+      }
+    }
+  };
+}
+
+impl_tuple!(T0 0);
+impl_tuple!(T0 0, T1 1);
+impl_tuple!(T0 0, T1 1, T2 2);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11);
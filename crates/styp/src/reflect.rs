@@ -0,0 +1,361 @@
+//! Reflection over the components of a value.
+//!
+//! The [`Reflect`] trait describes the components forming the structure of a
+//! type, following the same model as structural projection: named fields,
+//! tuple indexes, sequence elements and map entries. It also allows to walk a
+//! value recursively, yielding each nested component along with its
+//! [`FieldPath`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::collections::BTreeMap;
+//! use styp::{ComponentKind, Reflect};
+//! use styp_derive::StructuralProjection;
+//!
+//! #[derive(StructuralProjection)]
+//! struct Server {
+//!   pub ports: Vec<u16>,
+//!   pub env: BTreeMap<String, String>,
+//! }
+//!
+//! let kinds: Vec<ComponentKind> = Server::components().iter().map(|c| c.kind).collect();
+//! assert_eq!(kinds, [ComponentKind::Field("ports"), ComponentKind::Field("env")]);
+//!
+//! let server = Server {
+//!   ports: vec![80, 443],
+//!   env: BTreeMap::from([(String::from("HOME"), String::from("/root"))]),
+//! };
+//! let paths: Vec<String> = server.walk().iter().map(|(path, _)| path.to_string()).collect();
+//! assert_eq!(paths, ["ports", "ports[0]", "ports[1]", "env", "env[\"HOME\"]"]);
+//!
+//! let (_, value) = &server.walk()[2];
+//! assert_eq!(value.downcast_ref::<u16>(), Some(&443));
+//! ```
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// Kind of component in the structure of a type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ComponentKind {
+  /// Named field of a struct or enum variant.
+  Field(&'static str),
+  /// Unnamed field of a tuple, tuple struct or tuple variant.
+  Index(usize),
+  /// Element of a sequence, such as `Vec<T>`.
+  Element,
+  /// Entry of a map, such as `HashMap<K, V>`.
+  Entry,
+}
+
+/// Static description of a component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ComponentDescriptor {
+  /// Name of the enum variant containing the component, if any.
+  pub variant: Option<&'static str>,
+  pub kind: ComponentKind,
+}
+
+impl ComponentDescriptor {
+  pub const fn new(kind: ComponentKind) -> Self {
+    Self { variant: None, kind }
+  }
+}
+
+/// Segment of a [`FieldPath`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PathSegment {
+  /// Named field, displayed as `.name`.
+  Field(&'static str),
+  /// Tuple index, displayed as `.0`.
+  Index(usize),
+  /// Sequence element, displayed as `[3]`.
+  Element(usize),
+  /// Map entry, displayed as `["key"]`. The key is stored as its `Debug`
+  /// representation.
+  Key(String),
+}
+
+/// Path to a nested component, from the root value.
+///
+/// The path is displayed using Rust syntax, without the leading dot.
+///
+/// ```rust
+/// use styp::{FieldPath, PathSegment};
+///
+/// let path = FieldPath::from(vec![
+///   PathSegment::Field("a"),
+///   PathSegment::Field("b"),
+///   PathSegment::Element(3),
+///   PathSegment::Key(format!("{:?}", "key")),
+/// ]);
+/// assert_eq!(path.to_string(), r#"a.b[3]["key"]"#);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FieldPath {
+  segments: Vec<PathSegment>,
+}
+
+impl FieldPath {
+  /// Create an empty path, for the root value.
+  pub const fn new() -> Self {
+    Self { segments: Vec::new() }
+  }
+
+  pub fn segments(&self) -> &[PathSegment] {
+    &self.segments
+  }
+
+  pub fn push(&mut self, segment: PathSegment) {
+    self.segments.push(segment);
+  }
+
+  pub fn pop(&mut self) -> Option<PathSegment> {
+    self.segments.pop()
+  }
+}
+
+impl From<Vec<PathSegment>> for FieldPath {
+  fn from(segments: Vec<PathSegment>) -> Self {
+    Self { segments }
+  }
+}
+
+impl fmt::Display for FieldPath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (index, segment) in self.segments.iter().enumerate() {
+      let dot = if index == 0 { "" } else { "." };
+      match segment {
+        PathSegment::Field(name) => write!(f, "{dot}{name}")?,
+        PathSegment::Index(index) => write!(f, "{dot}{index}")?,
+        PathSegment::Element(index) => write!(f, "[{index}]")?,
+        PathSegment::Key(key) => write!(f, "[{key}]")?,
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Visitor receiving each nested component of a value along with its path.
+pub type Visitor<'v, 'a> = dyn FnMut(&FieldPath, &'a dyn Any) + 'v;
+
+/// Trait for types describing their components at runtime.
+///
+/// Primitives have no components. Smart pointers and `Option` are transparent:
+/// they expose the components of the value they contain.
+pub trait Reflect: Any {
+  /// Static description of the components of `Self`.
+  ///
+  /// For enums, this is the list of components of all the variants. Sequences
+  /// and maps describe a single component standing for all their elements.
+  fn components() -> &'static [ComponentDescriptor]
+  where
+    Self: Sized;
+
+  /// Call `visitor` for each nested component, recursively.
+  ///
+  /// Components are visited in pre-order: each component is visited before its
+  /// own sub-components. `path` is the path of `self`, it is restored before
+  /// returning.
+  fn visit_components<'a>(&'a self, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>);
+
+  /// Collect all the nested components along with their path.
+  fn walk(&self) -> Vec<(FieldPath, &dyn Any)> {
+    let mut components: Vec<(FieldPath, &dyn Any)> = Vec::new();
+    self.visit_components(&mut FieldPath::new(), &mut |path, value| {
+      components.push((path.clone(), value))
+    });
+    components
+  }
+}
+
+/// Visit `value` as the component `segment` of the value at `path`, then its
+/// nested components.
+pub fn visit<'a, T: Reflect>(value: &'a T, segment: PathSegment, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>) {
+  path.push(segment);
+  visitor(path, value);
+  value.visit_components(path, visitor);
+  path.pop();
+}
+
+/// Implement `Reflect` for types without any sub-component.
+macro_rules! impl_primitive {
+  ($($ty:ty),* $(,)?) => {
+    $(
+      impl Reflect for $ty {
+        fn components() -> &'static [ComponentDescriptor] {
+          &[]
+        }
+
+        fn visit_components<'a>(&'a self, _path: &mut FieldPath, _visitor: &mut Visitor<'_, 'a>) {}
+      }
+    )*
+  };
+}
+
+impl_primitive!(
+  (),
+  bool,
+  char,
+  u8,
+  u16,
+  u32,
+  u64,
+  u128,
+  usize,
+  i8,
+  i16,
+  i32,
+  i64,
+  i128,
+  isize,
+  f32,
+  f64,
+  String,
+  Box<str>,
+  &'static str,
+  core::time::Duration,
+);
+
+#[cfg(feature = "std")]
+impl_primitive!(std::path::PathBuf);
+
+/// Implement `Reflect` for wrappers exposing the components of their content.
+macro_rules! impl_transparent {
+  ($($ty:ident),* $(,)?) => {
+    $(
+      impl<T: Reflect> Reflect for $ty<T> {
+        fn components() -> &'static [ComponentDescriptor] {
+          T::components()
+        }
+
+        fn visit_components<'a>(&'a self, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>) {
+          T::visit_components(self, path, visitor)
+        }
+      }
+    )*
+  };
+}
+
+impl_transparent!(Box, Rc);
+#[cfg(target_has_atomic = "ptr")]
+impl_transparent!(Arc);
+
+impl<T: Reflect> Reflect for Option<T> {
+  fn components() -> &'static [ComponentDescriptor] {
+    T::components()
+  }
+
+  fn visit_components<'a>(&'a self, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>) {
+    if let Some(value) = self {
+      value.visit_components(path, visitor);
+    }
+  }
+}
+
+const ELEMENT: &[ComponentDescriptor] = &[ComponentDescriptor::new(ComponentKind::Element)];
+const ENTRY: &[ComponentDescriptor] = &[ComponentDescriptor::new(ComponentKind::Entry)];
+
+impl<T: Reflect, const N: usize> Reflect for [T; N] {
+  fn components() -> &'static [ComponentDescriptor] {
+    ELEMENT
+  }
+
+  fn visit_components<'a>(&'a self, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>) {
+    for (index, value) in self.iter().enumerate() {
+      visit(value, PathSegment::Element(index), path, visitor);
+    }
+  }
+}
+
+impl<T: Reflect> Reflect for Vec<T> {
+  fn components() -> &'static [ComponentDescriptor] {
+    ELEMENT
+  }
+
+  fn visit_components<'a>(&'a self, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>) {
+    for (index, value) in self.iter().enumerate() {
+      visit(value, PathSegment::Element(index), path, visitor);
+    }
+  }
+}
+
+impl<T: Reflect> Reflect for VecDeque<T> {
+  fn components() -> &'static [ComponentDescriptor] {
+    ELEMENT
+  }
+
+  fn visit_components<'a>(&'a self, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>) {
+    for (index, value) in self.iter().enumerate() {
+      visit(value, PathSegment::Element(index), path, visitor);
+    }
+  }
+}
+
+/// Map entries are visited through their value, the key is only used to build
+/// the path.
+#[cfg(feature = "std")]
+impl<K: fmt::Debug + 'static, V: Reflect, S: 'static> Reflect for HashMap<K, V, S> {
+  fn components() -> &'static [ComponentDescriptor] {
+    ENTRY
+  }
+
+  fn visit_components<'a>(&'a self, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>) {
+    for (key, value) in self {
+      visit(value, PathSegment::Key(format!("{key:?}")), path, visitor);
+    }
+  }
+}
+
+/// Map entries are visited through their value, the key is only used to build
+/// the path.
+impl<K: fmt::Debug + 'static, V: Reflect> Reflect for BTreeMap<K, V> {
+  fn components() -> &'static [ComponentDescriptor] {
+    ENTRY
+  }
+
+  fn visit_components<'a>(&'a self, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>) {
+    for (key, value) in self {
+      visit(value, PathSegment::Key(format!("{key:?}")), path, visitor);
+    }
+  }
+}
+
+/// Implement `Reflect` for tuples, with one component per element.
+macro_rules! impl_tuple {
+  ($($ty:ident $index:tt),+) => {
+    impl<$($ty: Reflect),+> Reflect for ($($ty,)+) {
+      fn components() -> &'static [ComponentDescriptor] {
+        &[$(ComponentDescriptor { variant: None, kind: ComponentKind::Index($index) }),+]
+      }
+
+      fn visit_components<'a>(&'a self, path: &mut FieldPath, visitor: &mut Visitor<'_, 'a>) {
+        $(visit(&self.$index, PathSegment::Index($index), path, visitor);)+
+      }
+    }
+  };
+}
+
+impl_tuple!(T0 0);
+impl_tuple!(T0 0, T1 1);
+impl_tuple!(T0 0, T1 1, T2 2);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11);
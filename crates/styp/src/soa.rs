@@ -0,0 +1,449 @@
+//! Structure of Arrays container built on structural projection.
+//!
+//! A [`SoaVec<T>`] stores a list of `T` values as one column per component:
+//! its storage is `Project<T, VecProjector>`. Rows are accessed through the
+//! `RefProjector` and `RefMutProjector` projections of `T`, and whole columns
+//! through its `SliceProjector` projection.
+//!
+//! The column operations are provided by the [`SoaLayout`] trait. It is
+//! implemented for tuples, and for structs with
+//! `#[derive(StructuralProjection)]`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use styp::SoaVec;
+//! use styp_derive::StructuralProjection;
+//!
+//! #[derive(Debug, PartialEq, StructuralProjection)]
+//! struct WeatherMeasurement {
+//!   pub temperature: i16,
+//!   pub pressure: u32,
+//! }
+//!
+//! let mut measures: SoaVec<WeatherMeasurement> = SoaVec::new();
+//! measures.push(WeatherMeasurement {
+//!   temperature: 23,
+//!   pressure: 101250,
+//! });
+//! measures.push(WeatherMeasurement {
+//!   temperature: 18,
+//!   pressure: 99800,
+//! });
+//!
+//! let first = measures.get(0).unwrap();
+//! assert_eq!(first.temperature, &23);
+//!
+//! let columns = measures.slices();
+//! assert_eq!(columns.pressure, &[101250, 99800]);
+//!
+//! measures.sort_by_key(|row| *row.temperature);
+//! let temperatures: Vec<i16> = measures.iter().map(|row| *row.temperature).collect();
+//! assert_eq!(temperatures, [18, 23]);
+//! ```
+
+use crate::{Project, RefMutProjector, RefProjector, SliceProjector, VecProjector};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::ops::Range;
+
+/// Column operations for storing values of type `Self` as a Structure of
+/// Arrays.
+///
+/// The associated types are projections of `Self`:
+/// - `Columns` is `Project<Self, VecProjector>`
+/// - `Ref<'a>` is `Project<Self, RefProjector<'a>>`
+/// - `Mut<'a>` is `Project<Self, RefMutProjector<'a>>`
+/// - `Slices<'a>` is `Project<Self, SliceProjector<'a>>`
+///
+/// The functions operate on columns of equal length, and panic if `index` is
+/// out of bounds. Use [`SoaVec`] for a safe interface maintaining these
+/// invariants.
+pub trait SoaLayout: Sized {
+  type Columns;
+  type Ref<'a>
+  where
+    Self: 'a;
+  type Mut<'a>
+  where
+    Self: 'a;
+  type Slices<'a>
+  where
+    Self: 'a;
+
+  /// Create empty columns.
+  fn columns_new() -> Self::Columns;
+
+  /// Append the components of `value` to the end of each column.
+  fn columns_push(columns: &mut Self::Columns, value: Self);
+
+  /// Borrow the components at `index`.
+  fn columns_get(columns: &Self::Columns, index: usize) -> Self::Ref<'_>;
+
+  /// Exclusively borrow the components at `index`.
+  fn columns_get_mut(columns: &mut Self::Columns, index: usize) -> Self::Mut<'_>;
+
+  /// Borrow each column as a slice.
+  fn columns_slices(columns: &Self::Columns) -> Self::Slices<'_>;
+
+  /// Remove the components at `index`, replacing them with the last ones.
+  fn columns_swap_remove(columns: &mut Self::Columns, index: usize) -> Self;
+
+  /// Swap the components at indexes `a` and `b`.
+  fn columns_swap(columns: &mut Self::Columns, a: usize, b: usize);
+}
+
+/// Growable list of `T` values, stored as one `Vec` per component.
+pub struct SoaVec<T: SoaLayout> {
+  columns: T::Columns,
+  len: usize,
+}
+
+impl<T: SoaLayout> SoaVec<T> {
+  /// Create an empty `SoaVec`.
+  pub fn new() -> Self {
+    Self {
+      columns: T::columns_new(),
+      len: 0,
+    }
+  }
+
+  pub const fn len(&self) -> usize {
+    self.len
+  }
+
+  pub const fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Borrow the underlying columns.
+  pub const fn columns(&self) -> &T::Columns {
+    &self.columns
+  }
+
+  /// Retrieve the underlying columns.
+  pub fn into_columns(self) -> T::Columns {
+    self.columns
+  }
+
+  /// Append a value at the end of the list.
+  pub fn push(&mut self, value: T) {
+    T::columns_push(&mut self.columns, value);
+    self.len += 1;
+  }
+
+  /// Borrow the value at `index`, or `None` if it is out of bounds.
+  pub fn get(&self, index: usize) -> Option<T::Ref<'_>> {
+    if index < self.len {
+      Some(T::columns_get(&self.columns, index))
+    } else {
+      None
+    }
+  }
+
+  /// Exclusively borrow the value at `index`, or `None` if it is out of bounds.
+  pub fn get_mut(&mut self, index: usize) -> Option<T::Mut<'_>> {
+    if index < self.len {
+      Some(T::columns_get_mut(&mut self.columns, index))
+    } else {
+      None
+    }
+  }
+
+  /// Borrow each column as a slice.
+  pub fn slices(&self) -> T::Slices<'_> {
+    T::columns_slices(&self.columns)
+  }
+
+  /// Remove the value at `index` and return it, replacing it with the last
+  /// value.
+  ///
+  /// This does not preserve ordering, but is `O(1)`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `index` is out of bounds.
+  pub fn swap_remove(&mut self, index: usize) -> T {
+    assert!(
+      index < self.len,
+      "swap_remove index (is {index}) should be < len (is {})",
+      self.len
+    );
+    self.len -= 1;
+    T::columns_swap_remove(&mut self.columns, index)
+  }
+
+  /// Swap the values at indexes `a` and `b`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `a` or `b` are out of bounds.
+  pub fn swap(&mut self, a: usize, b: usize) {
+    assert!(
+      a < self.len && b < self.len,
+      "swap indexes should be < len (is {})",
+      self.len
+    );
+    T::columns_swap(&mut self.columns, a, b);
+  }
+
+  /// Sort the values with a key extraction function.
+  ///
+  /// The sort is stable. Keys are computed once per value, then each column is
+  /// permuted in place.
+  pub fn sort_by_key<K, F>(&mut self, mut f: F)
+  where
+    K: Ord,
+    F: FnMut(T::Ref<'_>) -> K,
+  {
+    let keys: Vec<K> = self.iter().map(&mut f).collect();
+    let mut order: Vec<usize> = (0..self.len).collect();
+    order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+
+    // Apply the permutation cycle by cycle, so that the value at position `i`
+    // is moved from position `order[i]`.
+    let mut done = vec![false; self.len];
+    for start in 0..self.len {
+      let mut current = start;
+      while !done[current] {
+        done[current] = true;
+        let next = order[current];
+        if next == start {
+          break;
+        }
+        T::columns_swap(&mut self.columns, current, next);
+        current = next;
+      }
+    }
+  }
+
+  /// Iterate over borrowed values.
+  pub fn iter(&self) -> Iter<'_, T> {
+    Iter {
+      columns: &self.columns,
+      range: 0..self.len,
+    }
+  }
+}
+
+impl<T: SoaLayout> Default for SoaVec<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: SoaLayout> Clone for SoaVec<T>
+where
+  T::Columns: Clone,
+{
+  fn clone(&self) -> Self {
+    Self {
+      columns: self.columns.clone(),
+      len: self.len,
+    }
+  }
+}
+
+impl<T: SoaLayout> fmt::Debug for SoaVec<T>
+where
+  T::Columns: fmt::Debug,
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("SoaVec")
+      .field("columns", &self.columns)
+      .field("len", &self.len)
+      .finish()
+  }
+}
+
+impl<T: SoaLayout> Extend<T> for SoaVec<T> {
+  fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+    for value in iter {
+      self.push(value);
+    }
+  }
+}
+
+impl<T: SoaLayout> FromIterator<T> for SoaVec<T> {
+  fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+    let mut soa = Self::new();
+    soa.extend(iter);
+    soa
+  }
+}
+
+impl<'a, T: SoaLayout + 'a> IntoIterator for &'a SoaVec<T> {
+  type Item = T::Ref<'a>;
+  type IntoIter = Iter<'a, T>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+/// Iterator over the borrowed values of a [`SoaVec`].
+pub struct Iter<'a, T: SoaLayout> {
+  columns: &'a T::Columns,
+  range: Range<usize>,
+}
+
+impl<'a, T: SoaLayout + 'a> Iterator for Iter<'a, T> {
+  type Item = T::Ref<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.range.next().map(|index| T::columns_get(self.columns, index))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.range.size_hint()
+  }
+}
+
+impl<'a, T: SoaLayout + 'a> DoubleEndedIterator for Iter<'a, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.range.next_back().map(|index| T::columns_get(self.columns, index))
+  }
+}
+
+impl<'a, T: SoaLayout + 'a> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T: SoaLayout + 'a> FusedIterator for Iter<'a, T> {}
+
+/// Implement `SoaLayout` for tuples, with one column per element.
+macro_rules! impl_tuple {
+  ($($ty:ident $index:tt),+) => {
+    impl<$($ty),+> SoaLayout for ($($ty,)+) {
+      type Columns = Project<Self, VecProjector>;
+      type Ref<'a> = Project<Self, RefProjector<'a>> where Self: 'a;
+      type Mut<'a> = Project<Self, RefMutProjector<'a>> where Self: 'a;
+      type Slices<'a> = Project<Self, SliceProjector<'a>> where Self: 'a;
+
+      fn columns_new() -> Self::Columns {
+        ($(Vec::<$ty>::new(),)+)
+      }
+
+      fn columns_push(columns: &mut Self::Columns, value: Self) {
+        $(columns.$index.push(value.$index);)+
+      }
+
+      fn columns_get(columns: &Self::Columns, index: usize) -> Self::Ref<'_> {
+        ($(&columns.$index[index],)+)
+      }
+
+      fn columns_get_mut(columns: &mut Self::Columns, index: usize) -> Self::Mut<'_> {
+        ($(&mut columns.$index[index],)+)
+      }
+
+      fn columns_slices(columns: &Self::Columns) -> Self::Slices<'_> {
+        ($(columns.$index.as_slice(),)+)
+      }
+
+      fn columns_swap_remove(columns: &mut Self::Columns, index: usize) -> Self {
+        ($(columns.$index.swap_remove(index),)+)
+      }
+
+      fn columns_swap(columns: &mut Self::Columns, a: usize, b: usize) {
+        $(columns.$index.swap(a, b);)+
+      }
+    }
+  };
+}
+
+impl_tuple!(T0 0);
+impl_tuple!(T0 0, T1 1);
+impl_tuple!(T0 0, T1 1, T2 2);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sample() -> SoaVec<(u8, char)> {
+    [(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')].into_iter().collect()
+  }
+
+  fn rows(list: &SoaVec<(u8, char)>) -> Vec<(u8, char)> {
+    list.iter().map(|(number, letter)| (*number, *letter)).collect()
+  }
+
+  #[test]
+  fn get_mut() {
+    let mut list = sample();
+    let (number, letter) = list.get_mut(2).unwrap();
+    *number = 30;
+    *letter = 'C';
+
+    assert_eq!(list.get(2), Some((&30, &'C')));
+    assert!(list.get_mut(4).is_none());
+    assert_eq!(rows(&list), [(1, 'a'), (2, 'b'), (30, 'C'), (4, 'd')]);
+  }
+
+  #[test]
+  fn swap_remove() {
+    let mut list = sample();
+
+    assert_eq!(list.swap_remove(1), (2, 'b'));
+    assert_eq!(list.len(), 3);
+    assert_eq!(rows(&list), [(1, 'a'), (4, 'd'), (3, 'c')]);
+
+    assert_eq!(list.swap_remove(2), (3, 'c'));
+    assert_eq!(rows(&list), [(1, 'a'), (4, 'd')]);
+    assert_eq!(list.slices(), (&[1, 4][..], &['a', 'd'][..]));
+  }
+
+  #[test]
+  fn swap() {
+    let mut list = sample();
+    list.swap(0, 3);
+    list.swap(1, 1);
+
+    assert_eq!(rows(&list), [(4, 'd'), (2, 'b'), (3, 'c'), (1, 'a')]);
+  }
+
+  #[test]
+  #[should_panic(expected = "swap_remove index (is 4) should be < len (is 4)")]
+  fn swap_remove_out_of_bounds() {
+    sample().swap_remove(4);
+  }
+
+  #[test]
+  #[should_panic(expected = "swap indexes should be < len (is 4)")]
+  fn swap_out_of_bounds() {
+    sample().swap(1, 4);
+  }
+
+  #[test]
+  fn sort_by_key_cycle() {
+    // The sorted order is `[1, 3, 4, 2, 0]`: a single cycle over all the
+    // positions.
+    let mut list: SoaVec<(u8, char)> = [(4, 'e'), (0, 'a'), (3, 'd'), (1, 'b'), (2, 'c')].into_iter().collect();
+    list.sort_by_key(|(number, _)| *number);
+
+    assert_eq!(rows(&list), [(0, 'a'), (1, 'b'), (2, 'c'), (3, 'd'), (4, 'e')]);
+  }
+
+  #[test]
+  fn sort_by_key_stable() {
+    // The sorted order is `[1, 2, 5, 0, 4, 3]`: a cycle over five positions and
+    // a fixed point. Values with equal keys keep their relative order.
+    let mut list: SoaVec<(u8, char)> = [(2, 'x'), (0, 'a'), (1, 'b'), (3, 'z'), (2, 'y'), (1, 'c')]
+      .into_iter()
+      .collect();
+    list.sort_by_key(|(number, _)| *number);
+
+    assert_eq!(
+      rows(&list),
+      [(0, 'a'), (1, 'b'), (1, 'c'), (2, 'x'), (2, 'y'), (3, 'z')]
+    );
+  }
+}
@@ -0,0 +1,295 @@
+//! Pairing values with their projections.
+//!
+//! A value of type `T` and a companion value of type `Project<T, P>` share the
+//! same structure. [`StructuralZip`] combines them component by component into
+//! a single `Project<T, Zipped<P>>` value, where each component is a pair of
+//! the original component and its projection. Unzipping performs the inverse
+//! operation.
+//!
+//! Zipping is fallible when the structure depends on the value: collections
+//! must have the same length or keys, and enums must use the same variant.
+//!
+//! # Example
+//!
+//! ```rust
+//! use styp::{ConstProjector, Project, StructuralZip};
+//! use styp_derive::StructuralProjection;
+//!
+//! #[derive(Debug, PartialEq, StructuralProjection)]
+//! #[styp(attr(derive(Debug, PartialEq)))]
+//! struct Config {
+//!   pub host: String,
+//!   pub port: u16,
+//! }
+//!
+//! let config = Config {
+//!   host: String::from("localhost"),
+//!   port: 8080,
+//! };
+//! type Lines = ConstProjector<usize>;
+//! let lines: Project<Config, Lines> = ConfigStructure { host: 1, port: 2 };
+//!
+//! let zipped = StructuralZip::<Lines>::structural_zip(config, lines).unwrap();
+//! assert_eq!(zipped.port, (8080, 2));
+//!
+//! let (config, lines) = <Config as StructuralZip<Lines>>::structural_unzip(zipped);
+//! assert_eq!(config.host, "localhost");
+//! assert_eq!(lines, ConfigStructure { host: 1, port: 2 });
+//! ```
+
+#[cfg(feature = "alloc")]
+use crate::Entry;
+use crate::{IdentityProjector, PairProjector, Project, Projector, StructuralProjection};
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// Projector pairing each component with its projection through `P`.
+pub type Zipped<P> = PairProjector<IdentityProjector, P>;
+
+/// Error when the structure of a value does not match the structure of its
+/// projection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ZipError {
+  /// The collections have a different number of elements.
+  LengthMismatch { value: usize, projection: usize },
+  /// The maps do not have the same set of keys.
+  KeyMismatch,
+  /// The enums do not use the same variant.
+  VariantMismatch,
+}
+
+impl fmt::Display for ZipError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::LengthMismatch { value, projection } => write!(
+        f,
+        "length mismatch between value ({value}) and projection ({projection})"
+      ),
+      Self::KeyMismatch => f.write_str("key mismatch between value and projection"),
+      Self::VariantMismatch => f.write_str("variant mismatch between value and projection"),
+    }
+  }
+}
+
+impl core::error::Error for ZipError {}
+
+/// Combine a value with its projection through `TyProjector`, component by
+/// component.
+///
+/// The implementations are single-level: each component is paired with its
+/// projection, without recursing inside the component.
+///
+/// The projector can't be inferred from the projection type, so it usually
+/// has to be named explicitly: `StructuralZip::<P>::structural_zip(value, projection)`.
+pub trait StructuralZip<TyProjector>:
+  StructuralProjection<TyProjector> + StructuralProjection<Zipped<TyProjector>> + Sized
+{
+  /// Pair each component of `self` with the corresponding component of
+  /// `projection`.
+  fn structural_zip(
+    self,
+    projection: Project<Self, TyProjector>,
+  ) -> Result<Project<Self, Zipped<TyProjector>>, ZipError>;
+
+  /// Split a zipped value into the original value and its projection.
+  fn structural_unzip(zipped: Project<Self, Zipped<TyProjector>>) -> (Self, Project<Self, TyProjector>);
+}
+
+impl<TyProjector, T> StructuralZip<TyProjector> for Option<T>
+where
+  TyProjector: Projector<T>,
+{
+  fn structural_zip(
+    self,
+    projection: Option<<TyProjector as Projector<T>>::Output>,
+  ) -> Result<Option<(T, <TyProjector as Projector<T>>::Output)>, ZipError> {
+    match (self, projection) {
+      (Some(value), Some(projection)) => Ok(Some((value, projection))),
+      (None, None) => Ok(None),
+      _ => Err(ZipError::VariantMismatch),
+    }
+  }
+
+  fn structural_unzip(
+    zipped: Option<(T, <TyProjector as Projector<T>>::Output)>,
+  ) -> (Self, Option<<TyProjector as Projector<T>>::Output>) {
+    match zipped {
+      Some((value, projection)) => (Some(value), Some(projection)),
+      None => (None, None),
+    }
+  }
+}
+
+/// Arrays always have the same length as their projection, so zipping them
+/// never fails.
+///
+/// ```rust
+/// use styp::{ConstProjector, StructuralZip};
+///
+/// type Gain = ConstProjector<f32>;
+///
+/// let channels: [u16; 3] = [512, 1024, 0];
+/// let zipped = StructuralZip::<Gain>::structural_zip(channels, [0.5, 1.0, 0.0]).unwrap();
+/// assert_eq!(zipped, [(512, 0.5), (1024, 1.0), (0, 0.0)]);
+/// ```
+impl<TyProjector, T, const N: usize> StructuralZip<TyProjector> for [T; N]
+where
+  TyProjector: Projector<T>,
+{
+  fn structural_zip(
+    self,
+    projection: [<TyProjector as Projector<T>>::Output; N],
+  ) -> Result<[(T, <TyProjector as Projector<T>>::Output); N], ZipError> {
+    let mut projection = projection.into_iter();
+    Ok(self.map(|value| (value, projection.next().expect("arrays have the same length"))))
+  }
+
+  fn structural_unzip(
+    zipped: [(T, <TyProjector as Projector<T>>::Output); N],
+  ) -> (Self, [<TyProjector as Projector<T>>::Output; N]) {
+    let mut projection = [const { None }; N];
+    let mut index = 0;
+    let value = zipped.map(|(value, component_projection)| {
+      projection[index] = Some(component_projection);
+      index += 1;
+      value
+    });
+    let projection = projection.map(|component_projection| component_projection.expect("all elements are unzipped"));
+    (value, projection)
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<TyProjector, T> StructuralZip<TyProjector> for Vec<T>
+where
+  TyProjector: Projector<T>,
+{
+  fn structural_zip(
+    self,
+    projection: Vec<<TyProjector as Projector<T>>::Output>,
+  ) -> Result<Vec<(T, <TyProjector as Projector<T>>::Output)>, ZipError> {
+    if self.len() != projection.len() {
+      return Err(ZipError::LengthMismatch {
+        value: self.len(),
+        projection: projection.len(),
+      });
+    }
+    Ok(self.into_iter().zip(projection).collect())
+  }
+
+  fn structural_unzip(
+    zipped: Vec<(T, <TyProjector as Projector<T>>::Output)>,
+  ) -> (Self, Vec<<TyProjector as Projector<T>>::Output>) {
+    zipped.into_iter().unzip()
+  }
+}
+
+#[cfg(feature = "std")]
+impl<TyProjector, K, V, S> StructuralZip<TyProjector> for HashMap<K, V, S>
+where
+  TyProjector: Projector<K> + Projector<V>,
+  K: Clone + Eq + Hash,
+  S: BuildHasher + Default,
+{
+  fn structural_zip(
+    self,
+    mut projection: Project<Self, TyProjector>,
+  ) -> Result<Project<Self, Zipped<TyProjector>>, ZipError> {
+    if self.len() != projection.len() {
+      return Err(ZipError::KeyMismatch);
+    }
+    let mut zipped = HashMap::with_capacity_and_hasher(self.len(), S::default());
+    for (key, value) in self {
+      let entry = projection.remove(&key).ok_or(ZipError::KeyMismatch)?;
+      zipped.insert(key.clone(), Entry::new((key, entry.key), (value, entry.value)));
+    }
+    Ok(zipped)
+  }
+
+  fn structural_unzip(zipped: Project<Self, Zipped<TyProjector>>) -> (Self, Project<Self, TyProjector>) {
+    let mut value = HashMap::with_capacity_and_hasher(zipped.len(), S::default());
+    let mut projection = HashMap::with_capacity_and_hasher(zipped.len(), S::default());
+    for (key, entry) in zipped {
+      let (inner_key, key_projection) = entry.key;
+      let (inner_value, value_projection) = entry.value;
+      value.insert(inner_key, inner_value);
+      projection.insert(key, Entry::new(key_projection, value_projection));
+    }
+    (value, projection)
+  }
+}
+
+#[cfg(feature = "alloc")]
+impl<TyProjector, K, V> StructuralZip<TyProjector> for BTreeMap<K, V>
+where
+  TyProjector: Projector<K> + Projector<V>,
+  K: Clone + Ord,
+{
+  fn structural_zip(
+    self,
+    mut projection: Project<Self, TyProjector>,
+  ) -> Result<Project<Self, Zipped<TyProjector>>, ZipError> {
+    if self.len() != projection.len() {
+      return Err(ZipError::KeyMismatch);
+    }
+    let mut zipped = BTreeMap::new();
+    for (key, value) in self {
+      let entry = projection.remove(&key).ok_or(ZipError::KeyMismatch)?;
+      zipped.insert(key.clone(), Entry::new((key, entry.key), (value, entry.value)));
+    }
+    Ok(zipped)
+  }
+
+  fn structural_unzip(zipped: Project<Self, Zipped<TyProjector>>) -> (Self, Project<Self, TyProjector>) {
+    let mut value = BTreeMap::new();
+    let mut projection = BTreeMap::new();
+    for (key, entry) in zipped {
+      let (inner_key, key_projection) = entry.key;
+      let (inner_value, value_projection) = entry.value;
+      value.insert(inner_key, inner_value);
+      projection.insert(key, Entry::new(key_projection, value_projection));
+    }
+    (value, projection)
+  }
+}
+
+/// Implement `StructuralZip` for tuples, pairing each element.
+macro_rules! impl_tuple {
+  ($($ty:ident $index:tt),+) => {
+    impl<TyProjector, $($ty),+> StructuralZip<TyProjector> for ($($ty,)+)
+    where
+      TyProjector: $(Projector<$ty>+)+,
+    {
+      fn structural_zip(
+        self,
+        projection: Project<Self, TyProjector>,
+      ) -> Result<Project<Self, Zipped<TyProjector>>, ZipError> {
+        Ok(($((self.$index, projection.$index),)+))
+      }
+
+      fn structural_unzip(zipped: Project<Self, Zipped<TyProjector>>) -> (Self, Project<Self, TyProjector>) {
+        (($(zipped.$index.0,)+), ($(zipped.$index.1,)+))
+      }
+    }
+  };
+}
+
+impl_tuple!(T0 0);
+impl_tuple!(T0 0, T1 1);
+impl_tuple!(T0 0, T1 1, T2 2);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10);
+impl_tuple!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11);
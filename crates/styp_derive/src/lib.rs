@@ -0,0 +1,13 @@
+//! This crate provides derive macros for `styp` traits.
+
+use proc_macro::TokenStream;
+use syn::{DeriveInput, parse_macro_input};
+
+#[proc_macro_derive(StructuralProjection, attributes(styp))]
+pub fn derive_structural_projection(input: TokenStream) -> TokenStream {
+  let mut input = parse_macro_input!(input as DeriveInput);
+  let stream: proc_macro2::TokenStream =
+    rich_derive_impl::structural_projection::expand_derive_structural_projection(&mut input)
+      .unwrap_or_else(syn::Error::into_compile_error);
+  proc_macro::TokenStream::from(stream)
+}